@@ -0,0 +1,187 @@
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::encode::base64;
+use crate::fingerprint::{canonical_url, fnv1a};
+
+// Static assets of a page that are fetched and hashed.
+const MAX_ASSETS: usize = 10;
+
+// Parts of asset paths that give a framework or platform away.
+const FRAMEWORK_MARKERS: [(&str, &str); 12] = [
+    ("/_next/", "Next.js"),
+    ("/_nuxt/", "Nuxt"),
+    ("/wp-content/", "WordPress"),
+    ("/wp-includes/", "WordPress"),
+    ("/sites/default/files/", "Drupal"),
+    ("/media/jui/", "Joomla"),
+    ("/static/js/main.", "Create React App"),
+    ("/_app/immutable/", "SvelteKit"),
+    ("/build/_shared/", "Remix"),
+    ("/bundles/", "Symfony"),
+    ("/skin/frontend/", "Magento"),
+    ("/_astro/", "Astro"),
+];
+
+/// What the favicon and static assets of a host look like, to tell frameworks apart and to find
+/// hosts running the same thing.
+pub struct AssetPrint {
+    host: String,
+    // Shodan style favicon hash, searchable as http.favicon.hash.
+    favicon: Option<i32>,
+    frameworks: Vec<&'static str>,
+    // Hash over the hashes of every asset fetched, the same on hosts serving the same build.
+    assets: Option<(usize, u64)>,
+}
+
+// Fingerprints every host the targets point to once, and prints them with the groups of hosts
+// that look the same.
+pub async fn report(targets: &[String], delim: &str) {
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(10)).build().unwrap();
+    let mut hosts: Vec<reqwest::Url> = Vec::new();
+    for target in targets {
+        let Ok(url) = reqwest::Url::parse(&canonical_url(target, delim)) else { continue };
+        if !hosts.iter().any(|host| host.origin() == url.origin()) {
+            hosts.push(url);
+        }
+    }
+
+    let mut prints = Vec::new();
+    for url in hosts {
+        prints.push(AssetPrint::take(&client, &url).await);
+    }
+    for print in &prints {
+        print.print();
+    }
+    print_groups(&prints);
+}
+
+impl AssetPrint {
+    async fn take(client: &reqwest::Client, url: &reqwest::Url) -> AssetPrint {
+        let host = url.origin().ascii_serialization();
+        let page = fetch(client, url.as_str()).await.map(|bytes| String::from_utf8_lossy(&bytes).to_string());
+        let page = page.unwrap_or_default();
+
+        let favicon_url = link(&page, r#"(?i)<link[^>]*rel\s*=\s*["'][^"']*icon[^"']*["'][^>]*>"#)
+            .and_then(|href| url.join(&href).ok())
+            .or_else(|| url.join("/favicon.ico").ok());
+        let favicon = match favicon_url {
+            Some(favicon_url) => fetch(client, favicon_url.as_str()).await.map(|bytes| favicon_hash(&bytes)),
+            None => None,
+        };
+
+        let asset_urls: Vec<reqwest::Url> = asset_links(&page).filter_map(|link| url.join(&link).ok()).take(MAX_ASSETS).collect();
+        let mut frameworks: Vec<&'static str> = FRAMEWORK_MARKERS
+            .iter()
+            .filter(|(marker, _)| asset_urls.iter().any(|asset| asset.path().contains(marker)))
+            .map(|(_, framework)| *framework)
+            .collect();
+        frameworks.dedup();
+
+        let mut hashes = Vec::new();
+        for asset in &asset_urls {
+            if let Some(bytes) = fetch(client, asset.as_str()).await {
+                hashes.extend_from_slice(&fnv1a(&bytes).to_le_bytes());
+            }
+        }
+        let assets = if hashes.is_empty() { None } else { Some((hashes.len() / 8, fnv1a(&hashes))) };
+        AssetPrint { host, favicon, frameworks, assets }
+    }
+
+    fn print(&self) {
+        let mut parts = vec![format!("Assets of {}", self.host)];
+        match self.favicon {
+            Some(hash) => parts.push(format!("favicon hash {}", hash)),
+            None => parts.push(String::from("no favicon")),
+        }
+        if !self.frameworks.is_empty() {
+            parts.push(format!("looks like {}", self.frameworks.join(", ")));
+        }
+        if let Some((count, hash)) = self.assets {
+            parts.push(format!("{} static assets, fingerprint {:016x}", count, hash));
+        }
+        println!("{}", parts.join(". "));
+    }
+}
+
+// Hosts sharing a favicon or the exact same static assets most likely run the same application.
+fn print_groups(prints: &[AssetPrint]) {
+    let mut groups: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for print in prints {
+        if let Some(hash) = print.favicon {
+            groups.entry(format!("favicon hash {}", hash)).or_default().push(&print.host);
+        }
+        if let Some((_, hash)) = print.assets {
+            groups.entry(format!("asset fingerprint {:016x}", hash)).or_default().push(&print.host);
+        }
+    }
+    for (key, hosts) in groups {
+        if hosts.len() > 1 {
+            println!("Same {}: {}", key, hosts.join(", "));
+        }
+    }
+}
+
+async fn fetch(client: &reqwest::Client, url: &str) -> Option<Vec<u8>> {
+    let response = client.get(url).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response.bytes().await.ok().map(|bytes| bytes.to_vec())
+}
+
+// href of the first tag matching the pattern.
+fn link(page: &str, tag: &str) -> Option<String> {
+    static HREF: OnceLock<Regex> = OnceLock::new();
+    let href = HREF.get_or_init(|| Regex::new(r#"(?i)\bhref\s*=\s*["']([^"']+)["']"#).unwrap());
+    let tag = Regex::new(tag).unwrap().find(page)?;
+    Some(href.captures(tag.as_str())?[1].to_string())
+}
+
+// Scripts and stylesheets linked from the page, in order.
+fn asset_links(page: &str) -> impl Iterator<Item = String> + '_ {
+    static ASSET: OnceLock<Regex> = OnceLock::new();
+    let asset = ASSET.get_or_init(|| {
+        Regex::new(r#"(?i)<script[^>]*\bsrc\s*=\s*["']([^"']+)["']|<link[^>]*\bhref\s*=\s*["']([^"']+\.css[^"']*)["']"#).unwrap()
+    });
+    asset
+        .captures_iter(page)
+        .filter_map(|captures| captures.get(1).or_else(|| captures.get(2)).map(|link| link.as_str().to_string()))
+}
+
+// MurmurHash3 of the base64 encoded favicon, with a line break every 76 characters and at the
+// end, which is how Shodan computes it.
+fn favicon_hash(bytes: &[u8]) -> i32 {
+    let encoded = base64(bytes);
+    let mut text = String::new();
+    for line in encoded.as_bytes().chunks(76) {
+        text.push_str(std::str::from_utf8(line).unwrap());
+        text.push('\n');
+    }
+    murmur3_32(text.as_bytes(), 0) as i32
+}
+
+fn murmur3_32(bytes: &[u8], seed: u32) -> u32 {
+    const C1: u32 = 0xcc9e2d51;
+    const C2: u32 = 0x1b873593;
+    let mut hash = seed;
+    let mut chunks = bytes.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+        hash = (hash ^ k).rotate_left(13).wrapping_mul(5).wrapping_add(0xe6546b64);
+    }
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        let k = tail.iter().rev().fold(0u32, |k, byte| k << 8 | *byte as u32);
+        hash ^= k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+    }
+    hash ^= bytes.len() as u32;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85ebca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2ae35);
+    hash ^ (hash >> 16)
+}
@@ -0,0 +1,64 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use reqwest::header::HeaderMap;
+
+/// Shared budget of bytes sent and received per second, for --max-bandwidth. Every request and
+/// every piece of a response takes its size out of the budget, and whoever takes it below zero
+/// waits until the deficit has been made up.
+pub struct BandwidthLimiter {
+    // Bytes per second.
+    rate: f64,
+    // Bytes left and when they were last refilled. Up to a second worth of bytes builds up.
+    budget: Mutex<(f64, Instant)>,
+}
+
+impl BandwidthLimiter {
+    pub fn new(rate: f64) -> BandwidthLimiter {
+        BandwidthLimiter { rate, budget: Mutex::new((rate, Instant::now())) }
+    }
+
+    // Takes the bytes out of the budget, waiting when that leaves it overdrawn.
+    pub async fn consume(&self, bytes: usize) {
+        let wait = {
+            let mut budget = self.budget.lock().unwrap();
+            let now = Instant::now();
+            let (left, refilled) = *budget;
+            let left = (left + now.duration_since(refilled).as_secs_f64() * self.rate).min(self.rate) - bytes as f64;
+            *budget = (left, now);
+            if left < 0.0 {
+                Some(Duration::from_secs_f64(-left / self.rate))
+            } else {
+                None
+            }
+        };
+        if let Some(wait) = wait {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+// Parses "5MB/s", "500KB", "1.5M/s" or a plain amount of bytes into bytes per second. Units go
+// by 1024.
+pub fn parse(spec: &str) -> Result<f64, String> {
+    let amount = spec.trim().strip_suffix("/s").unwrap_or(spec.trim());
+    let split = amount.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(amount.len());
+    let (number, unit) = amount.split_at(split);
+    let number: f64 = number.parse().map_err(|_| format!("{} is not a bandwidth such as 5MB/s", spec))?;
+    let multiplier = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1.0,
+        "K" | "KB" => 1024.0,
+        "M" | "MB" => 1024.0 * 1024.0,
+        "G" | "GB" => 1024.0 * 1024.0 * 1024.0,
+        other => return Err(format!("unknown unit {}, use B, KB, MB or GB", other)),
+    };
+    if number <= 0.0 {
+        return Err(String::from("the bandwidth needs to be more than 0"));
+    }
+    Ok(number * multiplier)
+}
+
+// Size of the headers as they go over the wire, roughly.
+pub fn headers_size(headers: &HeaderMap) -> usize {
+    headers.iter().map(|(key, value)| key.as_str().len() + value.len() + 4).sum()
+}
@@ -0,0 +1,36 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io;
+
+use crate::output::ResultSink;
+
+// Word shown for the requests of a --benchmark run, which have no payload.
+pub const WORD: &str = "(benchmark)";
+
+/// How many requests of a --benchmark run got each status code, printed once the run is over in
+/// place of a line per request.
+#[derive(Default)]
+pub struct Statuses {
+    counts: BTreeMap<u64, usize>,
+}
+
+impl ResultSink for Statuses {
+    fn name(&self) -> String {
+        String::from("--benchmark")
+    }
+
+    fn write(&mut self, record: &Value) -> io::Result<()> {
+        if let Some(status) = record["status"].as_u64() {
+            *self.counts.entry(status).or_default() += 1;
+        }
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        if !self.counts.is_empty() {
+            let counts: Vec<String> = self.counts.iter().map(|(status, count)| format!("{}: {}", status, count)).collect();
+            println!("Status codes: {}", counts.join(", "));
+        }
+        Ok(())
+    }
+}
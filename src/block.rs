@@ -0,0 +1,139 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+use crate::challenge::BLOCK_STATUSES;
+use crate::results::RequestResult;
+
+// Amount of recent responses looked at when deciding whether the run got blocked.
+const WINDOW: usize = 20;
+// Share of blocked looking responses in the window that counts as being blocked.
+const BLOCKED_SHARE: f64 = 0.8;
+// Targets that already answered like this before the run got going aren't blocking the scan,
+// that's just how they answer.
+const BASELINE_SHARE: f64 = 0.5;
+
+// User agents cycled through with --rotate-user-agent.
+const USER_AGENTS: [&str; 5] = [
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 14_4) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Safari/605.1.15",
+    "Mozilla/5.0 (X11; Linux x86_64; rv:125.0) Gecko/20100101 Firefox/125.0",
+    "Mozilla/5.0 (iPhone; CPU iPhone OS 17_4 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Mobile/15E148 Safari/604.1",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:125.0) Gecko/20100101 Firefox/125.0",
+];
+
+/// Lets the collector hold every worker back while the run is paused. Each pause that ends bumps
+/// the rotation, which picks the next user agent and proxy when rotation was asked for.
+#[derive(Default)]
+pub struct PauseGate {
+    paused: watch::Sender<bool>,
+    rotation: AtomicUsize,
+}
+
+impl PauseGate {
+    // Returns straight away unless the run is paused, in which case it waits for it to resume.
+    pub async fn wait(&self) {
+        let mut paused = self.paused.subscribe();
+        paused.wait_for(|paused| !paused).await.unwrap();
+    }
+
+    pub fn rotation(&self) -> usize {
+        self.rotation.load(Ordering::Relaxed)
+    }
+
+    // User agent to send for the current rotation, None before the first block was detected.
+    pub fn user_agent(&self) -> Option<&'static str> {
+        match self.rotation() {
+            0 => None,
+            rotation => Some(USER_AGENTS[(rotation - 1) % USER_AGENTS.len()]),
+        }
+    }
+
+    // Proxy to use for the current rotation, None before the first block was detected.
+    pub fn proxy<'a>(&self, proxies: &'a [String]) -> Option<&'a str> {
+        match self.rotation() {
+            0 => None,
+            _ if proxies.is_empty() => None,
+            rotation => Some(&proxies[(rotation - 1) % proxies.len()]),
+        }
+    }
+}
+
+/// Watches the results as they come in and pauses the run when responses suddenly start looking
+/// like the client got blocked.
+pub struct BlockDetector {
+    gate: Arc<PauseGate>,
+    pause: Duration,
+    rotate: bool,
+    baseline: Vec<bool>,
+    recent: VecDeque<bool>,
+}
+
+impl BlockDetector {
+    pub fn new(gate: Arc<PauseGate>, pause: Duration, rotate: bool) -> BlockDetector {
+        BlockDetector { gate, pause, rotate, baseline: Vec::new(), recent: VecDeque::new() }
+    }
+
+    pub fn push(&mut self, result: &RequestResult) {
+        // Responses to requests sent before the last pause say nothing about the current state.
+        if *self.gate.paused.borrow() || result.rotation < self.gate.rotation() {
+            return;
+        }
+        let blocked = result.challenge.is_some() || BLOCK_STATUSES.contains(&result.status.as_u16());
+        if self.baseline.len() < WINDOW {
+            self.baseline.push(blocked);
+            return;
+        }
+
+        self.recent.push_back(blocked);
+        if self.recent.len() > WINDOW {
+            self.recent.pop_front();
+        }
+        if self.recent.len() == WINDOW && share(self.recent.iter()) >= BLOCKED_SHARE && share(self.baseline.iter()) < BASELINE_SHARE {
+            self.pause_run(result);
+        }
+    }
+
+    fn pause_run(&mut self, last: &RequestResult) {
+        let blocked = self.recent.iter().filter(|blocked| **blocked).count();
+        self.recent.clear();
+        let reason = match last.challenge {
+            Some(challenge) => challenge.to_string(),
+            None => format!("status {}", last.status),
+        };
+        eprintln!(
+            "The target seems to be blocking the scan ({} of the last {} responses look blocked, last one: {}). \
+            Pausing for {:.0?}.",
+            blocked,
+            WINDOW,
+            reason,
+            self.pause
+        );
+
+        self.gate.paused.send_replace(true);
+        let gate = Arc::clone(&self.gate);
+        let pause = self.pause;
+        let rotate = self.rotate;
+        tokio::spawn(async move {
+            tokio::time::sleep(pause).await;
+            gate.rotation.fetch_add(1, Ordering::Relaxed);
+            if rotate {
+                eprintln!("Resuming the scan with the next user agent or proxy.");
+            } else {
+                eprintln!("Resuming the scan.");
+            }
+            gate.paused.send_replace(false);
+        });
+    }
+}
+
+fn share<'a>(values: impl Iterator<Item = &'a bool>) -> f64 {
+    let (blocked, total) = values.fold((0, 0), |(blocked, total), value| (blocked + *value as usize, total + 1));
+    if total == 0 {
+        0.0
+    } else {
+        blocked as f64 / total as f64
+    }
+}
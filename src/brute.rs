@@ -0,0 +1,122 @@
+/// Every combination of the characters of a charset, from a shortest to a longest length, for
+/// "-l brute:CHARSET:MIN-MAX". Short tokens and PINs are brute forced without writing the
+/// wordlist first, the words are made one at a time as the run takes them.
+#[derive(Clone, Debug)]
+pub struct Brute {
+    charset: Vec<char>,
+    min: usize,
+    max: usize,
+}
+
+impl Brute {
+    // Parses "abc123:1-4", or "0123456789:4" for a single length. A character given twice is only
+    // used once.
+    pub fn parse(spec: &str) -> Result<Brute, String> {
+        let (charset, lengths) = spec.rsplit_once(':').ok_or("expected brute:CHARSET:MIN-MAX, such as brute:0123456789:1-4")?;
+        let mut unique = Vec::new();
+        for c in charset.chars() {
+            if !unique.contains(&c) {
+                unique.push(c);
+            }
+        }
+        if unique.is_empty() {
+            return Err(String::from("the charset is empty"));
+        }
+        let (min, max) = lengths.split_once('-').unwrap_or((lengths, lengths));
+        let min: usize = min.parse().map_err(|_| format!("invalid length \"{}\"", min))?;
+        let max: usize = max.parse().map_err(|_| format!("invalid length \"{}\"", max))?;
+        if min == 0 || min > max {
+            return Err(format!("invalid lengths {}-{}, lengths start at 1", min, max));
+        }
+        let brute = Brute { charset: unique, min, max };
+        if brute.count().is_none() {
+            return Err(format!("{} characters up to {} long are too many combinations", brute.charset.len(), max));
+        }
+        Ok(brute)
+    }
+
+    // How many words there are over all the lengths, None when it doesn't fit.
+    pub fn count(&self) -> Option<usize> {
+        (self.min..=self.max).try_fold(0usize, |total, length| {
+            let words = self.charset.len().checked_pow(u32::try_from(length).ok()?)?;
+            total.checked_add(words)
+        })
+    }
+
+    pub fn words(&self) -> BruteWords {
+        BruteWords { charset: self.charset.clone(), max: self.max, digits: Some(vec![0; self.min]) }
+    }
+}
+
+/// The words of a brute force, shortest first and in charset order within a length.
+pub struct BruteWords {
+    charset: Vec<char>,
+    max: usize,
+    // Position in the charset of every character of the next word, None once they're all out.
+    digits: Option<Vec<usize>>,
+}
+
+impl Iterator for BruteWords {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let digits = self.digits.as_mut()?;
+        let word = digits.iter().map(|digit| self.charset[*digit]).collect();
+
+        // Counts up from the last character, carrying over like an odometer. Past the last word
+        // of a length comes the first one of the next length.
+        let mut position = digits.len();
+        loop {
+            if position == 0 {
+                if digits.len() == self.max {
+                    self.digits = None;
+                } else {
+                    digits.fill(0);
+                    digits.push(0);
+                }
+                break;
+            }
+            position -= 1;
+            digits[position] += 1;
+            if digits[position] < self.charset.len() {
+                break;
+            }
+            digits[position] = 0;
+        }
+        Some(word)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(spec: &str) -> Vec<String> {
+        Brute::parse(spec).unwrap().words().collect()
+    }
+
+    #[test]
+    fn lengths() {
+        assert_eq!(words("ab:1-2"), ["a", "b", "aa", "ab", "ba", "bb"]);
+        assert_eq!(words("01:3"), ["000", "001", "010", "011", "100", "101", "110", "111"]);
+        assert_eq!(Brute::parse("0123456789:4").unwrap().count(), Some(10000));
+        assert_eq!(Brute::parse("abc:1-3").unwrap().count(), Some(3 + 9 + 27));
+    }
+
+    // The charset is everything before the last colon, colons included, and repeats are dropped.
+    #[test]
+    fn charset() {
+        assert_eq!(words("a:b:1"), ["a", ":", "b"]);
+        assert_eq!(words("abab:1"), ["a", "b"]);
+        assert_eq!(words("éü:1"), ["é", "ü"]);
+    }
+
+    #[test]
+    fn invalid_specs() {
+        for spec in ["abc", ":1-2", "abc:", "abc:x", "abc:1-x", "abc:0-2", "abc:3-2", "abc:-2"] {
+            assert!(Brute::parse(spec).is_err(), "{}", spec);
+        }
+        let error = Brute::parse("0123456789:1-30").unwrap_err();
+        assert!(error.contains("too many"), "{}", error);
+    }
+}
@@ -0,0 +1,117 @@
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::fingerprint::fnv1a;
+use crate::payloads::Payload;
+use crate::results::RequestResult;
+use crate::stats::RunStats;
+use crate::template::Template;
+use crate::{render_request, transport, Args};
+
+// Lengths of the random words sent to every target. Different lengths tell whether the word is
+// reflected in the response.
+const PROBE_LENGTHS: [usize; 3] = [8, 16, 24];
+
+/// What a response to a word the application doesn't know looks like. The length is left out when
+/// it changed with the word.
+#[derive(Debug, Clone, PartialEq)]
+struct Signature {
+    status: u16,
+    length: Option<usize>,
+    words: usize,
+    lines: usize,
+}
+
+/// The wildcard responses of every target, found with --auto-calibrate before the run. Results
+/// that look the same are filtered out.
+pub struct Calibration {
+    signatures: Vec<Option<Signature>>,
+}
+
+impl Calibration {
+    // Sends a few random words to every target and keeps what the answers have in common. Fails
+    // if the transport can't be set up.
+    pub async fn run(
+        args: &Arc<Args>,
+        targets: &[Template],
+        headers: &[(Template, Template)],
+        body: &Option<Template>,
+    ) -> Result<Calibration, String> {
+        let stats = Arc::new(RunStats::default());
+        let transport = transport::build(args, &stats, None)?;
+        let mut signatures = Vec::new();
+        for (index, target) in targets.iter().enumerate() {
+            let mut probes = Vec::new();
+            for (probe, length) in PROBE_LENGTHS.into_iter().enumerate() {
+                let payload = Payload::single(random_word(index * PROBE_LENGTHS.len() + probe, length));
+                let rendered = render_request(args, target, headers, body, Some(&payload));
+                match transport.send(&rendered).await {
+                    Ok(mut response) => {
+                        let text = String::from_utf8_lossy(&response.bytes().await.unwrap_or_default()).to_string();
+                        probes.push(Signature {
+                            status: response.status.as_u16(),
+                            length: Some(text.len()),
+                            words: text.split_whitespace().count(),
+                            lines: text.lines().count(),
+                        });
+                    }
+                    Err(error) => eprintln!("Could not calibrate {}: {}", target.text(), error),
+                }
+            }
+            let signature = common(&probes);
+            match &signature {
+                Some(signature) => println!("Calibrated {}: {}, filtering the responses that look the same", target.text(), signature),
+                None => println!("Calibrated {}: no wildcard response, nothing is filtered", target.text()),
+            }
+            signatures.push(signature);
+        }
+        Ok(Calibration { signatures })
+    }
+
+    pub fn is_wildcard(&self, result: &RequestResult) -> bool {
+        let Some(Some(signature)) = self.signatures.get(result.target) else { return false };
+        signature.status == result.status.as_u16()
+            && signature.length.is_none_or(|length| length == result.length)
+            && signature.words == result.words
+            && signature.lines == result.lines
+    }
+}
+
+impl std::fmt::Display for Signature {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "status {}", self.status)?;
+        if let Some(length) = self.length {
+            write!(f, ", {} bytes", length)?;
+        }
+        write!(f, ", {} words, {} lines", self.words, self.lines)
+    }
+}
+
+// The signature every probe shares. Probes differing by more than their length mean the target
+// has no single wildcard answer, nothing can safely be filtered then.
+fn common(probes: &[Signature]) -> Option<Signature> {
+    let first = probes.first()?;
+    if probes.len() < PROBE_LENGTHS.len() {
+        return None;
+    }
+    let same = |probe: &Signature| probe.status == first.status && probe.words == first.words && probe.lines == first.lines;
+    if !probes.iter().all(same) {
+        return None;
+    }
+    let length = probes.iter().all(|probe| probe.length == first.length).then_some(first.length).flatten();
+    Some(Signature { length, ..first.clone() })
+}
+
+// Letters and digits no application should know, different for every probe and every run.
+fn random_word(probe: usize, length: usize) -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    let mut word = String::new();
+    let mut round = 0;
+    while word.len() < length {
+        let hash = fnv1a(format!("{}-{}-{}-{}", nanos, std::process::id(), probe, round).as_bytes());
+        word.push_str(&format!("{:016x}", hash));
+        round += 1;
+    }
+    word.truncate(length);
+    word
+}
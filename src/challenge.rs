@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+
+/// Where a response carrying a challenge came from and what kind of challenge it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Challenge {
+    pub vendor: &'static str,
+    pub kind: &'static str,
+}
+
+impl std::fmt::Display for Challenge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.vendor, self.kind)
+    }
+}
+
+// Body fragments of the pages served instead of the application: (vendor, kind, fragment).
+const BODY_SIGNATURES: [(&str, &str, &str); 14] = [
+    ("cloudflare", "challenge", "cf-chl-"),
+    ("cloudflare", "challenge", "<title>Just a moment...</title>"),
+    ("cloudflare", "turnstile", "challenges.cloudflare.com/turnstile"),
+    ("cloudflare", "block", "Attention Required! | Cloudflare"),
+    ("akamai", "block", "Reference&#32;&#35;"),
+    ("akamai", "challenge", "/_sec/cp_challenge/"),
+    ("imperva", "block", "_Incapsula_Resource"),
+    ("datadome", "captcha", "geo.captcha-delivery.com"),
+    ("perimeterx", "captcha", "px-captcha"),
+    ("aws waf", "captcha", "awswaf.com"),
+    ("google", "captcha", "g-recaptcha"),
+    ("hcaptcha", "captcha", "h-captcha"),
+    ("generic", "cookie wall", "enable cookies"),
+    ("generic", "cookie wall", "cookies are disabled"),
+];
+
+// Cookies set by bot management products when they start treating a client as suspicious.
+const BOT_COOKIES: [(&str, &str); 6] = [
+    ("cloudflare", "__cf_bm"),
+    ("cloudflare", "cf_clearance"),
+    ("akamai", "_abck"),
+    ("akamai", "bm_sz"),
+    ("datadome", "datadome"),
+    ("imperva", "incap_ses_"),
+];
+
+// Statuses blocking layers answer with.
+pub const BLOCK_STATUSES: [u16; 3] = [403, 429, 503];
+
+// Tells whether a response is a challenge page, block page or cookie wall put in front of the
+// application, rather than an answer of the application itself.
+pub fn classify(status: reqwest::StatusCode, headers: &reqwest::header::HeaderMap, body: &str) -> Option<Challenge> {
+    // Cloudflare says so outright when it answered with a challenge.
+    if headers.get("cf-mitigated").is_some_and(|value| value == "challenge") {
+        return Some(Challenge { vendor: "cloudflare", kind: "challenge" });
+    }
+    if let Some((vendor, kind, _)) = BODY_SIGNATURES.iter().find(|(_, _, fragment)| body.contains(fragment)) {
+        return Some(Challenge { vendor, kind });
+    }
+
+    // A bot management cookie alone is set on regular pages too, only together with an error
+    // status does it mean the request got stopped.
+    if !BLOCK_STATUSES.contains(&status.as_u16()) {
+        return None;
+    }
+    let akamai_server = headers
+        .get(reqwest::header::SERVER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|server| server.starts_with("AkamaiGHost"));
+    if akamai_server {
+        return Some(Challenge { vendor: "akamai", kind: "block" });
+    }
+    headers
+        .get_all(reqwest::header::SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .find_map(|value| BOT_COOKIES.iter().find(|(_, cookie)| value.starts_with(cookie)))
+        .map(|(vendor, _)| Challenge { vendor, kind: "bot cookie" })
+}
+
+/// Counts the challenged responses of a run, to tell at the end how many payloads never
+/// reached the application.
+#[derive(Default)]
+pub struct ChallengeTally {
+    total: usize,
+    challenged: BTreeMap<Challenge, usize>,
+}
+
+impl ChallengeTally {
+    pub fn push(&mut self, challenge: Option<Challenge>) {
+        self.total += 1;
+        if let Some(challenge) = challenge {
+            *self.challenged.entry(challenge).or_default() += 1;
+        }
+    }
+
+    pub fn print_summary(&self) {
+        if self.challenged.is_empty() {
+            return;
+        }
+        let count: usize = self.challenged.values().sum();
+        let kinds: Vec<String> = self
+            .challenged
+            .iter()
+            .map(|(challenge, count)| format!("{}: {}", challenge, count))
+            .collect();
+        println!(
+            "Challenged: {} of {} payloads got a challenge or block page instead of a real response and were \
+            effectively untested ({}).",
+            count,
+            self.total,
+            kinds.join(", ")
+        );
+    }
+}
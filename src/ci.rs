@@ -0,0 +1,154 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+use crate::stats::RunStats;
+
+// Output limits in --ci mode, so that a noisy run can't blow up the job log.
+const MAX_RESULT_LINES: usize = 1000;
+const MAX_BODY_BYTES: usize = 2048;
+
+// Exit codes in --ci mode.
+pub const EXIT_CLEAN: i32 = 0;
+pub const EXIT_FINDINGS: i32 = 1;
+pub const EXIT_ERROR: i32 = 2;
+
+// Any panic ends the run with EXIT_ERROR and a JSON line saying why, instead of leaving a job
+// hanging on tasks that will never finish or exiting with the default 101.
+pub fn install_panic_hook() {
+    std::panic::set_hook(Box::new(|info| {
+        let message = match info.payload().downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => info.payload().downcast_ref::<String>().cloned().unwrap_or_default(),
+        };
+        eprintln!("{}", serde_json::json!({ "event": "error", "message": message }));
+        std::process::exit(EXIT_ERROR);
+    }));
+}
+
+// Prints a single line JSON progress heartbeat to stderr every interval until aborted.
+pub fn spawn_heartbeat(stats: Arc<RunStats>, total: usize, started: Instant, interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick fires straight away, before anything happened.
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            // Both counters move on their own, one can be read ahead of the other.
+            let (sent, in_flight) = (stats.requests_sent.load(Ordering::Relaxed), stats.in_flight());
+            eprintln!(
+                "{}",
+                serde_json::json!({
+                    "event": "progress",
+                    "completed": sent.saturating_sub(in_flight),
+                    "in_flight": in_flight,
+                    // null with --forever, which has no end.
+                    "total": (total != usize::MAX).then_some(total),
+                    "reported": stats.reported(),
+                    "elapsed_ms": started.elapsed().as_millis() as u64,
+                })
+            );
+        }
+    })
+}
+
+pub fn print_finished(
+    stats: &RunStats,
+    elapsed: Duration,
+    exit_code: i32,
+    stopped: bool,
+    tags: &[(String, String)],
+    identification: Option<&str>,
+) {
+    eprintln!(
+        "{}",
+        serde_json::json!({
+            "event": "finished",
+            "requests": stats.requests_sent.load(Ordering::Relaxed),
+            "reported": stats.reported(),
+            "failed": stats.failures.total(),
+            "suppressed": stats.reported().saturating_sub(MAX_RESULT_LINES),
+            "stopped": stopped,
+            "elapsed_ms": elapsed.as_millis() as u64,
+            "exit_code": exit_code,
+            "tags": crate::results::tags_json(tags),
+            "identification": identification,
+        })
+    );
+}
+
+// A run stopped before its end (by --max-errors or Ctrl-C) or where every request failed didn't
+// scan what it was asked to. It fails the job even if it reported something, a clean exit would
+// pass for a clean scan.
+pub fn exit_code(stats: &RunStats, stopped: bool) -> i32 {
+    if stopped || stats.all_failed() {
+        EXIT_ERROR
+    } else if stats.reported() > 0 {
+        EXIT_FINDINGS
+    } else {
+        EXIT_CLEAN
+    }
+}
+
+// Counts a reported result and tells whether it should still be printed. In --ci mode only the
+// first MAX_RESULT_LINES results are, the rest are only counted.
+pub fn count_result(stats: &RunStats, ci: bool) -> bool {
+    let count = stats.count_reported();
+    if ci && count == MAX_RESULT_LINES + 1 {
+        println!("Output limit of {} results reached, further results are only counted.", MAX_RESULT_LINES);
+    }
+    !ci || count <= MAX_RESULT_LINES
+}
+
+// Makes a response body safe for a job log: drops control characters (terminal escape sequences
+// included) and cuts it down to MAX_BODY_BYTES.
+pub fn sanitize_body(body: &str) -> String {
+    let mut clean: String = body
+        .chars()
+        .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
+        .collect();
+    if clean.len() > MAX_BODY_BYTES {
+        let mut end = MAX_BODY_BYTES;
+        while !clean.is_char_boundary(end) {
+            end -= 1;
+        }
+        let cut = clean.len() - end;
+        clean.truncate(end);
+        clean.push_str(&format!("\n[{} more bytes not shown]", cut));
+    }
+    clean
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::failures::Failure;
+
+    #[test]
+    fn exit_codes() {
+        let stats = Arc::new(RunStats::default());
+        assert_eq!(exit_code(&stats, false), EXIT_CLEAN);
+        let _request = stats.start_request();
+        let _request = stats.start_request();
+        stats.failures.push(Failure::Connect);
+        assert_eq!(exit_code(&stats, false), EXIT_CLEAN);
+        assert!(count_result(&stats, true));
+        assert_eq!(exit_code(&stats, false), EXIT_FINDINGS);
+        assert_eq!(exit_code(&stats, true), EXIT_ERROR);
+        stats.failures.push(Failure::Timeout);
+        assert_eq!(exit_code(&stats, false), EXIT_ERROR);
+    }
+
+    // Every run counts its own results, the library can run several in one process.
+    #[test]
+    fn results_are_counted_per_run() {
+        let (first, second) = (RunStats::default(), RunStats::default());
+        for _ in 0..MAX_RESULT_LINES {
+            assert!(count_result(&first, true));
+        }
+        assert!(!count_result(&first, true));
+        assert!(count_result(&second, true));
+        assert_eq!((first.reported(), second.reported()), (MAX_RESULT_LINES + 1, 1));
+    }
+}
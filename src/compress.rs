@@ -0,0 +1,276 @@
+use clap::ValueEnum;
+use miniz_oxide::inflate::stream::{inflate, InflateState};
+use miniz_oxide::{DataFormat, MZError, MZFlush, MZStatus};
+use ruzstd::decoding::errors::{FrameDecoderError, ReadFrameHeaderError};
+use ruzstd::decoding::{BlockDecodingStrategy, FrameDecoder};
+use std::io::{self, BufRead, Read, Write};
+
+// Flags of the gzip header (RFC 1952) telling which optional fields follow it.
+const GZIP_HEADER_CRC: u8 = 0x02;
+const GZIP_EXTRA: u8 = 0x04;
+const GZIP_NAME: u8 = 0x08;
+const GZIP_COMMENT: u8 = 0x10;
+// Bytes before the deflate stream starts: the fixed gzip header, and the zlib header.
+const GZIP_HEADER_LEN: usize = 10;
+const ZLIB_HEADER_LEN: usize = 2;
+// Bits 1 and 2 of the first byte of a deflate stream, the type of its first block. Both set is
+// the reserved type 3.
+const DEFLATE_BLOCK_TYPE: u8 = 0b110;
+// Brotli quality and window, the middle ground the brotli program also picks for streaming.
+const BROTLI_QUALITY: u32 = 5;
+const BROTLI_WINDOW: u32 = 22;
+
+/// Content encodings the request body can be sent in.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BodyCompression {
+    Gzip,
+    /// zlib wrapped deflate, which is what Content-Encoding: deflate means.
+    Deflate,
+    Br,
+}
+
+impl BodyCompression {
+    // Value of the Content-Encoding header.
+    pub fn name(self) -> &'static str {
+        match self {
+            BodyCompression::Gzip => "gzip",
+            BodyCompression::Deflate => "deflate",
+            BodyCompression::Br => "br",
+        }
+    }
+
+    // Encodes the body. When corrupt is set the encoded body is broken so that it no longer
+    // decodes, to see how the server copes with that.
+    pub fn compress(self, body: &[u8], corrupt: bool) -> Vec<u8> {
+        let mut encoded = match self {
+            BodyCompression::Gzip => gzip(body),
+            BodyCompression::Deflate => miniz_oxide::deflate::compress_to_vec_zlib(body, 6),
+            BodyCompression::Br => brotli(body),
+        };
+        if corrupt {
+            self.corrupt(&mut encoded);
+        }
+        encoded
+    }
+
+    // The first deflate block asks for the reserved block type, which every inflater turns down
+    // before writing anything. A wrong checksum at the end would go unnoticed by the many that
+    // never look at it. Brotli is cut before the meta-block that ends the stream.
+    fn corrupt(self, encoded: &mut Vec<u8>) {
+        match self {
+            BodyCompression::Gzip => encoded[GZIP_HEADER_LEN] |= DEFLATE_BLOCK_TYPE,
+            BodyCompression::Deflate => encoded[ZLIB_HEADER_LEN] |= DEFLATE_BLOCK_TYPE,
+            BodyCompression::Br => encoded.truncate(encoded.len() / 2),
+        }
+    }
+}
+
+fn gzip(body: &[u8]) -> Vec<u8> {
+    // No name nor time, unknown OS.
+    let mut encoded = vec![0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff];
+    encoded.extend(miniz_oxide::deflate::compress_to_vec(body, 6));
+    encoded.extend(crc32(body).to_le_bytes());
+    encoded.extend((body.len() as u32).to_le_bytes());
+    encoded
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn brotli(body: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    let mut writer = brotli::CompressorWriter::new(&mut encoded, 4096, BROTLI_QUALITY, BROTLI_WINDOW);
+    // Writing to memory doesn't fail.
+    let _ = writer.write_all(body);
+    drop(writer);
+    encoded
+}
+
+/// Decompresses a gzip stream as it is read, so that a large compressed wordlist never has to be
+/// unpacked. Concatenated members, as pigz and "cat a.gz b.gz" make, are read one after the
+/// other. The checksums aren't verified.
+pub struct GzipDecoder<R: BufRead> {
+    inner: R,
+    state: Box<InflateState>,
+    in_member: bool,
+}
+
+impl<R: BufRead> GzipDecoder<R> {
+    pub fn new(inner: R) -> GzipDecoder<R> {
+        GzipDecoder { inner, state: InflateState::new_boxed(DataFormat::Raw), in_member: false }
+    }
+
+    fn read_header(&mut self) -> io::Result<()> {
+        let mut header = [0; 10];
+        self.inner.read_exact(&mut header)?;
+        if header[..3] != [0x1f, 0x8b, 0x08] {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a gzip stream"));
+        }
+        let flags = header[3];
+        if flags & GZIP_EXTRA != 0 {
+            let mut length = [0; 2];
+            self.inner.read_exact(&mut length)?;
+            io::copy(&mut (&mut self.inner).take(u16::from_le_bytes(length) as u64), &mut io::sink())?;
+        }
+        for flag in [GZIP_NAME, GZIP_COMMENT] {
+            if flags & flag != 0 {
+                self.inner.read_until(0, &mut Vec::new())?;
+            }
+        }
+        if flags & GZIP_HEADER_CRC != 0 {
+            self.inner.read_exact(&mut [0; 2])?;
+        }
+        self.state.reset(DataFormat::Raw);
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for GzipDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            if !self.in_member {
+                if self.inner.fill_buf()?.is_empty() {
+                    return Ok(0);
+                }
+                self.read_header()?;
+                self.in_member = true;
+            }
+            let input = self.inner.fill_buf()?;
+            let ended = input.is_empty();
+            let result = inflate(&mut self.state, input, buf, MZFlush::None);
+            self.inner.consume(result.bytes_consumed);
+            match result.status {
+                Ok(MZStatus::StreamEnd) => {
+                    // The CRC32 and size of the member.
+                    self.inner.read_exact(&mut [0; 8])?;
+                    self.in_member = false;
+                }
+                Ok(_) | Err(MZError::Buf) if result.bytes_written == 0 && ended => {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the gzip stream is cut short"));
+                }
+                Ok(_) | Err(MZError::Buf) => {}
+                Err(error) => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("invalid gzip stream: {:?}", error))),
+            }
+            if result.bytes_written > 0 {
+                return Ok(result.bytes_written);
+            }
+        }
+    }
+}
+
+/// Decompresses a zstd stream as it is read. Like the zstd program, it goes through every frame
+/// of it, as concatenated or multithreaded compression makes several.
+pub struct ZstdDecoder<R: BufRead> {
+    inner: R,
+    decoder: FrameDecoder,
+    in_frame: bool,
+}
+
+impl<R: BufRead> ZstdDecoder<R> {
+    pub fn new(inner: R) -> ZstdDecoder<R> {
+        ZstdDecoder { inner, decoder: FrameDecoder::new(), in_frame: false }
+    }
+}
+
+impl<R: BufRead> Read for ZstdDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let invalid = |error: FrameDecoderError| io::Error::new(io::ErrorKind::InvalidData, format!("invalid zstd stream: {}", error));
+        loop {
+            if !self.in_frame {
+                if self.inner.fill_buf()?.is_empty() {
+                    return Ok(0);
+                }
+                match self.decoder.reset(&mut self.inner) {
+                    Ok(()) => self.in_frame = true,
+                    // Skippable frames hold metadata rather than data, their header is read already.
+                    Err(FrameDecoderError::ReadFrameHeaderError(ReadFrameHeaderError::SkipFrame { length, .. })) => {
+                        io::copy(&mut (&mut self.inner).take(length as u64), &mut io::sink())?;
+                    }
+                    Err(error) => return Err(invalid(error)),
+                }
+                continue;
+            }
+            if self.decoder.can_collect() == 0 && !self.decoder.is_finished() {
+                self.decoder.decode_blocks(&mut self.inner, BlockDecodingStrategy::UptoBlocks(1)).map_err(invalid)?;
+                continue;
+            }
+            let read = self.decoder.read(buf)?;
+            if read > 0 {
+                return Ok(read);
+            }
+            self.in_frame = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &[u8] = b"{\"username\":\"admin\",\"password\":\"hunter2\"}";
+
+    fn gunzip(encoded: &[u8]) -> io::Result<Vec<u8>> {
+        let mut decoded = Vec::new();
+        GzipDecoder::new(encoded).read_to_end(&mut decoded)?;
+        Ok(decoded)
+    }
+
+    fn unbrotli(encoded: &[u8]) -> io::Result<Vec<u8>> {
+        let mut decoded = Vec::new();
+        brotli::Decompressor::new(encoded, 4096).read_to_end(&mut decoded)?;
+        Ok(decoded)
+    }
+
+    #[test]
+    fn every_encoding_decodes_to_the_body() {
+        assert_eq!(gunzip(&BodyCompression::Gzip.compress(BODY, false)).unwrap(), BODY);
+        assert_eq!(miniz_oxide::inflate::decompress_to_vec_zlib(&BodyCompression::Deflate.compress(BODY, false)).unwrap(), BODY);
+        assert_eq!(unbrotli(&BodyCompression::Br.compress(BODY, false)).unwrap(), BODY);
+    }
+
+    #[test]
+    fn zstd_decodes_every_frame() {
+        let mut encoded = ruzstd::encoding::compress_to_vec(&b"admin\nroot\n"[..], ruzstd::encoding::CompressionLevel::Fastest);
+        // A skippable frame between the two, as some tools add.
+        encoded.extend([0x50, 0x2a, 0x4d, 0x18, 3, 0, 0, 0, 1, 2, 3]);
+        encoded.extend(ruzstd::encoding::compress_to_vec(&b"guest\n"[..], ruzstd::encoding::CompressionLevel::Fastest));
+        let mut decoded = Vec::new();
+        ZstdDecoder::new(&encoded[..]).read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, b"admin\nroot\nguest\n");
+        let cut = &encoded[..encoded.len() / 3];
+        assert!(ZstdDecoder::new(cut).read_to_end(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn brotli_compresses() {
+        let body = BODY.repeat(100);
+        assert!(BodyCompression::Br.compress(&body, false).len() < body.len() / 10);
+    }
+
+    #[test]
+    fn corrupt_bodies_no_longer_decode() {
+        assert!(gunzip(&BodyCompression::Gzip.compress(BODY, true)).is_err());
+        assert!(miniz_oxide::inflate::decompress_to_vec(&BodyCompression::Gzip.compress(BODY, true)[GZIP_HEADER_LEN..]).is_err());
+        assert!(miniz_oxide::inflate::decompress_to_vec_zlib(&BodyCompression::Deflate.compress(BODY, true)).is_err());
+        assert!(unbrotli(&BodyCompression::Br.compress(BODY, true)).is_err());
+    }
+
+    #[test]
+    fn crc32_of_the_check_string() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+}
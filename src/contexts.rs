@@ -0,0 +1,134 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::rate::RateLimiter;
+use crate::template::Template;
+use crate::transport::RenderedRequest;
+use crate::Args;
+
+/// A credential context of --context: a name and the headers every request made in it carries,
+/// such as the session cookie of one user. Every payload is sent once per context, so that what
+/// each of them can reach shows up side by side.
+#[derive(Debug, Clone)]
+pub struct Context {
+    pub name: String,
+    // An empty value takes the header out of the request, for contexts without credentials.
+    headers: Vec<(String, String)>,
+}
+
+impl Context {
+    // Parses "NAME:OPTIONS", where OPTIONS are -H (or --header) options like those of the run,
+    // such as "admin:-H Cookie: session=A -H X-Role: admin". "anon:" has no options at all.
+    pub fn parse(spec: &str) -> Result<Context, String> {
+        let (name, options) = spec.split_once(':').ok_or("expected NAME:OPTIONS, such as admin:-H Cookie: session=A")?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(String::from("the context has no name"));
+        }
+        let mut headers = Vec::new();
+        let mut rest = options.trim();
+        while !rest.is_empty() {
+            let option = ["-H ", "--header ", "--header="].iter().find(|option| rest.starts_with(*option));
+            let Some(option) = option else {
+                return Err(format!("expected -H before \"{}\", contexts only take headers", rest));
+            };
+            rest = &rest[option.len()..];
+            let end = [" -H ", " --header ", " --header="].iter().filter_map(|option| rest.find(option)).min().unwrap_or(rest.len());
+            let header = rest[..end].trim();
+            let header = ['\'', '"'].iter().find_map(|quote| header.strip_prefix(*quote)?.strip_suffix(*quote)).unwrap_or(header);
+            let (key, value) = header.split_once(':').ok_or_else(|| format!("invalid header \"{}\", expected \"Name: value\"", header))?;
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+            rest = rest[end..].trim_start();
+        }
+        Ok(Context { name: name.to_string(), headers })
+    }
+
+    // Sets the headers of the context on the request, in place of any header of the same name
+    // the run already had.
+    pub fn apply(&self, request: &mut RenderedRequest) {
+        for (key, value) in &self.headers {
+            request.headers.retain(|(name, _)| !name.eq_ignore_ascii_case(key));
+            if !value.is_empty() {
+                request.headers.push((key.clone(), value.clone()));
+            }
+        }
+    }
+}
+
+// Repeats every target once per context, the contexts of a target next to each other. The context
+// of the target at an index is contexts[index % contexts.len()].
+pub fn expand(targets: Vec<Template>, contexts: &[Context]) -> Vec<Template> {
+    if contexts.is_empty() {
+        return targets;
+    }
+    targets.into_iter().flat_map(|target| std::iter::repeat_n(target, contexts.len())).collect()
+}
+
+// How every expanded target is told apart: its URL, followed by the name of its context.
+pub fn labels(targets: &[Template], contexts: &[Context]) -> Vec<String> {
+    targets
+        .iter()
+        .enumerate()
+        .map(|(index, target)| match contexts.get(index % contexts.len().max(1)) {
+            Some(context) => format!("{} ({})", target.text(), context.name),
+            None => target.text().to_string(),
+        })
+        .collect()
+}
+
+pub fn of(contexts: &[Context], target: usize) -> Option<&Context> {
+    contexts.get(target % contexts.len().max(1))
+}
+
+/// What every context of --context keeps to itself: its --rate budget and its --max-errors count,
+/// so that one context getting throttled or locked out neither slows down nor stops the others.
+/// Each worker also sends the requests of every context through a transport of its own.
+pub struct Lanes {
+    lanes: Vec<Lane>,
+}
+
+pub struct Lane {
+    pub rate_limiter: Option<RateLimiter>,
+    failures: AtomicUsize,
+    stopped: AtomicBool,
+}
+
+impl Lanes {
+    // Contexts coordinating their rate through a file each get their own file, the name of the
+    // context added to its path.
+    pub fn new(args: &Args, contexts: &[Context]) -> Result<Lanes, String> {
+        let lanes = contexts
+            .iter()
+            .map(|context| {
+                let rate_limiter = args.rate.map(|rate| {
+                    let coordination = args.rate_coordination.as_ref().map(|spec| format!("{}.{}", spec, context.name));
+                    RateLimiter::new(rate, coordination.as_deref())
+                });
+                Ok(Lane { rate_limiter: rate_limiter.transpose()?, failures: AtomicUsize::new(0), stopped: AtomicBool::new(false) })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Lanes { lanes })
+    }
+
+    pub fn of(&self, target: usize) -> Option<&Lane> {
+        self.lanes.get(target % self.lanes.len().max(1))
+    }
+
+    // Counts a failed request of the context of the target. The context stops once it reaches
+    // --max-errors, and the whole run once every context has.
+    pub fn fail(&self, target: usize, contexts: &[Context], max_errors: Option<usize>, stop: &AtomicBool) {
+        let (Some(lane), Some(context)) = (self.of(target), of(contexts, target)) else { return };
+        let failed = lane.failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if max_errors.is_some_and(|max| failed >= max) && !lane.stopped.swap(true, Ordering::Relaxed) {
+            eprintln!("Stopping context {} after {} failed requests.", context.name, failed);
+            if self.lanes.iter().all(Lane::stopped) && !stop.swap(true, Ordering::Relaxed) {
+                eprintln!("Stopping the run, every context stopped.");
+            }
+        }
+    }
+}
+
+impl Lane {
+    pub fn stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,53 @@
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::encrypt::{self, Encryption};
+use crate::payloads::Dispatch;
+use crate::redact::Redactor;
+use crate::template::Template;
+use crate::{render_request, Args};
+
+// Writes every request of the run as raw HTTP text to its own file instead of sending it, along
+// with an index.tsv telling which payload and URL each file was rendered for. The URLs were
+// checked by get_targets already.
+pub fn write_all(
+    args: &Args,
+    dir: &str,
+    dispatch: Dispatch,
+    targets: &[Template],
+    headers: &[(Template, Template)],
+    body: &Option<Template>,
+) -> io::Result<()> {
+    let dir = Path::new(dir);
+    fs::create_dir_all(dir)?;
+    let encryption = Encryption::from_args(args);
+    let redactor = Redactor::from_args(args);
+    let mut index = BufWriter::new(encrypt::create(dir.join("index.tsv"), encryption.as_ref())?);
+    writeln!(index, "file\tword\turl")?;
+
+    // Wide enough for every file name to have the same length, so they sort in order.
+    let width = dispatch.len().max(1).to_string().len();
+    let mut count = 0;
+    for (number, (target, payload)) in dispatch.enumerate() {
+        let fill = if args.fills_defaults() { None } else { Some(&payload) };
+        let rendered = render_request(args, &targets[target], headers, body, fill);
+        let http = rendered.to_http().map_err(|e| io::Error::other(format!("Invalid URL {}: {}", rendered.url, e)))?;
+
+        let name = format!("{:0width$}.http", number + 1, width = width);
+        let (http, word, url) = match &redactor {
+            Some(redactor) => (
+                redactor.http(&http),
+                redactor.text(&payload.label()),
+                redactor.text(&rendered.url),
+            ),
+            None => (http, payload.label(), rendered.url),
+        };
+        encrypt::write(dir.join(&name), http, encryption.as_ref())?;
+        writeln!(index, "{}\t{}\t{}", name, word, url)?;
+        count += 1;
+    }
+    index.into_inner().map_err(|e| e.into_error()).and_then(|mut index| index.finish())?;
+    println!("Rendered {} requests to {}", count, dir.display());
+    Ok(())
+}
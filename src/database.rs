@@ -0,0 +1,133 @@
+use serde_json::Value;
+use std::io::{self, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::fingerprint::fnv1a;
+use crate::output::ResultSink;
+use crate::redact::{self, Redactor};
+use crate::session::timestamp;
+
+// Results inserted per transaction. One transaction per result would spend the run waiting on
+// the disk.
+const BATCH_SIZE: usize = 500;
+
+const SCHEMA: &str = "\
+CREATE TABLE IF NOT EXISTS runs (id TEXT PRIMARY KEY, started TEXT NOT NULL, command TEXT NOT NULL, tags TEXT);
+CREATE TABLE IF NOT EXISTS results (
+    run TEXT NOT NULL REFERENCES runs(id),
+    url TEXT,
+    sent_url TEXT,
+    word TEXT,
+    status INTEGER,
+    length INTEGER,
+    words INTEGER,
+    lines INTEGER,
+    duration_ms INTEGER,
+    matched TEXT,
+    score REAL,
+    record TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS results_run ON results(run);
+CREATE INDEX IF NOT EXISTS results_word ON results(word);
+";
+
+/// The --output-db SQLite database. Every run adds a row to the runs table, and its reported
+/// results go to the results table with the main fields in columns and the whole record as JSON,
+/// so that huge runs can be queried afterwards and compared with each other. Written through the
+/// sqlite3 program, the same way encrypted files go through age or gpg.
+pub struct DbSink {
+    path: String,
+    child: Child,
+    stdin: ChildStdin,
+    run: String,
+    batch: String,
+    batched: usize,
+}
+
+impl DbSink {
+    pub fn open(path: &str, tags: &Value, redactor: Option<&Redactor>) -> io::Result<DbSink> {
+        let mut child = Command::new("sqlite3")
+            .arg("-bail")
+            .arg(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|e| io::Error::new(e.kind(), format!("Could not start sqlite3: {}", e)))?;
+        let stdin = child.stdin.take().ok_or_else(|| io::Error::other("sqlite3 has no input"))?;
+
+        let now = SystemTime::now();
+        let nanos = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+        let run = format!("{:016x}", fnv1a(format!("{}-{}", nanos, std::process::id()).as_bytes()));
+        let command = redact::stored_arguments(std::env::args().collect(), redactor);
+        let mut sink = DbSink { path: path.to_string(), child, stdin, run, batch: String::new(), batched: 0 };
+        sink.stdin.write_all(SCHEMA.as_bytes())?;
+        writeln!(
+            sink.stdin,
+            "INSERT INTO runs VALUES ({}, {}, {}, {});",
+            quote(&sink.run),
+            quote(&timestamp(now)),
+            quote(&command.join(" ")),
+            quote(&tags.to_string())
+        )?;
+        Ok(sink)
+    }
+
+    fn flush_batch(&mut self) -> io::Result<()> {
+        if self.batched == 0 {
+            return Ok(());
+        }
+        write!(self.stdin, "BEGIN;\n{}COMMIT;\n", self.batch)?;
+        self.stdin.flush()?;
+        self.batch.clear();
+        self.batched = 0;
+        Ok(())
+    }
+}
+
+impl ResultSink for DbSink {
+    fn name(&self) -> String {
+        self.path.clone()
+    }
+
+    fn write(&mut self, record: &Value) -> io::Result<()> {
+        let text = |field: &str| record[field].as_str().map_or_else(|| String::from("NULL"), quote);
+        let number = |field: &str| if record[field].is_number() { record[field].to_string() } else { String::from("NULL") };
+        self.batch.push_str(&format!(
+            "INSERT INTO results VALUES ({}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {});\n",
+            quote(&self.run),
+            text("url"),
+            text("sent_url"),
+            text("word"),
+            number("status"),
+            number("length"),
+            number("words"),
+            number("lines"),
+            number("duration_ms"),
+            text("matched"),
+            number("score"),
+            quote(&record.to_string())
+        ));
+        self.batched += 1;
+        if self.batched >= BATCH_SIZE {
+            self.flush_batch()?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.flush_batch()?;
+        drop(self.stdin);
+        let status = self.child.wait()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!("sqlite3 exited with {}", status)))
+        }
+    }
+}
+
+// A string as an SQL literal. sqlite3 reads the statements as text, so NUL can't be part of it.
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\0', "").replace('\'', "''"))
+}
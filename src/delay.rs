@@ -0,0 +1,91 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Wait before each request, fixed ("200ms") or picked at random in a range ("100-500ms").
+#[derive(Clone, Debug)]
+pub struct Delay {
+    min: Duration,
+    max: Duration,
+}
+
+impl Delay {
+    // Parses "200ms", "2s", "1.5s" or a range of those such as "100-500ms" or "1s-3s". The unit of
+    // the upper bound goes for the lower one when it has none. Plain numbers are milliseconds.
+    pub fn parse(spec: &str) -> Result<Delay, String> {
+        let (min, max) = match spec.split_once('-') {
+            Some((min, max)) => {
+                let max = parse_duration(max)?;
+                let mut min = parse_duration(min)?;
+                if min.1.is_empty() {
+                    min.1 = max.1;
+                }
+                (min, max)
+            }
+            None => {
+                let delay = parse_duration(spec)?;
+                (delay, delay)
+            }
+        };
+        let (min, max) = (to_duration(min)?, to_duration(max)?);
+        if min > max {
+            return Err(format!("the range {} goes backwards", spec));
+        }
+        Ok(Delay { min, max })
+    }
+
+    // The wait before the next request.
+    pub fn pick(&self) -> Duration {
+        if self.min == self.max {
+            return self.min;
+        }
+        let span = (self.max - self.min).as_nanos() as u64;
+        self.min + Duration::from_nanos(random() % (span + 1))
+    }
+}
+
+// Parses a single duration such as "10s" or "500ms", for --timeout and --connect-timeout. Plain
+// numbers are milliseconds, as with --delay.
+pub fn parse_timeout(spec: &str) -> Result<Duration, String> {
+    let timeout = to_duration(parse_duration(spec)?)?;
+    if timeout.is_zero() {
+        return Err(String::from("the timeout needs to be more than 0"));
+    }
+    Ok(timeout)
+}
+
+// Parses a duration such as "500ms" or "2s" that can be 0, plain numbers being milliseconds.
+pub fn parse_time(spec: &str) -> Result<Duration, String> {
+    to_duration(parse_duration(spec)?)
+}
+
+// Splits "500ms" into 500 and "ms".
+fn parse_duration(text: &str) -> Result<(f64, &str), String> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number = number.parse::<f64>().map_err(|_| format!("invalid delay \"{}\"", text))?;
+    match unit {
+        "" | "ms" | "s" | "m" => Ok((number, unit)),
+        _ => Err(format!("unknown unit \"{}\", use ms, s or m", unit)),
+    }
+}
+
+fn to_duration((number, unit): (f64, &str)) -> Result<Duration, String> {
+    let seconds = match unit {
+        "s" => number,
+        "m" => number * 60.0,
+        _ => number / 1000.0,
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|e| e.to_string())
+}
+
+// Good enough randomness for jitter without pulling in a crate: every RandomState is seeded
+// differently, a counter keeps successive values apart.
+pub fn random() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.finish()
+}
@@ -0,0 +1,241 @@
+use clap::ValueEnum;
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::task::JoinHandle;
+
+use crate::ci;
+use crate::encrypt::{self, Encryption};
+use crate::progress;
+use crate::results::RequestResult;
+use crate::Args;
+
+// How much of the word goes into the name of a dump file.
+const MAX_FILE_WORD: usize = 40;
+
+// Failed requests are dumped by the workers, numbered apart from the results.
+static FAILURES_DUMPED: AtomicUsize = AtomicUsize::new(0);
+
+/// The results that get dumped with --verbose-on, instead of all of them.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerboseOn {
+    /// Results where --match-string or --match-regex found something.
+    Match,
+    /// Requests that failed without a response.
+    Error,
+    /// Responses with a 5xx status code.
+    #[value(name = "5xx")]
+    ServerError,
+}
+
+/// The full exchange of each reported result with --verbose: the request as it was sent, then the
+/// response headers and body. Every dump is written in one go between two delimiter lines, so it
+/// can't be cut by anything else the run prints. With --verbose-dir each one goes to its own file
+/// instead. With --save-responses the response headers and body also go to a file named after
+/// the word, written in the background so the results keep coming. Both kinds of files go through
+/// --encrypt-output.
+pub struct Dumper {
+    show: bool,
+    only: Vec<VerboseOn>,
+    dir: Option<PathBuf>,
+    responses: Option<PathBuf>,
+    encryption: Option<Encryption>,
+    saved_names: HashSet<String>,
+    saves: Vec<JoinHandle<()>>,
+    ci: bool,
+    count: usize,
+}
+
+// Creates the directories of --verbose-dir and --save-responses, before the run starts.
+pub fn create_dirs(args: &Args) -> Result<(), String> {
+    for dir in args.verbose_dir.iter().chain(&args.save_responses) {
+        fs::create_dir_all(dir).map_err(|e| format!("Could not create {}: {}", dir, e))?;
+    }
+    Ok(())
+}
+
+impl Dumper {
+    // The directories were made by create_dirs.
+    pub fn from_args(args: &Args) -> Option<Dumper> {
+        if !args.dumps() {
+            return None;
+        }
+        let dir = args.verbose_dir.as_ref().map(PathBuf::from);
+        let responses = args.save_responses.as_ref().map(PathBuf::from);
+        Some(Dumper {
+            show: args.verbose || dir.is_some() || !args.verbose_on.is_empty(),
+            only: args.verbose_on.clone(),
+            dir,
+            responses,
+            encryption: Encryption::from_args(args),
+            saved_names: HashSet::new(),
+            saves: Vec::new(),
+            ci: args.ci,
+            count: 0,
+        })
+    }
+
+    pub fn write(&mut self, result: &RequestResult) {
+        self.count += 1;
+        if self.responses.is_some() {
+            self.save_response(result);
+        }
+        if !self.show || !self.wanted(result) {
+            return;
+        }
+        let dump = self.format(result);
+        match &self.dir {
+            Some(dir) => {
+                let path = dir.join(file_name(self.count, &result.word));
+                if let Err(e) = encrypt::write(&path, &dump, self.encryption.as_ref()) {
+                    eprintln!("Could not write {}: {}", path.display(), e);
+                }
+            }
+            None => {
+                progress::clear();
+                let mut stdout = std::io::stdout().lock();
+                let _ = stdout.write_all(dump.as_bytes());
+                let _ = stdout.flush();
+            }
+        }
+    }
+
+    // Every result is dumped unless --verbose-on says which ones.
+    fn wanted(&self, result: &RequestResult) -> bool {
+        self.only.is_empty()
+            || self.only.iter().any(|only| match only {
+                VerboseOn::Match => result.matched.is_some(),
+                VerboseOn::Error => false,
+                VerboseOn::ServerError => result.status.is_server_error(),
+            })
+    }
+
+    // Waits for the responses still being saved.
+    pub async fn finish(self) {
+        for save in self.saves {
+            let _ = save.await;
+        }
+    }
+
+    fn save_response(&mut self, result: &RequestResult) {
+        let Some(dir) = &self.responses else {
+            return;
+        };
+        // The same word can come back for several targets or attempts, later ones get a number.
+        let word = sanitize(&result.word);
+        let mut name = format!("{}.txt", word);
+        let mut copy = 1;
+        while !self.saved_names.insert(name.clone()) {
+            copy += 1;
+            name = format!("{}-{}.txt", word, copy);
+        }
+        let path = dir.join(name);
+        let contents = response_text(result);
+        let encryption = self.encryption.clone();
+        self.saves.retain(|save| !save.is_finished());
+        self.saves.push(tokio::task::spawn_blocking(move || {
+            if let Err(e) = encrypt::write(&path, contents, encryption.as_ref()) {
+                eprintln!("Could not write {}: {}", path.display(), e);
+            }
+        }));
+    }
+
+    fn format(&self, result: &RequestResult) -> String {
+        let mut dump = format!("===== #{} Word: {}. Status code: {} =====\n", self.count, result.word, result.status);
+        dump.push_str("----- Request -----\n");
+        match &result.request {
+            Some(request) => dump.push_str(&request.replace("\r\n", "\n")),
+            None => dump.push_str(&format!("{}\n", result.sent_url)),
+        }
+        if !dump.ends_with('\n') {
+            dump.push('\n');
+        }
+        dump.push_str("----- Response -----\n");
+        dump.push_str(&response_head(result));
+        if let Some(body) = &result.body {
+            if self.ci {
+                dump.push_str(&ci::sanitize_body(body));
+            } else {
+                dump.push_str(body);
+            }
+            if !body.ends_with('\n') {
+                dump.push('\n');
+            }
+        }
+        dump.push_str(&format!("===== End of #{} =====\n", self.count));
+        dump
+    }
+}
+
+// Dumps a request that got no response, for --verbose-on error. The workers call this themselves
+// as failures don't go through the collector.
+pub fn write_failure(args: &Args, word: &str, request: Option<&str>, error: &str) {
+    if !args.verbose_on.contains(&VerboseOn::Error) {
+        return;
+    }
+    let count = FAILURES_DUMPED.fetch_add(1, Ordering::Relaxed) + 1;
+    let mut dump = format!("===== Failed #{} Word: {} =====\n", count, word);
+    dump.push_str("----- Request -----\n");
+    if let Some(request) = request {
+        dump.push_str(&request.replace("\r\n", "\n"));
+        if !dump.ends_with('\n') {
+            dump.push('\n');
+        }
+    }
+    dump.push_str(&format!("----- Error -----\n{}\n", error));
+    dump.push_str(&format!("===== End of failed #{} =====\n", count));
+    match &args.verbose_dir {
+        Some(dir) => {
+            let path = PathBuf::from(dir).join(format!("failed-{}", file_name(count, word)));
+            let encryption = Encryption::from_args(args);
+            if let Err(e) = fs::create_dir_all(dir).and_then(|_| encrypt::write(&path, &dump, encryption.as_ref())) {
+                eprintln!("Could not write {}: {}", path.display(), e);
+            }
+        }
+        None => {
+            progress::clear();
+            let mut stdout = std::io::stdout().lock();
+            let _ = stdout.write_all(dump.as_bytes());
+            let _ = stdout.flush();
+        }
+    }
+}
+
+// The status line and headers of the response, then the blank line before the body.
+fn response_head(result: &RequestResult) -> String {
+    let mut head = format!("{}\n", result.status);
+    for (name, value) in &result.headers {
+        head.push_str(&format!("{}: {}\n", name, String::from_utf8_lossy(value.as_bytes())));
+    }
+    head.push('\n');
+    head
+}
+
+fn response_text(result: &RequestResult) -> String {
+    let mut text = response_head(result);
+    text.push_str(result.body.as_deref().unwrap_or_default());
+    text
+}
+
+// Numbered so the files sort in the order the results were reported, the word makes them easy to
+// find.
+fn file_name(count: usize, word: &str) -> String {
+    format!("{:05}-{}.txt", count, sanitize(word))
+}
+
+// Keeps the word safe as part of a file name: no separators, dots or anything a shell would mind.
+// An empty word still gives a name.
+fn sanitize(word: &str) -> String {
+    let word: String = word
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .take(MAX_FILE_WORD)
+        .collect();
+    if word.is_empty() {
+        String::from("_")
+    } else {
+        word
+    }
+}
@@ -0,0 +1,216 @@
+use clap::{CommandFactory, FromArgMatches};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::rpc::{self, Embedding};
+use crate::stats::RunStats;
+use crate::usage::UsageError;
+use crate::Args;
+
+/// The request sent for every word: method, URL, headers and body, with the words going between
+/// pairs of the delimiter.
+#[derive(Debug, Clone)]
+pub struct RequestTemplate {
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: Option<String>,
+    delimiter: String,
+}
+
+impl RequestTemplate {
+    pub fn new(url: impl Into<String>, delimiter: impl Into<String>) -> RequestTemplate {
+        RequestTemplate {
+            method: String::from("GET"),
+            url: url.into(),
+            headers: Vec::new(),
+            body: None,
+            delimiter: delimiter.into(),
+        }
+    }
+
+    pub fn method(mut self, method: impl Into<String>) -> RequestTemplate {
+        self.method = method.into();
+        self
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> RequestTemplate {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<String>) -> RequestTemplate {
+        self.body = Some(body.into());
+        self
+    }
+}
+
+/// Words to send, from a file or given directly. A named wordlist only feeds the placeholders
+/// with its name, an unnamed one feeds all the others.
+#[derive(Debug, Clone)]
+pub enum Wordlist {
+    File { path: PathBuf, name: Option<String> },
+    Words { words: Vec<String>, name: Option<String> },
+}
+
+impl Wordlist {
+    pub fn file(path: impl Into<PathBuf>) -> Wordlist {
+        Wordlist::File { path: path.into(), name: None }
+    }
+
+    pub fn words<I: IntoIterator<Item = S>, S: Into<String>>(words: I) -> Wordlist {
+        Wordlist::Words { words: words.into_iter().map(Into::into).collect(), name: None }
+    }
+
+    pub fn named(self, placeholder: impl Into<String>) -> Wordlist {
+        match self {
+            Wordlist::File { path, .. } => Wordlist::File { path, name: Some(placeholder.into()) },
+            Wordlist::Words { words, .. } => Wordlist::Words { words, name: Some(placeholder.into()) },
+        }
+    }
+}
+
+/// Everything a run needs: the request, its wordlists, and any other option of the command line
+/// given as it would be typed, such as "--concurrency" and "20".
+#[derive(Debug, Clone)]
+pub struct Job {
+    request: RequestTemplate,
+    wordlists: Vec<Wordlist>,
+    options: Vec<String>,
+}
+
+impl Job {
+    pub fn new(request: RequestTemplate) -> Job {
+        Job { request, wordlists: Vec::new(), options: Vec::new() }
+    }
+
+    pub fn wordlist(mut self, wordlist: Wordlist) -> Job {
+        self.wordlists.push(wordlist);
+        self
+    }
+
+    pub fn option(mut self, option: impl Into<String>) -> Job {
+        self.options.push(option.into());
+        self
+    }
+
+    pub fn options<I: IntoIterator<Item = S>, S: Into<String>>(mut self, options: I) -> Job {
+        self.options.extend(options.into_iter().map(Into::into));
+        self
+    }
+
+    // Turns the job into the arguments of a run, checked by clap like a command line.
+    fn into_args(self) -> Result<Args, UsageError> {
+        let request = self.request;
+        let mut argv = vec![
+            String::from("httprepeater"),
+            String::from("--method"),
+            request.method,
+            String::from("--url"),
+            request.url,
+            String::from("--delim"),
+            request.delimiter,
+        ];
+        for (name, value) in request.headers {
+            argv.extend([String::from("--header"), format!("{}: {}", name, value)]);
+        }
+        if let Some(body) = request.body {
+            argv.extend([String::from("--body"), body]);
+        }
+        let mut words = Vec::new();
+        for wordlist in self.wordlists {
+            match wordlist {
+                Wordlist::File { path, name } => {
+                    let path = path.to_string_lossy().to_string();
+                    let spec = name.map_or_else(|| path.clone(), |name| format!("{}:{}", path, name));
+                    argv.extend([String::from("--list"), spec]);
+                }
+                Wordlist::Words { words: list, name } => words.push((name, list)),
+            }
+        }
+        argv.extend(self.options);
+        parse_args(argv, words, Vec::new())
+    }
+}
+
+// Parses the options of a run started from the library or a pipeline. Wordlists and targets handed
+// over directly don't go through the command line, so --list and --url aren't required along with
+// them. (Clap can't drop a requirement, it is made to depend on -D, which is required anyway.)
+pub(crate) fn parse_args(argv: Vec<String>, words: Vec<(Option<String>, Vec<String>)>, urls: Vec<String>) -> Result<Args, UsageError> {
+    let mut command = Args::command();
+    if !words.is_empty() {
+        command = command.mut_arg("list", |arg| arg.required_unless_present("delim"));
+    }
+    if !urls.is_empty() {
+        command = command.mut_arg("url", |arg| arg.required_unless_present("delim"));
+    }
+    let matches = command.try_get_matches_from(argv).map_err(|e| UsageError::new(e.to_string().trim()))?;
+    let mut args = Args::from_arg_matches(&matches).map_err(|e| UsageError::new(e.to_string().trim()))?;
+    if args.command.is_some() || args.rpc.is_some() || args.ci {
+        return Err(UsageError::new("subcommands, --rpc and --ci can't be used in an embedded run"));
+    }
+    args.words = words;
+    args.urls = urls;
+    Ok(args)
+}
+
+/// A job going on in the background. Its results come through the Results it was started with.
+pub struct Runner {
+    handle: JoinHandle<Result<(), UsageError>>,
+    stop: Arc<AtomicBool>,
+    stats: Arc<RunStats>,
+}
+
+impl Runner {
+    // Starts the job on the current tokio runtime. The run prints what it would print on the
+    // command line, the same results come as records through Results.
+    pub fn start(job: Job) -> Result<(Runner, Results), UsageError> {
+        Ok(Runner::start_args(job.into_args()?))
+    }
+
+    pub(crate) fn start_args(args: Args) -> (Runner, Results) {
+        let args = Arc::new(args);
+        let stop = Arc::new(AtomicBool::new(false));
+        let stats = Arc::new(RunStats::default());
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let embedding = Embedding { results: sender, stop: Arc::clone(&stop), stats: Arc::clone(&stats) };
+        let handle = tokio::spawn(crate::run(args, Some(embedding)));
+        (Runner { handle, stop, stats }, Results(receiver))
+    }
+
+    // Stops taking new words, the requests in flight still finish.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    pub fn requests_sent(&self) -> usize {
+        self.stats.requests_sent.load(Ordering::Relaxed)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    // Waits for the end of the run. A run refused for its options, or that failed halfway, gives
+    // the reason why.
+    pub async fn wait(self) -> Result<(), UsageError> {
+        match self.handle.await {
+            Ok(outcome) => outcome,
+            Err(e) => Err(UsageError::new(rpc::failure_message(e))),
+        }
+    }
+}
+
+/// The results of a run as they come, as the same JSON records the outputs write.
+pub struct Results(mpsc::UnboundedReceiver<Value>);
+
+impl Results {
+    // The next result, None once the run is over.
+    pub async fn next(&mut self) -> Option<Value> {
+        self.0.recv().await
+    }
+}
@@ -0,0 +1,143 @@
+use base64::Engine;
+use clap::ValueEnum;
+use sha2::Digest;
+
+use crate::Args;
+
+/// A transformation of the words of the wordlists before they are put in the request, for
+/// --encode. Several are applied one after the other, in the order they were given.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transform {
+    /// Percent-encodes everything but letters, digits and -._~
+    #[value(name = "urlencode")]
+    UrlEncode,
+    /// Percent-encodes twice, '%' becoming %25.
+    #[value(name = "double-urlencode")]
+    DoubleUrlEncode,
+    Base64,
+    /// The bytes of the word as lowercase hexadecimal.
+    Hex,
+    Md5,
+    Sha1,
+    Sha256,
+    Lowercase,
+    Uppercase,
+}
+
+impl Transform {
+    fn apply(self, word: &str) -> String {
+        match self {
+            Transform::UrlEncode => url_encode(word),
+            Transform::DoubleUrlEncode => url_encode(&url_encode(word)),
+            Transform::Base64 => base64(word.as_bytes()),
+            Transform::Hex => hex(word.as_bytes()),
+            Transform::Md5 => hex(&md5::Md5::digest(word)),
+            Transform::Sha1 => hex(&sha1::Sha1::digest(word)),
+            Transform::Sha256 => hex(&sha2::Sha256::digest(word)),
+            Transform::Lowercase => word.to_lowercase(),
+            Transform::Uppercase => word.to_uppercase(),
+        }
+    }
+}
+
+/// What every word goes through before it is put in the request: --prefix and --suffix are added,
+/// then the --encode chain runs over the whole.
+#[derive(Debug, Default)]
+pub struct Encoding {
+    prefix: String,
+    suffix: String,
+    chain: Vec<Transform>,
+}
+
+impl Encoding {
+    pub fn from_args(args: &Args) -> Encoding {
+        Encoding {
+            prefix: args.prefix.clone().unwrap_or_default(),
+            suffix: args.suffix.clone().unwrap_or_default(),
+            chain: args.encode.clone(),
+        }
+    }
+
+    pub fn apply(&self, word: &str) -> String {
+        let word = format!("{}{}{}", self.prefix, word, self.suffix);
+        self.chain.iter().fold(word, |word, transform| transform.apply(&word))
+    }
+}
+
+fn url_encode(word: &str) -> String {
+    let mut encoded = String::new();
+    for byte in word.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+pub fn base64(bytes: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(transform: Transform, word: &str) -> String {
+        transform.apply(word)
+    }
+
+    // RFC 4648, section 10.
+    #[test]
+    fn base64_test_vectors() {
+        let vectors = [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"), ("foob", "Zm9vYg=="), ("fooba", "Zm9vYmE="), ("foobar", "Zm9vYmFy")];
+        for (word, encoded) in vectors {
+            assert_eq!(encode(Transform::Base64, word), encoded);
+        }
+    }
+
+    // RFC 1321, appendix A.5.
+    #[test]
+    fn md5_test_vectors() {
+        assert_eq!(encode(Transform::Md5, ""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(encode(Transform::Md5, "abc"), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(encode(Transform::Md5, "message digest"), "f96b697d7cb7938d525a2f31aaf161d0");
+    }
+
+    // RFC 3174, section 7.3.
+    #[test]
+    fn sha1_test_vectors() {
+        assert_eq!(encode(Transform::Sha1, "abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(
+            encode(Transform::Sha1, "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+        );
+    }
+
+    // FIPS 180-2, appendix B.
+    #[test]
+    fn sha256_test_vectors() {
+        assert_eq!(encode(Transform::Sha256, "abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(
+            encode(Transform::Sha256, "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    #[test]
+    fn url_encoding_leaves_unreserved_characters() {
+        assert_eq!(encode(Transform::UrlEncode, "a-b_c.d~e f/é"), "a-b_c.d~e%20f%2F%C3%A9");
+        assert_eq!(encode(Transform::DoubleUrlEncode, "a b"), "a%2520b");
+    }
+
+    #[test]
+    fn the_chain_runs_over_prefix_and_suffix_in_order() {
+        let encoding = Encoding { prefix: String::from("<"), suffix: String::from(">"), chain: vec![Transform::Hex, Transform::Uppercase] };
+        assert_eq!(encoding.apply("a"), "3C613E");
+    }
+}
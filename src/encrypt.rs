@@ -0,0 +1,141 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+
+use crate::Args;
+
+/// How files written by the run are encrypted, from --encrypt-output. The encryption itself is
+/// left to the age and gpg programs, which need to be on the PATH.
+#[derive(Clone, Debug)]
+pub enum Encryption {
+    // Path of an age recipients file.
+    Age(String),
+    // GPG key ID, fingerprint or user ID of the recipient.
+    Gpg(String),
+}
+
+impl Encryption {
+    pub fn parse(spec: &str) -> Result<Encryption, String> {
+        match spec.split_once(':') {
+            Some(("age", recipients)) if !recipients.is_empty() => Ok(Encryption::Age(recipients.to_string())),
+            Some(("gpg", recipient)) if !recipient.is_empty() => Ok(Encryption::Gpg(recipient.to_string())),
+            _ => Err(String::from("use age:RECIPIENTS_FILE or gpg:RECIPIENT")),
+        }
+    }
+
+    pub fn from_args(args: &Args) -> Option<Encryption> {
+        args.encrypt_output.clone()
+    }
+
+    pub fn program(&self) -> &str {
+        match self {
+            Encryption::Age(_) => "age",
+            Encryption::Gpg(_) => "gpg",
+        }
+    }
+
+    // Encrypted files are named after the program, as the programs themselves do.
+    fn extension(&self) -> &str {
+        self.program()
+    }
+
+    fn command(&self, output: &Path) -> Command {
+        let mut command = match self {
+            Encryption::Age(recipients) => {
+                let mut command = Command::new("age");
+                command.arg("--encrypt").arg("--recipients-file").arg(recipients);
+                command
+            }
+            Encryption::Gpg(recipient) => {
+                let mut command = Command::new("gpg");
+                command.args(["--batch", "--yes", "--trust-model", "always", "--encrypt", "--recipient"]).arg(recipient);
+                command
+            }
+        };
+        command.arg("--output").arg(output);
+        command
+    }
+}
+
+/// A file being written, in clear or through the encryption program. Encrypted files get the
+/// extension of the program added to their name.
+pub enum Sink {
+    Plain(File),
+    Encrypted(Child),
+}
+
+// Creates the file at path, encrypted when there is an encryption. Nothing is written in clear
+// on the disk in that case, the program encrypts the data as it gets it.
+pub fn create(path: impl AsRef<Path>, encryption: Option<&Encryption>) -> io::Result<Sink> {
+    let encryption = match encryption {
+        Some(encryption) => encryption,
+        None => return Ok(Sink::Plain(File::create(path)?)),
+    };
+    let mut output = PathBuf::from(path.as_ref()).into_os_string();
+    output.push(format!(".{}", encryption.extension()));
+    let child = encryption
+        .command(Path::new(&output))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not start {}: {}", encryption.program(), e)))?;
+    Ok(Sink::Encrypted(child))
+}
+
+// Same as fs::write, through the encryption when there is one.
+pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>, encryption: Option<&Encryption>) -> io::Result<()> {
+    let mut sink = create(path, encryption)?;
+    sink.write_all(contents.as_ref())?;
+    sink.finish()
+}
+
+impl Sink {
+    // Waits for the encryption program to be done with the file. Dropping the sink does the same
+    // but can't report a failure.
+    pub fn finish(&mut self) -> io::Result<()> {
+        match self {
+            Sink::Plain(file) => file.flush(),
+            Sink::Encrypted(child) => {
+                drop(child.stdin.take());
+                let status = child.wait()?;
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err(io::Error::other(format!("the encryption program exited with {}", status)))
+                }
+            }
+        }
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Sink::Plain(file) => file.write(buf),
+            Sink::Encrypted(child) => match child.stdin.as_mut() {
+                Some(stdin) => stdin.write(buf),
+                None => Err(io::Error::from(io::ErrorKind::BrokenPipe)),
+            },
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Sink::Plain(file) => file.flush(),
+            Sink::Encrypted(child) => child.stdin.as_mut().map_or(Ok(()), |stdin| stdin.flush()),
+        }
+    }
+}
+
+impl Drop for Sink {
+    fn drop(&mut self) {
+        if let Sink::Encrypted(child) = self {
+            if child.stdin.is_some() {
+                if let Err(e) = self.finish() {
+                    eprintln!("Could not finish writing an encrypted file: {}", e);
+                }
+            }
+        }
+    }
+}
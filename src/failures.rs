@@ -0,0 +1,115 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Why a request got no complete response, reported instead of a result.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Failure {
+    Dns,
+    Connect,
+    Tls,
+    Timeout,
+    // Anything else, such as a connection dropped halfway through the response.
+    Request,
+}
+
+const KINDS: [Failure; 5] = [Failure::Dns, Failure::Connect, Failure::Tls, Failure::Timeout, Failure::Request];
+
+impl Failure {
+    // Works out the kind of failure from the error and whatever caused it. The transports don't
+    // share error types, so some of it goes by the messages.
+    pub fn classify(error: &(dyn Error + 'static)) -> Failure {
+        if crate::transport::is_timeout(error) {
+            return Failure::Timeout;
+        }
+        let mut connect = false;
+        let mut source: Option<&(dyn Error + 'static)> = Some(error);
+        while let Some(current) = source {
+            let message = current.to_string().to_ascii_lowercase();
+            if message.contains("dns error") || message.contains("failed to lookup address") {
+                return Failure::Dns;
+            }
+            if current.is::<native_tls::Error>() || message.contains("certificate") || message.contains("handshake") {
+                return Failure::Tls;
+            }
+            connect |= current.downcast_ref::<reqwest::Error>().is_some_and(reqwest::Error::is_connect)
+                || current.downcast_ref::<io::Error>().is_some_and(|e| {
+                    matches!(e.kind(), io::ErrorKind::ConnectionRefused | io::ErrorKind::HostUnreachable | io::ErrorKind::NetworkUnreachable)
+                });
+            source = current.source();
+        }
+        if connect {
+            Failure::Connect
+        } else {
+            Failure::Request
+        }
+    }
+}
+
+// The error along with everything that caused it, the outermost errors of the HTTP clients rarely
+// say what actually went wrong. The URL reqwest adds is left out, it holds the whole word and
+// would get around --redact.
+pub fn describe(error: &(dyn Error + 'static)) -> String {
+    let mut messages: Vec<String> = Vec::new();
+    let mut source: Option<&(dyn Error + 'static)> = Some(error);
+    while let Some(current) = source {
+        let mut message = current.to_string();
+        if let Some(start) = message.find(" for url (") {
+            message.truncate(start);
+        }
+        if !messages.iter().any(|known| known.contains(&message)) {
+            messages.push(message);
+        }
+        source = current.source();
+    }
+    messages.join(": ")
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Failure::Dns => "DNS",
+            Failure::Connect => "connect",
+            Failure::Tls => "TLS",
+            Failure::Timeout => "timeout",
+            Failure::Request => "request",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Count of the failed requests of a run by kind.
+#[derive(Default)]
+pub struct FailureTally {
+    counts: [AtomicUsize; KINDS.len()],
+}
+
+impl FailureTally {
+    // Counts the failure and returns how many there have been in total.
+    pub fn push(&self, failure: Failure) -> usize {
+        let index = KINDS.iter().position(|kind| *kind == failure).unwrap_or_default();
+        self.counts[index].fetch_add(1, Ordering::Relaxed);
+        self.total()
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().map(|count| count.load(Ordering::Relaxed)).sum()
+    }
+
+    // "Failed: 3 (connect: 2, timeout: 1)", or None when nothing failed.
+    pub fn summary(&self) -> Option<String> {
+        let total = self.total();
+        if total == 0 {
+            return None;
+        }
+        let kinds: Vec<String> = KINDS
+            .iter()
+            .zip(&self.counts)
+            .map(|(kind, count)| (kind, count.load(Ordering::Relaxed)))
+            .filter(|(_, count)| *count > 0)
+            .map(|(kind, count)| format!("{}: {}", kind, count))
+            .collect();
+        Some(format!("Failed: {} ({})", total, kinds.join(", ")))
+    }
+}
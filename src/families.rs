@@ -0,0 +1,144 @@
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc::Receiver;
+use tokio::sync::Mutex;
+
+use crate::fingerprint::canonical_url;
+use crate::hosts::IpFamily;
+use crate::payloads::Payload;
+use crate::stats::RunStats;
+use crate::template::Template;
+use crate::transport::{self, Transport};
+use crate::{render_request, Args};
+
+// Payloads compared at once when --concurrency wasn't given, each of them being two requests.
+const DEFAULT_CONCURRENCY: usize = 50;
+
+/// What one IP family answered to a request, as much as is compared.
+#[derive(PartialEq)]
+enum Answer {
+    Response { status: u16, words: usize },
+    Failed(String),
+}
+
+impl std::fmt::Display for Answer {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Answer::Response { status, words } => write!(f, "status {}, {} words", status, words),
+            Answer::Failed(error) => write!(f, "failed: {}", error),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Tally {
+    compared: AtomicUsize,
+    diverged: AtomicUsize,
+}
+
+// Sends every payload to its target over IPv4 and over IPv6, and reports the payloads that got
+// different answers: ACLs and WAF rules are often only set up for one of the stacks. Targets whose
+// host only has addresses of one family are left out. Byte lengths are left out of the comparison,
+// the status and the word count already tell a different answer from a different timestamp. Fails
+// if the transports can't be set up.
+pub async fn compare(
+    args: &Arc<Args>,
+    targets: &Arc<Vec<Template>>,
+    headers: &Arc<Vec<(Template, Template)>>,
+    body: &Arc<Option<Template>>,
+    queue: Arc<Mutex<Receiver<(usize, Payload)>>>,
+) -> Result<(), String> {
+    let mut dual_stack = Vec::new();
+    for target in targets.iter() {
+        dual_stack.push(check_target(args, target).await);
+    }
+    let dual_stack = Arc::new(dual_stack);
+
+    let stats = Arc::new(RunStats::default());
+    let ipv4: Arc<dyn Transport> = Arc::from(transport::build_for_family(args, &stats, IpFamily::V4)?);
+    let ipv6: Arc<dyn Transport> = Arc::from(transport::build_for_family(args, &stats, IpFamily::V6)?);
+    let tally = Arc::new(Tally::default());
+    let workers: Vec<_> = (0..args.concurrency.unwrap_or(DEFAULT_CONCURRENCY))
+        .map(|_| {
+            let (args, targets, headers, body) = (Arc::clone(args), Arc::clone(targets), Arc::clone(headers), Arc::clone(body));
+            let (queue, dual_stack, tally) = (Arc::clone(&queue), Arc::clone(&dual_stack), Arc::clone(&tally));
+            let (ipv4, ipv6) = (Arc::clone(&ipv4), Arc::clone(&ipv6));
+            tokio::spawn(async move {
+                loop {
+                    let next = queue.lock().await.recv().await;
+                    let Some((target_index, payload)) = next else { break };
+                    if !dual_stack[target_index] {
+                        continue;
+                    }
+                    let rendered = render_request(&args, &targets[target_index], &headers, &body, Some(&payload));
+                    let (over_ipv4, over_ipv6) = tokio::join!(send(&*ipv4, &rendered, &args), send(&*ipv6, &rendered, &args));
+                    tally.compared.fetch_add(1, Ordering::Relaxed);
+                    if over_ipv4 != over_ipv6 {
+                        tally.diverged.fetch_add(1, Ordering::Relaxed);
+                        println!("Diverged: {}. IPv4: {}. IPv6: {}. URL: {}", payload.label(), over_ipv4, over_ipv6, rendered.url);
+                    }
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        let _ = worker.await;
+    }
+    println!(
+        "Complete! Compared {} payloads over IPv4 and IPv6, {} got different answers",
+        tally.compared.load(Ordering::Relaxed),
+        tally.diverged.load(Ordering::Relaxed)
+    );
+    Ok(())
+}
+
+// Whether the host of the target has addresses of both families. Addresses written in the URL
+// belong to a single family, and so do names listed with one of them in the --hosts-file.
+async fn check_target(args: &Args, target: &Template) -> bool {
+    let url = canonical_url(target.text(), args.delim());
+    let Some((host, port)) = reqwest::Url::parse(&url)
+        .ok()
+        .and_then(|url| Some((url.host_str()?.trim_start_matches('[').trim_end_matches(']').to_string(), url.port_or_known_default()?)))
+    else {
+        println!("Not comparing {}: the URL has no host", target.text());
+        return false;
+    };
+    if host.parse::<IpAddr>().is_ok() {
+        println!("Not comparing {}: the host is an IP address, use a name with both A and AAAA records", target.text());
+        return false;
+    }
+    let addresses: Vec<IpAddr> = match args.hosts_file.as_ref().and_then(|hosts| hosts.lookup(&host)) {
+        Some(addresses) => addresses.to_vec(),
+        None => match tokio::net::lookup_host((host.as_str(), port)).await {
+            Ok(addresses) => addresses.map(|address| address.ip()).collect(),
+            Err(e) => {
+                println!("Not comparing {}: {}", target.text(), e);
+                return false;
+            }
+        },
+    };
+    for family in [IpFamily::V4, IpFamily::V6] {
+        if !addresses.iter().any(|address| family.matches(address)) {
+            println!("Not comparing {}: {} has no {} address", target.text(), host, family);
+            return false;
+        }
+    }
+    true
+}
+
+async fn send(transport: &dyn Transport, request: &transport::RenderedRequest, args: &Args) -> Answer {
+    let exchange = async {
+        let mut response = transport.send(request).await?;
+        let body = response.bytes().await?;
+        Ok::<Answer, transport::TransportError>(Answer::Response {
+            status: response.status.as_u16(),
+            words: String::from_utf8_lossy(&body).split_whitespace().count(),
+        })
+    };
+    let answer = match args.timeout {
+        Some(timeout) => tokio::time::timeout(timeout, exchange).await.unwrap_or_else(|_| Err("timed out".into())),
+        None => exchange.await,
+    };
+    answer.unwrap_or_else(|e| Answer::Failed(e.to_string()))
+}
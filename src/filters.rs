@@ -0,0 +1,143 @@
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use crate::calibrate::Calibration;
+use crate::delay;
+use crate::language;
+use crate::results::RequestResult;
+use crate::Args;
+
+/// A bound on how long a response took, for --match-time: ">500ms" flags the slow ones, "<50ms"
+/// the fast ones.
+#[derive(Debug, Clone, Copy)]
+pub enum TimeThreshold {
+    Slower(Duration),
+    Faster(Duration),
+}
+
+impl TimeThreshold {
+    pub fn parse(spec: &str) -> Result<TimeThreshold, String> {
+        let spec = spec.trim();
+        match spec.split_at(spec.find(|c: char| c != '>' && c != '<').unwrap_or(spec.len())) {
+            (">", time) => Ok(TimeThreshold::Slower(delay::parse_time(time)?)),
+            ("<", time) => Ok(TimeThreshold::Faster(delay::parse_time(time)?)),
+            _ => Err(format!("invalid time \"{}\", use >TIME or <TIME such as >500ms", spec)),
+        }
+    }
+
+    fn allows(&self, duration: Duration) -> bool {
+        match self {
+            TimeThreshold::Slower(time) => duration > *time,
+            TimeThreshold::Faster(time) => duration < *time,
+        }
+    }
+}
+
+/// Status code, size, word and line count, language, --count-regex count and response time conditions a response has to meet to be reported, and
+/// the wildcard responses of --auto-calibrate it must not look like.
+pub struct ResponseFilter {
+    match_status: Vec<RangeInclusive<usize>>,
+    filter_status: Vec<RangeInclusive<usize>>,
+    match_size: Vec<RangeInclusive<usize>>,
+    filter_size: Vec<RangeInclusive<usize>>,
+    match_text_length: Vec<RangeInclusive<usize>>,
+    filter_text_length: Vec<RangeInclusive<usize>>,
+    match_language: Vec<String>,
+    filter_language: Vec<String>,
+    match_words: Vec<RangeInclusive<usize>>,
+    filter_words: Vec<RangeInclusive<usize>>,
+    match_lines: Vec<RangeInclusive<usize>>,
+    filter_lines: Vec<RangeInclusive<usize>>,
+    match_count: Vec<RangeInclusive<usize>>,
+    filter_count: Vec<RangeInclusive<usize>>,
+    match_time: Vec<TimeThreshold>,
+    calibration: Option<Calibration>,
+}
+
+impl ResponseFilter {
+    // Returns None when no filters were given, so that nothing needs to be checked.
+    pub fn from_args(args: &Args, calibration: Option<Calibration>) -> Option<ResponseFilter> {
+        let filter = ResponseFilter {
+            match_status: args.match_status.clone(),
+            filter_status: args.filter_status.clone(),
+            match_size: args.match_size.clone(),
+            filter_size: args.filter_size.clone(),
+            match_text_length: args.match_text_length.clone(),
+            filter_text_length: args.filter_text_length.clone(),
+            match_language: args.match_language.iter().map(|language| language.trim().to_lowercase()).collect(),
+            filter_language: args.filter_language.iter().map(|language| language.trim().to_lowercase()).collect(),
+            match_words: args.match_words.clone(),
+            filter_words: args.filter_words.clone(),
+            match_lines: args.match_lines.clone(),
+            filter_lines: args.filter_lines.clone(),
+            match_count: args.match_count.clone(),
+            filter_count: args.filter_count.clone(),
+            match_time: args.match_time.clone(),
+            calibration,
+        };
+        let empty = filter.match_status.is_empty()
+            && filter.filter_status.is_empty()
+            && filter.match_size.is_empty()
+            && filter.filter_size.is_empty()
+            && filter.match_text_length.is_empty()
+            && filter.filter_text_length.is_empty()
+            && filter.match_language.is_empty()
+            && filter.filter_language.is_empty()
+            && filter.match_words.is_empty()
+            && filter.filter_words.is_empty()
+            && filter.match_lines.is_empty()
+            && filter.filter_lines.is_empty()
+            && filter.match_count.is_empty()
+            && filter.filter_count.is_empty()
+            && filter.match_time.is_empty()
+            && filter.calibration.is_none();
+        if empty {
+            None
+        } else {
+            Some(filter)
+        }
+    }
+
+    // A response is reported when it fits every --match-* flag that was given and none of the
+    // --filter-* ones.
+    pub fn allows(&self, result: &RequestResult) -> bool {
+        let status = result.status.as_u16() as usize;
+        let size = result.length;
+        // The text length is always measured when one of its filters was given.
+        let text_length = result.text_length.unwrap_or_default();
+        // Likewise the count, its filters require --count-regex.
+        let count = result.count.unwrap_or_default();
+        let language = result.language.unwrap_or(language::UNKNOWN);
+        (self.match_status.is_empty() || contains(&self.match_status, status))
+            && !contains(&self.filter_status, status)
+            && (self.match_size.is_empty() || contains(&self.match_size, size))
+            && !contains(&self.filter_size, size)
+            && (self.match_text_length.is_empty() || contains(&self.match_text_length, text_length))
+            && !contains(&self.filter_text_length, text_length)
+            && (self.match_language.is_empty() || self.match_language.iter().any(|wanted| wanted == language))
+            && !self.filter_language.iter().any(|unwanted| unwanted == language)
+            && (self.match_words.is_empty() || contains(&self.match_words, result.words))
+            && !contains(&self.filter_words, result.words)
+            && (self.match_lines.is_empty() || contains(&self.match_lines, result.lines))
+            && !contains(&self.filter_lines, result.lines)
+            && (self.match_count.is_empty() || contains(&self.match_count, count))
+            && !contains(&self.filter_count, count)
+            && self.match_time.iter().all(|threshold| threshold.allows(result.duration))
+            && !self.calibration.as_ref().is_some_and(|calibration| calibration.is_wildcard(result))
+    }
+}
+
+fn contains(ranges: &[RangeInclusive<usize>], value: usize) -> bool {
+    ranges.iter().any(|range| range.contains(&value))
+}
+
+// Parses values like "404" or "500-599", several of them can be given separated by commas.
+pub fn parse_range(value: &str) -> Result<RangeInclusive<usize>, String> {
+    let number = |text: &str| {
+        text.trim().parse::<usize>().map_err(|_| format!("expected a number or a range like 200-299, got \"{}\"", value))
+    };
+    match value.split_once('-') {
+        Some((start, end)) => Ok(number(start)?..=number(end)?),
+        None => Ok(number(value)?..=number(value)?),
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::peek::Peek;
+
+// Headers that change on every response and would make every snapshot look different.
+const VOLATILE_HEADERS: [&str; 10] = [
+    "date",
+    "age",
+    "expires",
+    "last-modified",
+    "content-length",
+    "etag",
+    "set-cookie",
+    "x-request-id",
+    "x-amzn-requestid",
+    "cf-ray",
+];
+
+/// How a target answered a plain GET at one point of the run. Comparing the snapshot taken
+/// before the run with the one taken after it tells whether the target started behaving
+/// differently halfway, e.g. because a WAF kicked in, which makes the results unreliable.
+#[derive(Debug)]
+pub struct Snapshot {
+    // Either the status line or the error the request failed with.
+    status: String,
+    title: Option<String>,
+    body_length: usize,
+    body_hash: u64,
+    headers: BTreeMap<String, String>,
+    cookie_names: Vec<String>,
+    // Size and hash of the DER encoded leaf certificate, for https targets.
+    certificate: Option<(usize, u64)>,
+}
+
+// The canonical request is the target URL with every delimited part taken out, so it doesn't
+// depend on any word of the wordlist.
+pub fn canonical_url(target: &str, delim: &str) -> String {
+    if delim.is_empty() {
+        return target.to_string();
+    }
+    target
+        .split(delim)
+        .step_by(2)
+        .collect::<Vec<&str>>()
+        .concat()
+}
+
+pub async fn take_all(targets: &[String], delim: &str) -> Vec<Snapshot> {
+    let client = reqwest::Client::builder()
+        .tls_info(true)
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(Duration::from_secs(10))
+        .build()
+        .unwrap();
+    let handles: Vec<_> = targets
+        .iter()
+        .map(|target| {
+            let client = client.clone();
+            let url = canonical_url(target, delim);
+            tokio::spawn(async move { Snapshot::take(&client, &url).await })
+        })
+        .collect();
+
+    let mut snapshots = Vec::new();
+    for handle in handles {
+        snapshots.push(handle.await.unwrap());
+    }
+    snapshots
+}
+
+impl Snapshot {
+    async fn take(client: &reqwest::Client, url: &str) -> Snapshot {
+        let response = match client.get(url).send().await {
+            Ok(response) => response,
+            Err(error) => {
+                return Snapshot {
+                    status: format!("error: {}", error),
+                    title: None,
+                    body_length: 0,
+                    body_hash: 0,
+                    headers: BTreeMap::new(),
+                    cookie_names: Vec::new(),
+                    certificate: None,
+                }
+            }
+        };
+
+        let status = response.status().to_string();
+        let headers = response.headers().clone();
+        let certificate = response
+            .extensions()
+            .get::<reqwest::tls::TlsInfo>()
+            .and_then(|info| info.peer_certificate())
+            .map(|der| (der.len(), fnv1a(der)));
+        let body = response.bytes().await.unwrap_or_default();
+
+        let mut cookie_names: Vec<String> = headers
+            .get_all(reqwest::header::SET_COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .filter_map(|value| value.split_once('=').map(|(name, _)| name.trim().to_string()))
+            .collect();
+        cookie_names.sort();
+        cookie_names.dedup();
+
+        Snapshot {
+            status,
+            title: Peek::inspect(&headers, &body, true).title,
+            body_length: body.len(),
+            body_hash: fnv1a(&body),
+            headers: headers
+                .iter()
+                .filter(|(name, _)| !VOLATILE_HEADERS.contains(&name.as_str()))
+                .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).to_string()))
+                .collect(),
+            cookie_names,
+            certificate,
+        }
+    }
+
+    // Lists every difference between two snapshots of the same target, in a readable form.
+    pub fn diff(&self, after: &Snapshot) -> Vec<String> {
+        let mut changes = Vec::new();
+        if self.status != after.status {
+            changes.push(format!("status: {} -> {}", self.status, after.status));
+        }
+        if self.title != after.title {
+            changes.push(format!("title: {:?} -> {:?}", self.title, after.title));
+        }
+        if self.body_length != after.body_length {
+            changes.push(format!("body length: {} -> {}", self.body_length, after.body_length));
+        } else if self.body_hash != after.body_hash {
+            changes.push(String::from("body content changed, same length"));
+        }
+
+        let names: std::collections::BTreeSet<&String> = self.headers.keys().chain(after.headers.keys()).collect();
+        for name in names {
+            let before_value = self.headers.get(name);
+            let after_value = after.headers.get(name);
+            if before_value != after_value {
+                changes.push(format!(
+                    "header {}: {} -> {}",
+                    name,
+                    before_value.map(String::as_str).unwrap_or("(none)"),
+                    after_value.map(String::as_str).unwrap_or("(none)")
+                ));
+            }
+        }
+        if self.cookie_names != after.cookie_names {
+            changes.push(format!("cookies set: {:?} -> {:?}", self.cookie_names, after.cookie_names));
+        }
+        if self.certificate != after.certificate {
+            changes.push(String::from("TLS certificate changed"));
+        }
+        changes
+    }
+}
+
+// Prints whether each target still answers the canonical request the way it did before the run.
+pub fn print_report(targets: &[String], before: &[Snapshot], after: &[Snapshot]) {
+    for ((target, before), after) in targets.iter().zip(before).zip(after) {
+        let changes = before.diff(after);
+        if changes.is_empty() {
+            println!("Target fingerprint unchanged: {}", target);
+        } else {
+            println!("Target fingerprint changed during the run, results may be unreliable: {}", target);
+            for change in changes {
+                println!("  {}", change);
+            }
+        }
+    }
+}
+
+// Small stable hash, only used to notice that content changed.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
@@ -0,0 +1,73 @@
+use serde_json::Value;
+
+/// A line format for the results, given with --output-template, such as
+/// "{{status}} {{length}} {{word}} -> {{location}}". Fields are the keys of the structured record
+/// of a result, with dots reaching into the nested ones ({{headers.Server}}, {{tags.team}}), and
+/// ":N" pads a field to N characters ({{status:3}}). Fields the result doesn't have are left
+/// empty.
+#[derive(Clone, Debug)]
+pub struct LineTemplate {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug)]
+enum Part {
+    Text(String),
+    Field { path: Vec<String>, width: usize },
+}
+
+impl LineTemplate {
+    pub fn parse(template: &str) -> Result<LineTemplate, String> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_string()));
+            }
+            let field = &rest[start + 2..];
+            let end = field.find("}}").ok_or_else(|| format!("unclosed {{{{ in \"{}\"", template))?;
+            let (name, width) = match field[..end].trim().split_once(':') {
+                Some((name, width)) => {
+                    (name, width.trim().parse().map_err(|_| format!("invalid width \"{}\" for {}", width, name))?)
+                }
+                None => (field[..end].trim(), 0),
+            };
+            if name.is_empty() || name.split('.').any(str::is_empty) {
+                return Err(format!("invalid field \"{}\"", &field[..end]));
+            }
+            parts.push(Part::Field { path: name.split('.').map(str::to_string).collect(), width });
+            rest = &field[end + 2..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_string()));
+        }
+        Ok(LineTemplate { parts })
+    }
+
+    // Numbers are padded on the left so that they line up, text on the right.
+    pub fn render(&self, record: &Value) -> String {
+        let mut line = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => line.push_str(text),
+                Part::Field { path, width } => {
+                    let value = path.iter().try_fold(record, |value, key| value.get(key));
+                    let text = match value {
+                        None | Some(Value::Null) => String::new(),
+                        Some(Value::String(text)) => text.clone(),
+                        Some(Value::Array(values)) => {
+                            values.iter().map(|value| value.as_str().map_or_else(|| value.to_string(), str::to_string)).collect::<Vec<_>>().join(", ")
+                        }
+                        Some(value) => value.to_string(),
+                    };
+                    if value.is_some_and(Value::is_number) {
+                        line.push_str(&format!("{:>width$}", text, width = width));
+                    } else {
+                        line.push_str(&format!("{:<width$}", text, width = width));
+                    }
+                }
+            }
+        }
+        line
+    }
+}
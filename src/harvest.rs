@@ -0,0 +1,125 @@
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+// Most words harvested over a whole run, so that a site generating endless unique tokens can't
+// keep the run going forever.
+const MAX_HARVESTED: usize = 1000;
+
+/// Collects candidate words out of the target's own responses (field names and IDs, link paths,
+/// query parameters, keys of JS objects), to be sent once the wordlist is done. Candidates seen
+/// in the most responses go first. New words found in those responses feed the next round, until
+/// a round finds nothing new.
+#[derive(Default)]
+pub struct Harvester {
+    state: Mutex<State>,
+    // Work items handed out and not finished yet. A round only starts once everything before it
+    // was answered, since those answers are what it is made of.
+    pending: AtomicUsize,
+}
+
+#[derive(Default)]
+struct State {
+    tried: HashSet<String>,
+    // In how many responses each candidate was found.
+    candidates: HashMap<String, usize>,
+    harvested: usize,
+}
+
+/// Held by the task of a work item until it is done.
+pub struct Ticket(Arc<Harvester>);
+
+impl Ticket {
+    // Records the words of the work item and what its response brought.
+    pub fn harvest(&self, words: &[String], body: &str) {
+        self.0.harvest(words, body);
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        self.0.pending.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Harvester {
+    // Called for every work item put in the queue.
+    pub fn queued(&self) {
+        self.pending.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn ticket(self: &Arc<Self>) -> Ticket {
+        Ticket(Arc::clone(self))
+    }
+
+    fn harvest(&self, words: &[String], body: &str) {
+        let found: HashSet<String> = tokens(body).collect();
+        let mut state = self.state.lock().unwrap();
+        for word in words {
+            state.candidates.remove(word);
+            state.tried.insert(word.clone());
+        }
+        for token in found {
+            if !state.tried.contains(&token) {
+                *state.candidates.entry(token).or_default() += 1;
+            }
+        }
+    }
+
+    // Blocks until every queued item was answered, or the queue was closed (the run was stopped).
+    pub fn wait_idle(&self, closed: impl Fn() -> bool) -> bool {
+        while self.pending.load(Ordering::Relaxed) > 0 {
+            if closed() {
+                return false;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        true
+    }
+
+    // The words of the next round, most seen first. Empty once nothing new was found.
+    pub fn next_round(&self) -> Vec<String> {
+        let mut state = self.state.lock().unwrap();
+        let mut candidates: Vec<(String, usize)> = state.candidates.drain().collect();
+        candidates.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        candidates.truncate(MAX_HARVESTED - state.harvested);
+        state.harvested += candidates.len();
+        let words: Vec<String> = candidates.into_iter().map(|(word, _)| word).collect();
+        state.tried.extend(words.iter().cloned());
+        words
+    }
+}
+
+// Candidate words of a response body.
+fn tokens(body: &str) -> impl Iterator<Item = String> + '_ {
+    static PATTERNS: OnceLock<[Regex; 4]> = OnceLock::new();
+    static LINK: OnceLock<Regex> = OnceLock::new();
+    let patterns = PATTERNS.get_or_init(|| {
+        [
+            // Form fields and element IDs.
+            Regex::new(r#"(?i)\b(?:name|id|for)\s*=\s*["']([\w.-]+)["']"#).unwrap(),
+            // Keys of JS objects and JSON.
+            Regex::new(r#"["']([A-Za-z_][\w-]*)["']\s*:"#).unwrap(),
+            // Query parameters anywhere.
+            Regex::new(r"[?&]([A-Za-z_][\w-]*)=").unwrap(),
+            // Values that look like IDs.
+            Regex::new(r#"(?i)\b(?:id|uuid|key|token)["']?\s*[:=]\s*["']?([\w-]+)"#).unwrap(),
+        ]
+    });
+    // Paths of links, forms and anything quoted that starts with a slash.
+    let link = LINK.get_or_init(|| Regex::new(r#"(?i)(?:(?:href|src|action)\s*=\s*["']|["'])(/[\w./-]*)"#).unwrap());
+
+    let captured = patterns.iter().flat_map(move |pattern| pattern.captures_iter(body).map(|captures| captures[1].to_string()));
+    let segments = link.captures_iter(body).flat_map(|captures| {
+        captures[1].split('/').map(str::to_string).collect::<Vec<String>>()
+    });
+    captured.chain(segments).filter(|token| is_candidate(token))
+}
+
+fn is_candidate(token: &str) -> bool {
+    (3..=40).contains(&token.len())
+        && token.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        && !token.starts_with('.')
+}
@@ -0,0 +1,85 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::fingerprint::fnv1a;
+use crate::payloads;
+use crate::redact::{self, Redactor};
+use crate::session::{app_dir, timestamp};
+use crate::usage::UsageError;
+use crate::Args;
+
+// How long after a run finished the same command line is refused without --force.
+const RECENT: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// The completed runs, one file per command line in ~/.httprepeater/history/, so that a command
+/// coming back from the shell history doesn't hit a target again by accident. The file holds when
+/// the run finished, how many results it reported, its session and the command line itself.
+pub struct RunHistory {
+    path: PathBuf,
+    command_line: String,
+}
+
+impl RunHistory {
+    // Refuses the run when the same command line completed recently, unless --force was given.
+    pub fn check(args: &Args) -> Result<RunHistory, UsageError> {
+        let arguments: Vec<String> = std::env::args().skip(1).filter(|arg| arg != "--force").collect();
+        let dir = app_dir().map_err(|e| UsageError::new(e.to_string()))?;
+        // The file is named after the real command line, two scans only apart in a redacted
+        // value aren't the same scan. The file itself only gets the masked one.
+        let path = dir.join("history").join(format!("{:016x}.tsv", fnv1a(arguments.join(" ").as_bytes())));
+        let command_line = redact::stored_arguments(arguments, Redactor::from_args(args).as_ref()).join(" ");
+        let history = RunHistory { path, command_line };
+        // The same command line reading its words from stdin can be a whole other scan, and
+        // benchmarks are meant to be run again.
+        if args.force || args.list.iter().any(|spec| payloads::is_stdin(spec)) || args.benchmark.is_some() {
+            return Ok(history);
+        }
+        let Some((finished, reported, session)) = history.last_run() else {
+            return Ok(history);
+        };
+        let age = SystemTime::now().duration_since(finished).unwrap_or_default();
+        if age >= RECENT {
+            return Ok(history);
+        }
+        let previous = match session {
+            Some(session) => format!("Its results are in session {} (httprepeater sessions show {}).", session, session),
+            None => String::from("Its results weren't stored in a session."),
+        };
+        Err(UsageError::new(format!(
+            "The same scan finished {} ago ({}) and reported {} results. {} Add --force to run it again.",
+            format_age(age),
+            timestamp(finished),
+            reported,
+            previous
+        )))
+    }
+
+    // Remembers that the run completed. Runs that were stopped early don't count.
+    pub fn record(&self, reported: usize, session: Option<&str>) {
+        let finished = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let line = format!("{}\t{}\t{}\t{}\n", finished, reported, session.unwrap_or_default(), self.command_line);
+        let written = self.path.parent().map_or(Ok(()), fs::create_dir_all).and_then(|_| fs::write(&self.path, line));
+        if let Err(e) = written {
+            eprintln!("Could not record the run in {}: {}", self.path.display(), e);
+        }
+    }
+
+    fn last_run(&self) -> Option<(SystemTime, usize, Option<String>)> {
+        let text = fs::read_to_string(&self.path).ok()?;
+        let mut fields = text.trim_end().splitn(4, '\t');
+        let finished = UNIX_EPOCH + Duration::from_secs(fields.next()?.parse().ok()?);
+        let reported = fields.next()?.parse().ok()?;
+        let session = fields.next().filter(|session| !session.is_empty()).map(str::to_string);
+        // Two command lines with the same hash aren't the same scan.
+        (fields.next()? == self.command_line).then_some((finished, reported, session))
+    }
+}
+
+fn format_age(age: Duration) -> String {
+    match age.as_secs() {
+        secs if secs < 60 => format!("{} seconds", secs),
+        secs if secs < 3600 => format!("{} minutes", secs / 60),
+        secs => format!("{} hours", secs / 3600),
+    }
+}
@@ -0,0 +1,166 @@
+use hyper_util::client::legacy::connect::dns::{GaiResolver, Name};
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower_service::Service;
+
+/// Static name to address mappings read from a file in the /etc/hosts format, used instead of DNS
+/// for the names it lists. Every other name still goes through the system resolver.
+#[derive(Debug, Clone)]
+pub struct HostsMap(Arc<HashMap<String, Vec<IpAddr>>>);
+
+impl HostsMap {
+    pub fn load(path: &str) -> Result<HostsMap, String> {
+        let text = crate::wordlist::read_text(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+        HostsMap::parse(&text)
+    }
+
+    // Reads lines of "address name [alias...]", with # starting a comment. A name listed on
+    // several lines gets every address, in order.
+    fn parse(text: &str) -> Result<HostsMap, String> {
+        let mut hosts: HashMap<String, Vec<IpAddr>> = HashMap::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut fields = line.split_whitespace();
+            let Some(address) = fields.next() else { continue };
+            let address: IpAddr = address.parse().map_err(|_| format!("line {}: invalid address {}", number + 1, address))?;
+            let mut named = false;
+            for name in fields {
+                hosts.entry(name.to_ascii_lowercase()).or_default().push(address);
+                named = true;
+            }
+            if !named {
+                return Err(format!("line {}: {} has no name", number + 1, address));
+            }
+        }
+        Ok(HostsMap(Arc::new(hosts)))
+    }
+
+    pub fn lookup(&self, host: &str) -> Option<&[IpAddr]> {
+        self.0.get(&host.trim_end_matches('.').to_ascii_lowercase()).map(Vec::as_slice)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &[IpAddr])> {
+        self.0.iter().map(|(name, addresses)| (name.as_str(), addresses.as_slice()))
+    }
+}
+
+/// One of the two IP stacks, for --compare-ip-families.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpFamily {
+    V4,
+    V6,
+}
+
+impl IpFamily {
+    pub fn matches(self, address: &IpAddr) -> bool {
+        match self {
+            IpFamily::V4 => address.is_ipv4(),
+            IpFamily::V6 => address.is_ipv6(),
+        }
+    }
+}
+
+impl std::fmt::Display for IpFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            IpFamily::V4 => "IPv4",
+            IpFamily::V6 => "IPv6",
+        })
+    }
+}
+
+/// The resolver of the hyper transport, and of the reqwest one when it is held to an IP family:
+/// names of the --hosts-file first, the system resolver for the rest. With a family, the
+/// addresses of the other one are dropped.
+#[derive(Clone)]
+pub struct HostsResolver {
+    hosts: Option<HostsMap>,
+    system: GaiResolver,
+    family: Option<IpFamily>,
+}
+
+impl HostsResolver {
+    pub fn new(hosts: Option<HostsMap>, family: Option<IpFamily>) -> HostsResolver {
+        HostsResolver { hosts, system: GaiResolver::new(), family }
+    }
+
+    // A name without an address of the family fails to resolve, rather than connecting over the
+    // other one.
+    fn keep_family(family: Option<IpFamily>, addresses: Vec<SocketAddr>, name: &str) -> io::Result<Addresses> {
+        let Some(family) = family else { return Ok(addresses.into_iter()) };
+        let kept: Vec<SocketAddr> = addresses.into_iter().filter(|address| family.matches(&address.ip())).collect();
+        if kept.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} has no {} address", name, family)));
+        }
+        Ok(kept.into_iter())
+    }
+}
+
+type Addresses = std::vec::IntoIter<SocketAddr>;
+
+impl Service<Name> for HostsResolver {
+    type Response = Addresses;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Addresses>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.system.poll_ready(cx)
+    }
+
+    // The port is set by the connector afterwards, 0 stands in for it.
+    fn call(&mut self, name: Name) -> Self::Future {
+        let family = self.family;
+        if let Some(addresses) = self.hosts.as_ref().and_then(|hosts| hosts.lookup(name.as_str())) {
+            let addresses: Vec<SocketAddr> = addresses.iter().map(|address| SocketAddr::new(*address, 0)).collect();
+            return Box::pin(async move { HostsResolver::keep_family(family, addresses, name.as_str()) });
+        }
+        let resolving = self.system.call(name.clone());
+        Box::pin(async move { HostsResolver::keep_family(family, resolving.await?.collect(), name.as_str()) })
+    }
+}
+
+impl reqwest::dns::Resolve for HostsResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let mut resolver = self.clone();
+        Box::pin(async move {
+            let name: Name = name.as_str().parse()?;
+            let addresses = resolver.call(name).await?;
+            Ok(Box::new(addresses) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addresses(hosts: &HostsMap, name: &str) -> Vec<String> {
+        hosts.lookup(name).unwrap_or_default().iter().map(IpAddr::to_string).collect()
+    }
+
+    #[test]
+    fn names_and_aliases() {
+        let hosts = HostsMap::parse(
+            "# staging\n10.0.0.1 app.internal api.internal # both\n\n  ::1\tApp.Internal\n10.0.0.2 api.internal\r\n",
+        )
+        .unwrap();
+        assert_eq!(addresses(&hosts, "app.internal"), ["10.0.0.1", "::1"]);
+        assert_eq!(addresses(&hosts, "API.internal."), ["10.0.0.1", "10.0.0.2"]);
+        assert!(hosts.lookup("other.internal").is_none());
+        assert_eq!(hosts.entries().count(), 2);
+    }
+
+    #[test]
+    fn invalid_lines() {
+        let error = HostsMap::parse("10.0.0.1 app\n10.0.0.300 api\n").unwrap_err();
+        assert!(error.starts_with("line 2: invalid address"), "{}", error);
+        let error = HostsMap::parse("10.0.0.1 # no name\n").unwrap_err();
+        assert!(error.starts_with("line 1: 10.0.0.1 has no name"), "{}", error);
+        assert!(HostsMap::parse("app.internal 10.0.0.1\n").is_err());
+    }
+}
@@ -0,0 +1,129 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+use crate::payloads::Payload;
+use crate::stats::RunStats;
+use crate::template::Template;
+use crate::{render_request, transport, Args};
+
+// Word sent to get the baseline, which no application should know about.
+const BASELINE_WORD: &str = "httprepeater-baseline-7f3kq";
+// Elements listed in a diff before the rest is only counted.
+const MAX_LISTED: usize = 5;
+
+// Tells HTML responses apart from the rest by their content type, or by their first character
+// when there is none.
+pub fn is_html(headers: &reqwest::header::HeaderMap, body: &str) -> bool {
+    match headers.get(reqwest::header::CONTENT_TYPE).and_then(|value| value.to_str().ok()) {
+        Some(content_type) => content_type.contains("html"),
+        None => body.trim_start().starts_with('<'),
+    }
+}
+
+// Every element of the page in order, each written as tag#id.class so that elements can be told
+// apart without keeping the whole DOM around.
+pub fn elements(body: &str) -> Vec<String> {
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    static ID: OnceLock<Regex> = OnceLock::new();
+    static CLASS: OnceLock<Regex> = OnceLock::new();
+    let tag = TAG.get_or_init(|| Regex::new(r"<([a-zA-Z][a-zA-Z0-9-]*)([^>]*)>").unwrap());
+    let id = ID.get_or_init(|| Regex::new(r#"(?i)\bid\s*=\s*["']?([^"'\s>]+)"#).unwrap());
+    let class = CLASS.get_or_init(|| Regex::new(r#"(?i)\bclass\s*=\s*["']([^"']*)["']"#).unwrap());
+
+    tag.captures_iter(body)
+        .map(|captures| {
+            let mut element = captures[1].to_lowercase();
+            let attributes = &captures[2];
+            if let Some(id) = id.captures(attributes) {
+                element.push_str(&format!("#{}", &id[1]));
+            }
+            if let Some(class) = class.captures(attributes) {
+                for name in class[1].split_whitespace() {
+                    element.push_str(&format!(".{}", name));
+                }
+            }
+            element
+        })
+        .collect()
+}
+
+// Describes which elements the page has more or less of than the baseline, e.g.
+// "+2 (div.alert, p) -1 (form#login)". None when the structure is the same.
+pub fn diff(baseline: &[String], page: &[String]) -> Option<String> {
+    let mut counts: HashMap<&str, isize> = HashMap::new();
+    for element in page {
+        *counts.entry(element).or_default() += 1;
+    }
+    for element in baseline {
+        *counts.entry(element).or_default() -= 1;
+    }
+
+    // Listed in the order they appear in, which reads better than any sorting.
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    for element in page.iter().chain(baseline) {
+        if let Some(count) = counts.remove(element.as_str()) {
+            if count > 0 {
+                added.push((element.as_str(), count));
+            } else if count < 0 {
+                removed.push((element.as_str(), -count));
+            }
+        }
+    }
+    if added.is_empty() && removed.is_empty() {
+        return None;
+    }
+
+    let describe = |sign: char, elements: &[(&str, isize)]| {
+        let total: isize = elements.iter().map(|(_, count)| count).sum();
+        let mut names: Vec<String> = elements
+            .iter()
+            .take(MAX_LISTED)
+            .map(|(name, count)| if *count > 1 { format!("{} x{}", name, count) } else { name.to_string() })
+            .collect();
+        if elements.len() > MAX_LISTED {
+            names.push(String::from("..."));
+        }
+        format!("{}{} ({})", sign, total, names.join(", "))
+    };
+    let mut parts = Vec::new();
+    if !added.is_empty() {
+        parts.push(describe('+', &added));
+    }
+    if !removed.is_empty() {
+        parts.push(describe('-', &removed));
+    }
+    Some(parts.join(" "))
+}
+
+// Sends the request of every target once with a word no application should know, and keeps the
+// elements of the HTML answers as the baselines the results get compared against. Fails if the
+// transport can't be set up.
+pub async fn baselines(
+    args: &Arc<Args>,
+    targets: &[Template],
+    headers: &[(Template, Template)],
+    body: &Option<Template>,
+) -> Result<Vec<Option<Vec<String>>>, String> {
+    let stats = Arc::new(RunStats::default());
+    let transport = transport::build(args, &stats, None)?;
+    let payload = Payload::single(BASELINE_WORD.to_string());
+
+    let mut baselines = Vec::new();
+    for target in targets {
+        let rendered = render_request(args, target, headers, body, Some(&payload));
+        let baseline = match transport.send(&rendered).await {
+            Ok(mut response) => {
+                let text = String::from_utf8_lossy(&response.bytes().await.unwrap_or_default()).to_string();
+                is_html(&response.headers, &text).then(|| elements(&text))
+            }
+            Err(error) => {
+                eprintln!("Could not get the HTML diff baseline of {}: {}", target.text(), error);
+                None
+            }
+        };
+        baselines.push(baseline);
+    }
+    Ok(baselines)
+}
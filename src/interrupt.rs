@@ -0,0 +1,28 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::stats::RunStats;
+
+// Exit code of a process killed by SIGINT, which is what shells expect after a second Ctrl-C.
+#[cfg(not(windows))]
+const EXIT_INTERRUPTED: i32 = 130;
+// STATUS_CONTROL_C_EXIT, what Windows reports for a process ended by Ctrl-C.
+#[cfg(windows)]
+const EXIT_INTERRUPTED: i32 = 0xC000013Au32 as i32;
+
+// Waits for Ctrl-C. The first one stops the run from taking new words and lets the requests in
+// flight finish, so that outputs get closed properly and the summary is still printed. A second
+// one quits right away.
+pub async fn watch(stop: Arc<AtomicBool>, stats: Arc<RunStats>) {
+    if tokio::signal::ctrl_c().await.is_err() {
+        return;
+    }
+    stop.store(true, Ordering::Relaxed);
+    eprintln!(
+        "Interrupted, waiting for the {} requests in flight to finish. Press Ctrl-C again to quit right away.",
+        stats.in_flight()
+    );
+    if tokio::signal::ctrl_c().await.is_ok() {
+        std::process::exit(EXIT_INTERRUPTED);
+    }
+}
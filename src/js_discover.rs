@@ -0,0 +1,100 @@
+use regex::Regex;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::fingerprint::canonical_url;
+
+// Scripts downloaded per target page, and the most read of each of them.
+const MAX_SCRIPTS: usize = 50;
+const MAX_SCRIPT_BYTES: usize = 5 * 1024 * 1024;
+
+// Downloads the page of every target (with the delimited parts left out) along with the scripts
+// it links to, and pulls out the paths and parameter names found in them. Paths lose their
+// leading slash, since the URL template usually has one before the placeholder already.
+pub async fn discover(targets: &[String], delim: &str) -> Vec<String> {
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(10)).build().unwrap();
+    let mut words = Vec::new();
+    let mut seen = HashSet::new();
+    for target in targets {
+        let page_url = canonical_url(target, delim);
+        let Some(page) = fetch(&client, &page_url).await else { continue };
+        let mut sources = vec![page.clone()];
+
+        let base = reqwest::Url::parse(&page_url).ok();
+        let scripts: Vec<reqwest::Url> = script_links(&page)
+            .filter_map(|link| base.as_ref()?.join(&link).ok())
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+            .take(MAX_SCRIPTS)
+            .collect();
+        for script in scripts {
+            if let Some(source) = fetch(&client, script.as_str()).await {
+                sources.push(source);
+            }
+        }
+
+        for source in &sources {
+            for word in extract(source) {
+                if seen.insert(word.clone()) {
+                    words.push(word);
+                }
+            }
+        }
+    }
+    println!("JS discovery found {} paths and parameters", words.len());
+    words
+}
+
+async fn fetch(client: &reqwest::Client, url: &str) -> Option<String> {
+    let mut response = match client.get(url).send().await {
+        Ok(response) => response,
+        Err(error) => {
+            eprintln!("JS discovery could not fetch {}: {}", url, error);
+            return None;
+        }
+    };
+    let mut body = Vec::new();
+    while let Ok(Some(chunk)) = response.chunk().await {
+        body.extend_from_slice(&chunk);
+        if body.len() >= MAX_SCRIPT_BYTES {
+            break;
+        }
+    }
+    Some(String::from_utf8_lossy(&body).to_string())
+}
+
+fn script_links(page: &str) -> impl Iterator<Item = String> + '_ {
+    static SCRIPT: OnceLock<Regex> = OnceLock::new();
+    let script = SCRIPT.get_or_init(|| Regex::new(r#"(?i)<script[^>]*\bsrc\s*=\s*["']([^"']+)["']"#).unwrap());
+    script.captures_iter(page).map(|captures| captures[1].to_string())
+}
+
+// Quoted strings that look like paths ("/api/v1/users", "./login", "admin/panel.php") and the
+// names of query parameters.
+fn extract(source: &str) -> Vec<String> {
+    static PATH: OnceLock<Regex> = OnceLock::new();
+    static PARAMETER: OnceLock<Regex> = OnceLock::new();
+    let path = PATH.get_or_init(|| {
+        Regex::new(r#"["'`]((?:\.{0,2}/)?[A-Za-z0-9_\-]+(?:/[A-Za-z0-9_\-.]*)+|/[A-Za-z0-9_\-.]+)(?:\?[^"'`]*)?["'`]"#).unwrap()
+    });
+    let parameter = PARAMETER.get_or_init(|| Regex::new(r"[?&]([A-Za-z_][\w-]{0,39})=").unwrap());
+
+    let mut words = Vec::new();
+    for captures in path.captures_iter(source) {
+        let found = captures[1].trim_start_matches('.').trim_start_matches('/');
+        // Protocol relative URLs, MIME types and the like aren't paths of the target.
+        if found.is_empty() || found.starts_with('/') || found.len() > 200 || is_mime_type(found) {
+            continue;
+        }
+        words.push(found.to_string());
+    }
+    for captures in parameter.captures_iter(source) {
+        words.push(captures[1].to_string());
+    }
+    words
+}
+
+fn is_mime_type(text: &str) -> bool {
+    const TYPES: [&str; 7] = ["application/", "text/", "image/", "audio/", "video/", "font/", "multipart/"];
+    TYPES.iter().any(|prefix| text.starts_with(prefix))
+}
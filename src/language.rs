@@ -0,0 +1,64 @@
+// Shown when the text is too short or too mixed for a language to stand out.
+pub const UNKNOWN: &str = "unknown";
+
+// The best language needs this many times the stopwords of the runner-up, and at least MIN_HITS
+// of them, to make the call.
+const MARGIN: f64 = 1.5;
+const MIN_HITS: usize = 3;
+
+// The most common short words of each language. They make up a large share of any text and
+// barely overlap between languages, which is all a detector for error pages needs.
+const STOPWORDS: [(&str, &[&str]); 11] = [
+    ("en", &["the", "and", "of", "to", "is", "in", "that", "it", "for", "you", "was", "with", "on", "are", "this", "be", "not", "have", "please", "your", "page", "found"]),
+    ("fr", &["le", "la", "les", "des", "et", "est", "un", "une", "du", "que", "pas", "pour", "dans", "ce", "vous", "sur", "avec", "être", "cette", "page", "votre"]),
+    ("de", &["der", "die", "das", "und", "ist", "nicht", "ein", "eine", "zu", "den", "mit", "sich", "auf", "für", "sie", "wurde", "werden", "von", "dem", "bitte", "ihre"]),
+    ("es", &["el", "la", "los", "las", "de", "que", "y", "en", "un", "una", "es", "por", "con", "no", "para", "su", "se", "del", "al", "página", "está"]),
+    ("it", &["il", "lo", "gli", "di", "che", "è", "e", "un", "una", "per", "non", "sono", "con", "del", "della", "le", "si", "pagina", "questa", "alla"]),
+    ("pt", &["o", "os", "as", "de", "que", "e", "do", "da", "em", "um", "uma", "não", "para", "com", "por", "é", "dos", "ao", "página", "você"]),
+    ("nl", &["de", "het", "een", "en", "van", "is", "niet", "dat", "op", "te", "zijn", "met", "voor", "er", "u", "deze", "wordt", "pagina", "geen", "uw"]),
+    ("pl", &["i", "w", "nie", "się", "na", "jest", "do", "to", "że", "z", "jak", "dla", "strona", "może", "lub", "została", "przez", "są", "tej", "ten"]),
+    ("tr", &["ve", "bir", "bu", "da", "de", "için", "ile", "değil", "olarak", "sayfa", "lütfen", "daha", "çok", "gibi", "ama", "veya", "bulunamadı", "olan"]),
+    ("ru", &["и", "в", "не", "на", "что", "с", "по", "это", "как", "для", "страница", "то", "найдена", "или", "вы", "из", "к", "от", "пожалуйста", "был"]),
+    ("uk", &["і", "в", "не", "на", "що", "з", "до", "це", "як", "для", "сторінка", "або", "ви", "від", "буде", "було", "знайдено", "будь", "ласка", "та"]),
+];
+
+// The language of a text, as a two letter code, or "unknown". Scripts other languages don't share
+// (kana, hangul, han) settle it first, stopwords do the rest.
+pub fn detect(text: &str) -> &'static str {
+    let (mut kana, mut hangul, mut han) = (0, 0, 0);
+    for c in text.chars() {
+        match c {
+            '\u{3040}'..='\u{30ff}' => kana += 1,
+            '\u{ac00}'..='\u{d7af}' => hangul += 1,
+            '\u{4e00}'..='\u{9fff}' => han += 1,
+            _ => {}
+        }
+    }
+    if kana >= MIN_HITS {
+        return "ja";
+    }
+    if hangul >= MIN_HITS {
+        return "ko";
+    }
+    if han >= MIN_HITS {
+        return "zh";
+    }
+
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let mut scores: Vec<(&'static str, usize)> = STOPWORDS
+        .iter()
+        .map(|(language, stopwords)| (*language, words.iter().filter(|word| stopwords.contains(&word.as_str())).count()))
+        .collect();
+    scores.sort_by_key(|(_, hits)| std::cmp::Reverse(*hits));
+    let (best, hits) = scores[0];
+    let runner_up = scores[1].1;
+    if hits >= MIN_HITS && hits as f64 >= runner_up as f64 * MARGIN {
+        best
+    } else {
+        UNKNOWN
+    }
+}
@@ -0,0 +1,105 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+// Every power of two of microseconds is split into this many buckets, which keeps percentiles
+// within about 6% of the real value.
+const SUB_BUCKETS: usize = 16;
+// Powers of two covered, up to about 2^40 microseconds (12 days).
+const RANGES: usize = 40;
+
+/// Durations of the completed requests, recorded by every worker into the same buckets so that
+/// nothing needs merging at the end. Min, max and the sum are kept exactly.
+pub struct LatencyHistogram {
+    buckets: Vec<AtomicU64>,
+    count: AtomicU64,
+    sum: AtomicU64,
+    min: AtomicU64,
+    max: AtomicU64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> LatencyHistogram {
+        LatencyHistogram {
+            buckets: (0..SUB_BUCKETS * RANGES).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0),
+            min: AtomicU64::new(u64::MAX),
+            max: AtomicU64::new(0),
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn record(&self, duration: Duration) {
+        let micros = duration.as_micros().min(u64::MAX as u128) as u64;
+        self.buckets[bucket(micros)].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(micros, Ordering::Relaxed);
+        self.min.fetch_min(micros, Ordering::Relaxed);
+        self.max.fetch_max(micros, Ordering::Relaxed);
+    }
+
+    // Smallest duration at least `quantile` of the requests took no longer than, as the upper
+    // bound of its bucket.
+    fn percentile(&self, quantile: f64) -> u64 {
+        let count = self.count.load(Ordering::Relaxed);
+        let rank = ((count as f64 * quantile).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, bucket) in self.buckets.iter().enumerate() {
+            seen += bucket.load(Ordering::Relaxed);
+            if seen >= rank {
+                return upper_bound(index).min(self.max.load(Ordering::Relaxed));
+            }
+        }
+        self.max.load(Ordering::Relaxed)
+    }
+
+    // "Latency: min 1.2ms, avg 20.4ms, p50 15.0ms, p95 60.1ms, p99 95.0ms, max 120.3ms.
+    // Throughput: 85.2 requests/s", or None when no request completed.
+    pub fn summary(&self, elapsed: Duration) -> Option<String> {
+        let count = self.count.load(Ordering::Relaxed);
+        if count == 0 {
+            return None;
+        }
+        Some(format!(
+            "Latency: min {}, avg {}, p50 {}, p95 {}, p99 {}, max {}. Throughput: {:.1} requests/s",
+            format_micros(self.min.load(Ordering::Relaxed)),
+            format_micros(self.sum.load(Ordering::Relaxed) / count),
+            format_micros(self.percentile(0.50)),
+            format_micros(self.percentile(0.95)),
+            format_micros(self.percentile(0.99)),
+            format_micros(self.max.load(Ordering::Relaxed)),
+            count as f64 / elapsed.as_secs_f64().max(0.001)
+        ))
+    }
+}
+
+// Values under SUB_BUCKETS microseconds get a bucket each, above that every power of two is cut in
+// SUB_BUCKETS equal parts.
+fn bucket(micros: u64) -> usize {
+    if micros < SUB_BUCKETS as u64 {
+        return micros as usize;
+    }
+    let power = 63 - micros.leading_zeros() as usize;
+    let shift = power - SUB_BUCKETS.trailing_zeros() as usize;
+    let sub = ((micros >> shift) as usize) - SUB_BUCKETS;
+    let index = (shift + 1) * SUB_BUCKETS + sub;
+    index.min(SUB_BUCKETS * RANGES - 1)
+}
+
+fn upper_bound(index: usize) -> u64 {
+    if index < SUB_BUCKETS {
+        return index as u64;
+    }
+    let shift = index / SUB_BUCKETS - 1;
+    let sub = (index % SUB_BUCKETS + SUB_BUCKETS) as u64;
+    ((sub + 1) << shift) - 1
+}
+
+fn format_micros(micros: u64) -> String {
+    match micros {
+        0..1000 => format!("{}µs", micros),
+        1000..1_000_000 => format!("{:.1}ms", micros as f64 / 1000.0),
+        _ => format!("{:.2}s", micros as f64 / 1_000_000.0),
+    }
+}
@@ -0,0 +1,1790 @@
+use clap::{Parser, Subcommand};
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time;
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
+
+mod assets;
+mod bandwidth;
+mod benchmark;
+mod block;
+mod brute;
+mod calibrate;
+mod challenge;
+mod ci;
+mod compress;
+mod contexts;
+mod corpus;
+mod database;
+mod delay;
+mod dump;
+mod embed;
+mod encode;
+mod encrypt;
+mod failures;
+mod families;
+mod filters;
+mod fingerprint;
+mod formatter;
+mod harvest;
+mod history;
+mod hosts;
+mod htmldiff;
+mod interrupt;
+mod js_discover;
+mod language;
+mod latency;
+mod limits;
+mod load;
+mod matchers;
+mod matrix;
+mod one;
+mod outliers;
+mod output;
+mod payloads;
+mod peek;
+mod pipeline;
+mod placeholders;
+mod progress;
+mod proxies;
+mod queues;
+mod rate;
+mod redact;
+mod render;
+mod request_id;
+mod resources;
+mod retry;
+mod rules;
+mod rpc;
+mod results;
+mod resume;
+mod score;
+mod session;
+mod shuffle;
+mod smart_order;
+mod socks;
+mod stats;
+mod sweep;
+mod template;
+mod text;
+mod tls_info;
+mod transport;
+mod tree;
+mod urls;
+mod usage;
+mod webhook;
+mod wordlist;
+mod wordlist_store;
+
+pub use embed::{Job, RequestTemplate, Results, Runner, Wordlist};
+pub use usage::UsageError;
+
+// Work items produced ahead of the workers.
+const QUEUE_SIZE: usize = 1024;
+// Requests in flight at most in a run without an end and without --concurrency.
+const ENDLESS_CONCURRENCY: usize = 100;
+
+/// Make an HTTP request repeatedly with a wordlist and receive data characteristics
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    // Wordlists handed over by a program embedding the library, along with their placeholder
+    // names. They come on top of the files of --list.
+    #[arg(skip)]
+    words: Vec<(Option<String>, Vec<String>)>,
+
+    // Targets handed over by a pipeline stage, instead of --url.
+    #[arg(skip)]
+    urls: Vec<String>,
+
+    /// What HTTP method to use.
+    /// 
+    /// Example: -m "POST"
+    #[arg(short, long, default_value = "GET")]
+    method: String,
+    
+    /// Header to include in the HTTP request.
+    /// Multiple headers may be included, but they must come with their own individual flag.
+    /// 
+    /// Example: -H "Accept: application/json"
+    #[arg(short = 'H', long)]
+    header: Vec<String>,
+
+    /// Body to include with the HTTP request.
+    /// 
+    /// Example: -b '{"username":"john","password":"123456"}'
+    #[arg(short, long, required = false)]
+    body: Option<String>,
+
+    /// Compresses the body before sending it and sets Content-Encoding accordingly, unless the
+    /// header was given with -H.
+    /// 
+    /// Example: -b '{"username":"##FUZZ##"}' --compress-body gzip
+    #[arg(long, value_enum, requires = "body")]
+    compress_body: Option<compress::BodyCompression>,
+
+    /// Breaks the compressed body so that it no longer decodes, to see how the server handles
+    /// broken compressed payloads. Gzip and deflate bodies start with an invalid block, brotli
+    /// bodies are cut short.
+    /// 
+    /// Example: -b '{"username":"##FUZZ##"}' --compress-body gzip --corrupt-compression
+    #[arg(long, requires = "compress_body")]
+    corrupt_compression: bool,
+
+    /// URL to make the request to. Delimiters can be used in the path and query, just like in
+    /// headers and body.
+    /// 
+    /// Example: -u "http://example.com/##word##?id=1"
+    #[arg(short, long, required_unless_present_any = ["url_file", "rpc"], conflicts_with = "url_file")]
+    url: Option<String>,
+
+    /// File with one URL per line. Every word from the wordlist is sent to every URL.
+    /// 
+    /// Example: --url-file "hosts.txt"
+    #[arg(long)]
+    url_file: Option<String>,
+
+    /// Wordlist file to use for repeated HTTP requests. Built-in wordlists fetched with
+    /// "httprepeater wordlists fetch" are used by their name prefixed with "@".
+    /// Multiple wordlists may be included as "file:NAME", each feeding the ##NAME## placeholders.
+    /// How their words are combined is set with --mode. A wordlist without a name feeds every
+    /// other placeholder. "-" reads the wordlist from stdin, as in
+    /// "sort -u lists/* | httprepeater -l - ...". Wordlists compressed with gzip or zstd are
+    /// decompressed as they are read. "brute:CHARSET:MIN-MAX" makes every combination of the
+    /// characters of CHARSET from MIN to MAX long instead of reading a file.
+    /// 
+    /// Example: -l "words.txt" or -l @common-paths or -l "users.txt:USER" -l "passwords.txt:PASS" or -l "brute:0123456789:4:PIN"
+    #[arg(short, long, allow_hyphen_values = true, required_unless_present_any = ["from_session", "defaults", "word", "length_sweep", "rpc", "benchmark"])]
+    list: Vec<String>,
+
+    /// How the words of several wordlists are combined. "clusterbomb" sends every combination,
+    /// "pitchfork" sends the first words of every list together, then the second ones and so on.
+    /// 
+    /// Example: -l "users.txt:USER" -l "passwords.txt:PASS" --mode pitchfork
+    #[arg(long, value_enum, default_value = "clusterbomb")]
+    mode: payloads::Mode,
+
+    /// Leaves out the first N payloads, to start partway through the wordlists. Counted within the
+    /// shard with --shard.
+    /// 
+    /// Example: -l "words.txt" --skip 250000
+    #[arg(long, default_value_t = 0)]
+    skip: usize,
+
+    /// Sends at most N payloads, after --skip.
+    /// 
+    /// Example: -l "words.txt" --skip 250000 --limit 10000
+    #[arg(long)]
+    limit: Option<usize>,
+
+    /// Only sends this machine's part of the payloads, I/N being the Ith of N parts of about the
+    /// same size. Running every part from 1/N to N/N sends every payload exactly once.
+    /// 
+    /// Example: -l "words.txt" --shard 2/4
+    #[arg(long, value_parser = payloads::Shard::parse)]
+    shard: Option<payloads::Shard>,
+
+    /// Sends the payloads in a random order instead of the order of the wordlists, so that the
+    /// requests don't walk the alphabet. The seed is printed when none is given, running again
+    /// with it gives the same order. Holds every payload in memory.
+    /// 
+    /// Example: -l "words.txt" --shuffle 1337
+    #[arg(long, value_name = "SEED")]
+    shuffle: Option<Option<u64>>,
+
+    /// Sends the whole set of payloads this many times, as a soak test or load generator would.
+    /// Every round goes in the same order. Holds every payload in memory.
+    /// 
+    /// Example: -l "endpoints.txt" --repeat 20 --rate 50
+    #[arg(long, conflicts_with = "resume")]
+    repeat: Option<usize>,
+
+    /// Sends the payloads again and again until the run is interrupted, like --repeat without an
+    /// end. Unless --concurrency or --rps say otherwise, up to 100 requests are in flight.
+    /// 
+    /// Example: -l "endpoints.txt" --forever --rate 50
+    #[arg(long, conflicts_with_all = ["repeat", "resume"])]
+    forever: bool,
+
+    /// Sends the same request this many times, as fast as --concurrency, --rate and the server
+    /// allow, and reports the throughput, the latency percentiles and the status codes instead of
+    /// a line per request. Needs no wordlist, placeholders get their defaults as with --defaults.
+    /// 
+    /// Example: -u "http://example.com/health" --benchmark 10000 --concurrency 100
+    #[arg(long, conflicts_with_all = ["list", "from_session", "defaults", "word", "length_sweep", "shuffle", "repeat", "forever"])]
+    benchmark: Option<usize>,
+
+    /// Writes every request of the run as raw HTTP text to a file in this directory instead of
+    /// sending it. index.tsv in the same directory tells which word and URL each file is for.
+    /// 
+    /// Example: -u "http://example.com/##path##" -l "words.txt" --render-only "requests/"
+    #[arg(long)]
+    render_only: Option<String>,
+
+    /// Uses this single word instead of a wordlist.
+    /// 
+    /// Example: -u "http://example.com/##path##" --word admin
+    #[arg(long, conflicts_with_all = ["list", "from_session", "defaults"])]
+    word: Option<String>,
+
+    /// With the render subcommand, compares the rendered request against this golden file.
+    /// 
+    /// Example: httprepeater render --word test -u "http://example.com/##path##" --expect golden.txt
+    #[arg(long)]
+    expect: Option<String>,
+
+    /// With the render subcommand, writes the rendered request to the --expect file instead of
+    /// comparing it.
+    /// 
+    /// Example: httprepeater render --word test -u "http://example.com/##path##" --expect golden.txt --update-expected
+    #[arg(long, requires = "expect")]
+    update_expected: bool,
+
+    /// Uses the same text repeated at growing lengths instead of a wordlist, to find out where
+    /// length limits are. Takes TEXTxMIN..MAX with an optional ":step=" of a number or
+    /// "powers-of-two" (the default). Long words are shortened in the printed results.
+    /// 
+    /// Example: -u "http://example.com/?q=##FUZZ##" --length-sweep "'A'x1..65536:step=powers-of-two"
+    #[arg(long, value_parser = sweep::LengthSweep::parse, conflicts_with_all = ["list", "from_session", "defaults", "word"])]
+    length_sweep: Option<sweep::LengthSweep>,
+
+    /// Text encoding of the wordlist file. Lines are converted to UTF-8 before being used.
+    /// "auto" looks for a byte order mark and falls back to latin1 for lines that aren't valid UTF-8.
+    /// 
+    /// Example: -l "rockyou.txt" --list-encoding latin1
+    #[arg(long, value_enum, default_value = "utf8")]
+    list_encoding: wordlist::ListEncoding,
+
+    /// Takes the whitespace around every word of the wordlists away.
+    /// 
+    /// Example: -l "words.txt" --trim
+    #[arg(long)]
+    trim: bool,
+
+    /// Leaves the empty lines of the wordlists out, after --trim when both are given.
+    /// 
+    /// Example: -l "words.txt" --skip-empty
+    #[arg(long)]
+    skip_empty: bool,
+
+    /// Sends every word of a wordlist once, the first time it comes up. The words already sent
+    /// are remembered, use --external-sort for lists too big for that.
+    /// 
+    /// Example: -l "merged.txt" --unique
+    #[arg(long)]
+    unique: bool,
+
+    /// Dedupes --unique through the sort program and a temporary file instead of memory, for
+    /// wordlists of hundreds of millions of lines. The words are then sent in byte order. Needs
+    /// the sort program on the PATH, checked before the run starts.
+    /// 
+    /// Example: -l "breach-compilation.txt" --unique --external-sort
+    #[arg(long, requires = "unique")]
+    external_sort: bool,
+
+    /// Percent-encodes the characters of a word that would make the URL invalid (spaces, quotes,
+    /// braces, non-ASCII) when it is put in the URL. '%' is never encoded, so words can carry
+    /// their own encoding.
+    /// 
+    /// Example: -u "http://example.com/?q=##q##" --auto-url-encode false
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    auto_url_encode: bool,
+
+    /// Also sends every word with each of these extensions appended, after the bare word, as
+    /// directory fuzzers do. With several wordlists the extension goes to the word of the unnamed
+    /// one, or else of the first one.
+    /// 
+    /// Example: -u "http://example.com/##path##" -l "words.txt" -x .php,.bak,.old
+    #[arg(short = 'x', long, value_delimiter = ',')]
+    extensions: Vec<String>,
+
+    /// Mangles every word with the rules of this hashcat or John the Ripper rule file, such as
+    /// best64.rule, sending one candidate per rule instead of the bare word (keep a ":" rule for
+    /// it). Candidates repeated between rules of a word are sent once. With several wordlists the
+    /// rules go to the unnamed one, or else to the first one.
+    /// 
+    /// Example: -u "http://example.com/login?password=##pw##" -l "passwords.txt" --rules best64.rule
+    #[arg(long, value_parser = rules::Rules::load)]
+    rules: Option<Arc<rules::Rules>>,
+
+    /// Adds this text in front of every word, without editing the wordlist. It is added before
+    /// --encode runs.
+    /// 
+    /// Example: -l "words.txt" --prefix "admin_"
+    #[arg(long, allow_hyphen_values = true)]
+    prefix: Option<String>,
+
+    /// Adds this text after every word, without editing the wordlist. It is added before
+    /// --encode runs.
+    /// 
+    /// Example: -l "words.txt" --suffix ".php"
+    #[arg(long, allow_hyphen_values = true)]
+    suffix: Option<String>,
+
+    /// Transforms every word before it is put in the request, with a chain of urlencode,
+    /// double-urlencode, base64, hex, md5, sha1, sha256, lowercase and uppercase applied in the
+    /// order given, after --prefix and --suffix. Results still show the words as they are in the
+    /// wordlist.
+    /// 
+    /// Example: -l "ids.txt" -e base64,urlencode or -l "passwords.txt" --encode md5
+    #[arg(short, long, value_enum, value_delimiter = ',')]
+    encode: Vec<encode::Transform>,
+
+    /// Delimiter to change the data between it with each wordlist item.
+    /// A placeholder can declare a default value after ":=", which is used by --defaults.
+    /// 
+    /// Example: -b '{"username":"john","password":"##123456##"}' -D "##"
+    #[arg(short = 'D', long, required_unless_present_any = ["rpc", "benchmark"])]
+    delim: Option<String>,
+
+    /// Sends a single request per URL with every placeholder set to its declared default instead
+    /// of using a wordlist. Meant as a sanity check of a request before fuzzing it.
+    /// 
+    /// Example: -u "http://example.com/?user=##FUZZ:=guest##" -D "##" --defaults
+    #[arg(long, conflicts_with_all = ["list", "from_session"])]
+    defaults: bool,
+
+    /// Displays the full exchange of every reported result: the request as it was sent, then the
+    /// response headers and body, each dump between delimiter lines.
+    /// 
+    /// Example: -u "http://example.com" --verbose
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Writes the dumps of --verbose to one file per result in this directory instead of showing
+    /// them.
+    /// 
+    /// Example: -u "http://example.com/##FUZZ##" -D "##" -l words.txt --verbose-dir dumps
+    #[arg(long)]
+    verbose_dir: Option<String>,
+
+    /// Only dumps the results worth a closer look instead of all of them: "match" for the ones
+    /// --match-string or --match-regex found something in, "error" for failed requests, "5xx"
+    /// for server errors. Shown like --verbose, or written to --verbose-dir.
+    /// 
+    /// Example: -u "http://example.com/##FUZZ##" -D "##" -l words.txt --verbose-on error,5xx
+    #[arg(long, value_enum, value_delimiter = ',')]
+    verbose_on: Vec<dump::VerboseOn>,
+
+    /// Saves the response headers and body of every reported result to a file in this directory,
+    /// named after the word.
+    /// 
+    /// Example: -u "http://example.com/##FUZZ##" -D "##" -l words.txt --match-status 200 --save-responses responses
+    #[arg(long)]
+    save_responses: Option<String>,
+
+    /// Follows the redirect status codes.
+    /// 
+    /// Example: -u "http://example.com" --allowredirects
+    #[arg(short, long)]
+    allowredirects: bool,
+
+    /// Backend used to send the requests. "hyper" sends only the headers that were given, and "raw"
+    /// writes the request straight to the socket in exactly the given order and form. Only
+    /// "reqwest" follows redirects.
+    /// 
+    /// Example: -u "http://example.com" --transport raw
+    #[arg(long, value_enum, default_value = "reqwest")]
+    transport: transport::TransportKind,
+
+    /// Time each thread waits before starting its next request, either fixed or picked at random
+    /// in a range for every request. Plain numbers are milliseconds, "s" and "m" can be used too.
+    /// 
+    /// Example: -u "http://example.com/##path##" --delay 200ms
+    /// Example: -u "http://example.com/##path##" --delay 100-500ms
+    #[arg(long, value_parser = delay::Delay::parse)]
+    delay: Option<delay::Delay>,
+
+    /// Time a request gets to complete, from connecting until the whole body was read. Requests
+    /// that take longer are reported as failed instead of waited for. Plain numbers are
+    /// milliseconds, "s" and "m" can be used too.
+    /// 
+    /// Example: -u "http://example.com/##path##" --timeout 10s
+    #[arg(long, value_parser = delay::parse_timeout)]
+    timeout: Option<time::Duration>,
+
+    /// Time a request gets to connect (including the TLS handshake with the reqwest transport).
+    /// 
+    /// Example: -u "http://example.com/##path##" --connect-timeout 3s
+    #[arg(long, value_parser = delay::parse_timeout)]
+    connect_timeout: Option<time::Duration>,
+
+    /// Resolves the names listed in this file, in the /etc/hosts format ("10.0.0.5 app.internal"),
+    /// to their addresses for the run, without touching /etc/hosts. Other names go through DNS as
+    /// usual. The SNI and Host header keep the name. Through an HTTP proxy the proxy resolves.
+    /// 
+    /// Example: -u "https://app.internal/##path##" --hosts-file "internal_hosts"
+    #[arg(long, value_parser = hosts::HostsMap::load)]
+    hosts_file: Option<hosts::HostsMap>,
+
+    /// How many more times a request is sent when it fails altogether (refused, reset or timed
+    /// out) or gets one of the --retry-on statuses. The wait between attempts starts at 500ms and
+    /// doubles every time, unless the server sends a Retry-After.
+    /// 
+    /// Example: -u "http://example.com/##path##" --retries 3
+    #[arg(long, default_value_t = 0)]
+    retries: u32,
+
+    /// Status codes that get a request sent again with --retries, separated by commas.
+    /// 
+    /// Example: -u "http://example.com/##path##" --retries 3 --retry-on 429,503
+    #[arg(long, value_delimiter = ',', default_value = "429,502,503,504")]
+    retry_on: Vec<u16>,
+
+    /// Stops the run once this many requests failed (DNS, connect, TLS, timeout or any other
+    /// error). Failed requests are listed as they happen either way.
+    /// 
+    /// Example: -u "http://example.com/##path##" --max-errors 50
+    #[arg(long)]
+    max_errors: Option<usize>,
+
+    /// Downloads the page of every target (with the delimited parts left out) and the scripts it
+    /// links to before the run, and sends the paths and parameter names found in them ahead of
+    /// the wordlist. Their results are marked with "(js)".
+    /// 
+    /// Example: -u "http://example.com/##path##" -l "words.txt" --js-discover
+    #[arg(long, conflicts_with_all = ["defaults", "word"])]
+    js_discover: bool,
+
+    /// Experimental. Harvests candidate words from the target's own responses (form fields, IDs,
+    /// link paths, query parameters, JS object keys) and sends them once the wordlist is done,
+    /// the ones seen in the most responses first. Their responses are harvested in turn, until
+    /// nothing new comes up or 1000 words were added.
+    /// 
+    /// Example: -u "http://example.com/##path##" -l "words.txt" --harvest
+    #[arg(long, conflicts_with_all = ["defaults", "word"])]
+    harvest: bool,
+
+    /// Reorders the upcoming payloads as results come in, sending first the words that share an
+    /// extension, a token or a prefix with recent hits (after finding "admin", "admin_login" and
+    /// "administrator" move up). Hits are the reported results, so this works best along with
+    /// filters. Looks at the next 1000 payloads.
+    /// 
+    /// Example: -u "http://example.com/##path##" -l "words.txt" --filter-status 404 --smart-order
+    #[arg(long)]
+    smart_order: bool,
+
+    /// Prints the reported results as a tree of the directories and files of every host, with
+    /// their status codes, once the run is over.
+    /// 
+    /// Example: -u "http://example.com/##path##" -l "words.txt" --filter-status 404 --tree
+    #[arg(long)]
+    tree: bool,
+
+    /// Maximum amount of requests in flight at the same time, across all threads. Words past the
+    /// limit are only taken from the wordlist once a request finishes, so large wordlists don't
+    /// pile up as pending requests. Unlimited by default, except for 100 with --forever, or with
+    /// --duration and no --rps.
+    /// 
+    /// Example: -u "http://example.com/##path##" -l "words.txt" --concurrency 50
+    #[arg(long)]
+    concurrency: Option<usize>,
+
+    /// Amount of threads to use for sending http requests.
+    /// This doesn't actually create os threads, but rather creates request clients that tokio can use to assign to individual os threads.
+    /// 
+    /// Example: -u "http://example.com" -t 4
+    #[arg(short, long, default_value_t = 1)]
+    threads: u16,
+
+    /// Maximum amount of requests sent per second. The limit is shared by every thread, and
+    /// requests past it wait for their turn before being started at all.
+    /// 
+    /// Example: -u "http://example.com" --rate 20
+    #[arg(long)]
+    rate: Option<f64>,
+
+    /// Shares the --rate budget with every other instance pointed at the same file, so that
+    /// several scans running at once stay within one agreed rate together. The rate set by the
+    /// first instance is used by all of them. If the file can't be used, the instance goes on
+    /// with its own --rate.
+    /// 
+    /// Example: --rate 20 --rate-coordination file:/tmp/httprepeater.bucket
+    #[arg(long, requires = "rate")]
+    rate_coordination: Option<String>,
+
+    /// Starts this many requests per second on a timer, whether the responses to the earlier ones
+    /// came back or not, as load generators do. A server slowing down then gets more requests in
+    /// flight instead of fewer requests, which --rate and --concurrency would give it.
+    /// 
+    /// Example: -u "http://example.com/api" --benchmark 100000 --rps 200 --ramp 30s --duration 5m
+    #[arg(long, conflicts_with_all = ["rate", "concurrency", "delay"])]
+    rps: Option<f64>,
+
+    /// Climbs from 0 to --rps in a straight line over this time, then holds --rps.
+    /// 
+    /// Example: --rps 200 --ramp 30s
+    #[arg(long, requires = "rps", value_parser = delay::parse_time)]
+    ramp: Option<time::Duration>,
+
+    /// Stops starting requests after this time, the --ramp included, and waits for the ones in
+    /// flight. Until then the payloads are sent again and again, unless --repeat or --benchmark
+    /// say how many times.
+    /// 
+    /// Example: -l "endpoints.txt" --rps 50 --duration 10m
+    #[arg(long, value_parser = load::parse_duration)]
+    duration: Option<time::Duration>,
+
+    /// Maximum amount of bytes sent and received per second, shared by every thread. Units go by
+    /// 1024 and can be B, KB, MB or GB.
+    /// 
+    /// Example: -u "http://example.com/##path##" --max-bandwidth 5MB/s
+    #[arg(long, value_parser = bandwidth::parse)]
+    max_bandwidth: Option<f64>,
+
+    /// Scores every response by how much it stands out from the rest (rare status codes, outlier
+    /// lengths, error messages, reflected payloads, slow responses) and prints the results sorted
+    /// by score once the run is over, instead of printing them as they arrive.
+    /// 
+    /// Example: -u "http://example.com" --score
+    #[arg(long)]
+    score: bool,
+
+    /// Only prints the N highest scoring results. Used with --score.
+    /// 
+    /// Example: -u "http://example.com" --score --top 20
+    #[arg(long, requires = "score")]
+    top: Option<usize>,
+
+    /// Learns the usual response lengths of the target from the first responses of the run and
+    /// only reports responses whose length doesn't fit any of them.
+    /// 
+    /// Example: -u "http://example.com" --auto-filter-outliers
+    #[arg(long)]
+    auto_filter_outliers: bool,
+
+    /// Number of responses used to learn the usual response lengths. Used with --auto-filter-outliers.
+    /// 
+    /// Example: -u "http://example.com" --auto-filter-outliers --outlier-sample 200
+    #[arg(long, default_value_t = 50, requires = "auto_filter_outliers")]
+    outlier_sample: usize,
+
+    /// Keeps track of the requests completed so far in this state file, and skips them when the
+    /// file is already there. Lets an interrupted run be started again with the same command
+    /// without going through the words that were already sent.
+    /// 
+    /// Example: -u "http://example.com/##FUZZ##" -l "words.txt" --resume state.json
+    #[arg(long)]
+    resume: Option<String>,
+
+    /// Runs the scan even though the same command line finished less than 12 hours ago. Completed
+    /// runs are remembered in ~/.httprepeater/history/ so that one coming back from the shell
+    /// history doesn't hit a target again by accident.
+    /// 
+    /// Example: -u "http://example.com/##path##" -l words.txt --force
+    #[arg(long)]
+    force: bool,
+
+    /// Name of the session to store this run in. Sessions live in ~/.httprepeater/sessions/ and keep
+    /// the configuration, run history, result log and matched words of every run made with them.
+    /// 
+    /// Example: -u "http://example.com" --session acme-api
+    #[arg(long)]
+    session: Option<String>,
+
+    /// Uses the matched words of a previous session as the wordlist, in the form "name:matches".
+    /// Lets the results of one run (e.g. discovered paths) drive the next one.
+    /// 
+    /// Example: -u "http://example.com/##path##/?id=1" --from-session acme-api:matches
+    #[arg(long, conflicts_with = "list")]
+    from_session: Option<String>,
+
+    /// Prints a matrix of the results once the run is over, with a row for every word and a column
+    /// for every URL of --url-file. Rows where the targets didn't agree are marked with a "*".
+    /// Runs with --context always print it, with a column for every context.
+    /// 
+    /// Example: --url-file "hosts.txt" --matrix
+    #[arg(long)]
+    matrix: bool,
+
+    /// Sends every payload once per credential context, each a name and the -H options its
+    /// requests carry in place of the run's headers of the same name, and prints a matrix of the
+    /// status codes every context got for every word. Shows what one user can reach that only
+    /// another should, in a single run. A header without a value is left out of the requests of
+    /// the context. Multiple contexts may be included. Every context gets its own connections,
+    /// its own --rate and its own --max-errors, so a locked out context stops on its own while the
+    /// others go on.
+    /// 
+    /// Example: --context "admin:-H Cookie: session=A" --context "user:-H Cookie: session=B" --context "anon:-H Cookie:"
+    #[arg(long, value_parser = contexts::Context::parse, conflicts_with = "compare_ip_families")]
+    context: Vec<contexts::Context>,
+
+    /// Also writes the matrix to a CSV file. Used with --matrix.
+    /// 
+    /// Example: --url-file "hosts.txt" --matrix --matrix-output "matrix.csv"
+    #[arg(long, requires = "matrix")]
+    matrix_output: Option<String>,
+
+    /// Only reports responses with these status codes. Takes codes and ranges separated by commas.
+    /// 
+    /// Example: -u "http://example.com/##path##" --match-status 200-299,401
+    #[arg(long, value_delimiter = ',', value_parser = filters::parse_range)]
+    match_status: Vec<RangeInclusive<usize>>,
+
+    /// Hides responses with these status codes. Takes codes and ranges separated by commas.
+    /// 
+    /// Example: -u "http://example.com/##path##" --filter-status 404,500-599
+    #[arg(long, value_delimiter = ',', value_parser = filters::parse_range)]
+    filter_status: Vec<RangeInclusive<usize>>,
+
+    /// Only reports responses whose length in bytes is one of these. Takes sizes and ranges
+    /// separated by commas.
+    /// 
+    /// Example: -u "http://example.com/##path##" --match-size 1000-5000
+    #[arg(long, value_delimiter = ',', value_parser = filters::parse_range)]
+    match_size: Vec<RangeInclusive<usize>>,
+
+    /// Hides responses whose length in bytes is one of these. Takes sizes and ranges separated by
+    /// commas.
+    /// 
+    /// Example: -u "http://example.com/##path##" --filter-size 0,1534
+    #[arg(long, value_delimiter = ',', value_parser = filters::parse_range)]
+    filter_size: Vec<RangeInclusive<usize>>,
+
+    /// Sends a few random words to every target before the run, and hides the responses that
+    /// look like their answers (same status, word and line count, and length unless it changed
+    /// with the word). Filters out the "not found" pages of targets answering everything.
+    /// 
+    /// Example: -u "http://example.com/##path##" -l words.txt --auto-calibrate
+    #[arg(long)]
+    auto_calibrate: bool,
+
+    /// Only reports responses whose body has one of these numbers of words, separated by
+    /// whitespace. Takes counts and ranges separated by commas.
+    /// 
+    /// Example: -u "http://example.com/##path##" --match-words 10-200
+    #[arg(long, value_delimiter = ',', value_parser = filters::parse_range)]
+    match_words: Vec<RangeInclusive<usize>>,
+
+    /// Hides responses whose body has one of these numbers of words. Takes counts and ranges
+    /// separated by commas.
+    /// 
+    /// Example: -u "http://example.com/##path##" --filter-words 12
+    #[arg(long, value_delimiter = ',', value_parser = filters::parse_range)]
+    filter_words: Vec<RangeInclusive<usize>>,
+
+    /// Only reports responses whose body has one of these numbers of lines. Takes counts and
+    /// ranges separated by commas.
+    /// 
+    /// Example: -u "http://example.com/##path##" --match-lines 1-20
+    #[arg(long, value_delimiter = ',', value_parser = filters::parse_range)]
+    match_lines: Vec<RangeInclusive<usize>>,
+
+    /// Hides responses whose body has one of these numbers of lines. Takes counts and ranges
+    /// separated by commas.
+    /// 
+    /// Example: -u "http://example.com/##path##" --filter-lines 7
+    #[arg(long, value_delimiter = ',', value_parser = filters::parse_range)]
+    filter_lines: Vec<RangeInclusive<usize>>,
+
+    /// Only reports responses that took longer (>) or less (<) than this to come, to spot the
+    /// delays of time-based injections or of checking an existing user's password. Give both to
+    /// keep a window. The time of every reported response is shown.
+    /// 
+    /// Example: -u "http://example.com/?id=1##sleep##" --match-time ">2s"
+    #[arg(long, value_delimiter = ',', value_parser = filters::TimeThreshold::parse)]
+    match_time: Vec<filters::TimeThreshold>,
+
+    /// Also reports the length of the visible text of every response: the body with HTML tags,
+    /// comments, scripts and styles taken out, in characters. It changes much less than the byte
+    /// length on templated pages whose markup differs from one request to the next.
+    /// 
+    /// Example: -u "http://example.com/##path##" --text-length
+    #[arg(long)]
+    text_length: bool,
+
+    /// Only reports responses whose visible text length is one of these. Takes lengths and ranges
+    /// separated by commas.
+    /// 
+    /// Example: -u "http://example.com/##path##" --match-text-length 100-400
+    #[arg(long, value_delimiter = ',', value_parser = filters::parse_range)]
+    match_text_length: Vec<RangeInclusive<usize>>,
+
+    /// Hides responses whose visible text length is one of these. Takes lengths and ranges
+    /// separated by commas.
+    /// 
+    /// Example: -u "http://example.com/##path##" --filter-text-length 312
+    #[arg(long, value_delimiter = ',', value_parser = filters::parse_range)]
+    filter_text_length: Vec<RangeInclusive<usize>>,
+
+    /// Also reports the human language of every response, from its visible text, as a two letter
+    /// code (en, fr, de, es, it, pt, nl, pl, tr, ru, uk, ja, ko, zh) or "unknown". Error pages of
+    /// multi-locale apps differ per language, which throws length filters off.
+    /// 
+    /// Example: -u "http://example.com/##path##" --detect-language
+    #[arg(long)]
+    detect_language: bool,
+
+    /// Only reports responses in one of these languages. Takes codes separated by commas.
+    /// 
+    /// Example: -u "http://example.com/##path##" --match-language en,unknown
+    #[arg(long, value_delimiter = ',')]
+    match_language: Vec<String>,
+
+    /// Hides responses in one of these languages. Takes codes separated by commas.
+    /// 
+    /// Example: -u "http://example.com/##path##" --filter-language de,fr
+    #[arg(long, value_delimiter = ',')]
+    filter_language: Vec<String>,
+
+    /// Appends the first characters of every response body to its result line, on a single line
+    /// with the whitespace squeezed. Takes how many characters to show.
+    /// 
+    /// Example: -u "http://example.com/##path##" --preview 120
+    #[arg(long)]
+    preview: Option<usize>,
+
+    /// Compares the HTML structure of every response with a baseline response (the same request
+    /// with a word the application can't know) and shows which elements were added or removed,
+    /// e.g. "+2 (div.alert, p) -1 (form#login)".
+    /// 
+    /// Example: -u "http://example.com/##path##" --html-diff
+    #[arg(long)]
+    html_diff: bool,
+
+    /// Only reports responses whose body contains this string.
+    /// Multiple strings may be included, a response is reported if any of them is found.
+    /// 
+    /// Example: -u "http://example.com" --match-string "Welcome back"
+    #[arg(long)]
+    match_string: Vec<String>,
+
+    /// Only reports responses whose body matches this regular expression.
+    /// Multiple expressions may be included, a response is reported if any of them matches.
+    /// 
+    /// Example: -u "http://example.com" --match-regex "user_id=\d+"
+    #[arg(long, value_parser = regex::bytes::Regex::new)]
+    match_regex: Vec<regex::bytes::Regex>,
+
+    /// How many bytes of the body to show on each side of what --match-string or --match-regex
+    /// found, in the result line and the outputs. 0 leaves the excerpt out.
+    /// 
+    /// Example: -u "http://example.com/##path##" --match-regex "api_key=\w+" --match-context 80
+    #[arg(long, default_value_t = 40)]
+    match_context: usize,
+
+    /// Counts how many times this regular expression matches in each body, shown with every
+    /// result and filtered on with --match-count and --filter-count. The number of table rows or
+    /// records a data endpoint returns says more than the length of its body.
+    /// 
+    /// Example: -u "http://example.com/users?id=##id##" --count-regex "<tr>" --filter-count 0
+    #[arg(long, value_parser = regex::Regex::new)]
+    count_regex: Option<regex::Regex>,
+
+    /// Only reports responses where --count-regex matched one of these numbers of times. Takes
+    /// counts and ranges separated by commas.
+    /// 
+    /// Example: -u "http://example.com/users?id=##id##" --count-regex "<tr>" --match-count 2-1000
+    #[arg(long, value_delimiter = ',', requires = "count_regex", value_parser = filters::parse_range)]
+    match_count: Vec<RangeInclusive<usize>>,
+
+    /// Hides responses where --count-regex matched one of these numbers of times. Takes counts and
+    /// ranges separated by commas.
+    /// 
+    /// Example: -u "http://example.com/users?id=##id##" --count-regex "<tr>" --filter-count 0,1
+    #[arg(long, value_delimiter = ',', requires = "count_regex", value_parser = filters::parse_range)]
+    filter_count: Vec<RangeInclusive<usize>>,
+
+    /// Runs --match-string and --match-regex over the body as it is downloaded and stops the download
+    /// as soon as one of them matches. Meant for endpoints returning huge bodies. Regex matches
+    /// longer than 4KB that cross a chunk boundary can be missed. The length, words and lines of a
+    /// stopped download are those of the part that was read.
+    /// 
+    /// Example: -u "http://example.com/export" --match-string "secret" --stream-match
+    #[arg(long)]
+    stream_match: bool,
+
+    /// Only reads the first N bytes of every response body and closes the connection afterwards.
+    /// Reports the size (from Content-Length when available), the page title and known page
+    /// signatures found in those bytes. Meant for fast discovery sweeps.
+    /// 
+    /// Example: -u "http://example.com/##path##" --peek-bytes 2048
+    #[arg(long, conflicts_with = "stream_match")]
+    peek_bytes: Option<usize>,
+
+    /// Writes every reported result to this file as a structured record (word, status, length,
+    /// duration, what it matched...).
+    /// 
+    /// Example: -u "http://example.com/##path##" --output "results.json"
+    #[arg(long)]
+    output: Option<String>,
+
+    /// Format of the --output file. "json" writes a single array, "jsonl" one record per line.
+    /// 
+    /// Example: --output "results.jsonl" --output-format jsonl
+    #[arg(long, value_enum, default_value = "json", requires = "output")]
+    output_format: output::OutputFormat,
+
+    /// Prints every reported result with this template instead of the usual line, and writes the
+    /// --output file with it too, one line per result. Fields are the keys of the --output
+    /// records (status, length, words, lines, word, url, location, duration_ms, matched...), with
+    /// dots for the nested ones such as {{headers.Server}}. {{field:N}} pads the field to N
+    /// characters.
+    /// 
+    /// Example: --output-template "{{status}} {{length:8}} {{word}} -> {{location}}"
+    #[arg(long, value_parser = formatter::LineTemplate::parse, conflicts_with = "output_format")]
+    output_template: Option<formatter::LineTemplate>,
+
+    /// Adds every reported result to this SQLite database, creating it if needed, so that the
+    /// results of huge runs can be queried afterwards and compared across runs. Every run gets a
+    /// row in the "runs" table, its results go to the "results" table. Needs the sqlite3 program
+    /// on the PATH, checked before the run starts.
+    /// 
+    /// Example: -u "http://example.com/##path##" --output-db "results.sqlite"
+    #[arg(long, conflicts_with = "encrypt_output")]
+    output_db: Option<String>,
+
+    /// Encrypts the files the run writes (--output, --matrix-output, --render-only, --verbose-dir
+    /// and --save-responses) as they are written, with age for a recipients file or gpg for a key.
+    /// Nothing is written in clear, and the files get a .age or .gpg extension. Needs the age or
+    /// gpg program on the PATH, checked before the run starts. Can't be used with sessions nor
+    /// --resume, whose files are read back by later runs.
+    /// 
+    /// Example: --output "results.json" --encrypt-output "age:recipients.txt"
+    /// Example: --output "results.json" --encrypt-output "gpg:alice@example.com"
+    #[arg(long, conflicts_with_all = ["session", "resume"], value_parser = encrypt::Encryption::parse)]
+    encrypt_output: Option<encrypt::Encryption>,
+
+    /// Masks the value of this header in everything that is shown or written: printed results,
+    /// sessions, output files, webhooks, rendered requests. The real value is still sent.
+    /// Multiple headers may be included.
+    /// 
+    /// Example: -u "http://example.com" -H "Authorization: Bearer abc" --redact "Authorization"
+    #[arg(long)]
+    redact: Vec<String>,
+
+    /// Masks whatever matches this regular expression in everything that is shown or written,
+    /// the same way as --redact. Multiple expressions may be included.
+    /// 
+    /// Example: -u "http://example.com/login" -b "user=admin&password=hunter2" --redact-regex "password=\S+"
+    #[arg(long, value_parser = regex::Regex::new)]
+    redact_regex: Vec<regex::Regex>,
+
+    /// Publishes every reported result as a JSON message to a NATS subject.
+    /// 
+    /// Example: -u "http://example.com/##path##" --output-nats "nats://localhost:4222/scans.results"
+    #[arg(long)]
+    output_nats: Option<String>,
+
+    /// Adds every reported result to a Redis stream, as the "result" field of each entry.
+    /// 
+    /// Example: -u "http://example.com/##path##" --output-redis "redis://localhost:6379/scan-results"
+    #[arg(long)]
+    output_redis: Option<String>,
+
+    /// Labels the run with a key=value pair, added to every --output record, every webhook post
+    /// and the summary so that results of many runs can be told apart later. Multiple tags may be
+    /// included.
+    /// 
+    /// Example: -u "http://example.com" --tag engagement=acme --tag ticket=SEC-123
+    #[arg(long, value_parser = parse_tag)]
+    tag: Vec<(String, String)>,
+
+    /// Keeps these response headers in every --output record and webhook post, under "headers".
+    /// Other headers are left out, keeping the records small. Takes header names separated by
+    /// commas.
+    /// 
+    /// Example: -u "http://example.com/##path##" --output out.jsonl --capture-headers Server,X-Request-Id,Location
+    #[arg(long, value_delimiter = ',')]
+    capture_headers: Vec<String>,
+
+    /// Sends a unique ID in this header with every request, and records it with the result, so
+    /// that the requests seen in the server logs can be matched to the results of the run.
+    /// 
+    /// Example: -u "http://example.com/##path##" --request-id-header X-Scan-Id
+    #[arg(long)]
+    request_id_header: Option<String>,
+
+    /// Identifies every request as part of an agreed test, with this text in the
+    /// --identify-header header. The text is also shown when the run starts and kept in the
+    /// session log and the --ci summary.
+    /// 
+    /// Example: -u "http://example.com/##path##" --identify "pentest: ticket SEC-123, contact sec@example.com"
+    #[arg(long)]
+    identify: Option<String>,
+
+    /// Header carrying the --identify text.
+    /// 
+    /// Example: --identify "pentest: ticket SEC-123" --identify-header X-Pentest
+    #[arg(long, default_value = "X-Security-Test", requires = "identify")]
+    identify_header: String,
+
+    /// Runs as a JSON-RPC 2.0 server driven by another program instead of scanning right away.
+    /// Commands come in on stdin one per line: configure {"args": [...]} with the usual options,
+    /// start, status and stop. Results are streamed back on stdout as "result" notifications.
+    /// 
+    /// Example: --rpc stdio
+    #[arg(long, value_enum, conflicts_with = "ci")]
+    rpc: Option<rpc::RpcTransport>,
+
+    /// Posts results as JSON to this URL, one request per result. Failed deliveries are retried
+    /// a few times before being given up on.
+    /// 
+    /// Example: -u "http://example.com" --result-webhook "http://localhost:9000/results"
+    #[arg(long)]
+    result_webhook: Option<String>,
+
+    /// Which results are posted to --result-webhook. "matched" only posts the results that are
+    /// reported, "all" posts every response.
+    /// 
+    /// Example: --result-webhook "http://localhost:9000/results" --webhook-filter all
+    #[arg(long, value_enum, default_value = "matched", requires = "result_webhook")]
+    webhook_filter: webhook::WebhookFilter,
+
+    /// Before the run, fetches the favicon and the scripts and stylesheets of every host the
+    /// targets point to, and reports the favicon hash (the one Shodan uses), the frameworks the
+    /// asset paths give away and which hosts serve the same thing.
+    /// 
+    /// Example: --url-file "hosts.txt" -l "words.txt" --asset-fingerprint
+    #[arg(long)]
+    asset_fingerprint: bool,
+
+    /// Before the run, shakes hands with every https host the targets point to and reports the
+    /// negotiated TLS version and cipher and the certificate (subject, issuer, names, expiry,
+    /// whether it is trusted). An untrusted certificate on a known host can give away a
+    /// middlebox intercepting the traffic. Only available on Linux.
+    /// 
+    /// Example: --url-file "hosts.txt" -l "words.txt" --tls-info
+    #[arg(long)]
+    tls_info: bool,
+
+    /// Sends every payload over both IPv4 and IPv6 and only reports the ones that got different
+    /// answers (status code or word count), since ACLs and WAF rules often differ between the two
+    /// stacks. Targets need a host name with both A and AAAA records.
+    /// 
+    /// Example: -u "https://example.com/admin/##path##" --compare-ip-families
+    #[arg(long, conflicts_with_all = ["proxy", "proxy_list", "rpc", "render_only"])]
+    compare_ip_families: bool,
+
+    /// Sends a plain GET to every target (with the delimited parts of the URL left out) before and
+    /// after the run and reports what changed between the two responses: status, title, body,
+    /// headers, cookies and TLS certificate. A change means the target may have started treating
+    /// the scan differently halfway, e.g. because a WAF kicked in.
+    /// 
+    /// Example: -u "http://example.com/##path##" --fingerprint
+    #[arg(long)]
+    fingerprint: bool,
+
+    /// Pauses the run when responses suddenly start looking like the scan got blocked: most
+    /// responses turning into 403, 429 or 503, challenge pages, captchas or cookie walls. Targets
+    /// that answered like that from the start of the run don't count as blocking.
+    /// 
+    /// Example: -u "http://example.com/##path##" --block-detect
+    #[arg(long)]
+    block_detect: bool,
+
+    /// Seconds to pause for when --block-detect finds the scan blocked.
+    /// 
+    /// Example: --block-detect --block-pause 300
+    #[arg(long, default_value_t = 60, requires = "block_detect")]
+    block_pause: u64,
+
+    /// Switches to another common browser user agent every time the run resumes after a block.
+    /// 
+    /// Example: --block-detect --rotate-user-agent
+    #[arg(long, requires = "block_detect")]
+    rotate_user_agent: bool,
+
+    /// Proxy every request goes through. HTTP proxies work with the reqwest transport, SOCKS5
+    /// proxies with the raw transport. With socks5h:// the proxy resolves the hostnames, for
+    /// internal names only the proxy's network can resolve; with socks5:// they are resolved
+    /// locally.
+    /// 
+    /// Example: -u "http://intranet.corp/##FUZZ##" -l "words.txt" --transport raw --proxy "socks5h://127.0.0.1:1080"
+    #[arg(long)]
+    proxy: Option<String>,
+
+    /// Proxy to switch to when the run resumes after a block. Multiple proxies may be included and
+    /// are used in turn. Requests go out directly until the first block. Only works with the
+    /// reqwest transport.
+    /// 
+    /// Example: --block-detect --rotate-proxy "http://10.0.0.2:3128" --rotate-proxy "http://10.0.0.3:3128"
+    #[arg(long, requires = "block_detect")]
+    rotate_proxy: Vec<String>,
+
+    /// File with a proxy per line. Every request goes through the next proxy of the list, to spread
+    /// the requests of a large scan over several source addresses. Proxies without a scheme are
+    /// taken as HTTP proxies. Only works with the reqwest transport.
+    /// 
+    /// Example: -u "http://example.com/##FUZZ##" -l "words.txt" --proxy-list "proxies.txt"
+    #[arg(long, conflicts_with = "rotate_proxy")]
+    proxy_list: Option<String>,
+
+    /// Non-interactive mode for CI jobs. Prints a JSON progress line to stderr every
+    /// --ci-heartbeat seconds and a JSON summary at the end, caps the amount of printed results
+    /// and the size of printed bodies, strips control characters from bodies, and exits with
+    /// 0 when nothing was reported, 1 when results were reported and 2 when the run failed: its
+    /// options were invalid, it was stopped by --max-errors or Ctrl-C, or every request failed.
+    /// A failed run exits with 2 even when it reported results, as they are incomplete.
+    /// 
+    /// Example: -u "http://example.com" --match-string "stack trace" --ci
+    #[arg(long)]
+    ci: bool,
+
+    /// Hides the progress bar shown on stderr while the run goes on. It is only ever shown when
+    /// stderr is a terminal, and never in --ci mode.
+    /// 
+    /// Example: -u "http://example.com/##path##" -l words.txt --no-progress
+    #[arg(long)]
+    no_progress: bool,
+
+    /// Seconds between two progress lines in --ci mode.
+    /// 
+    /// Example: --ci --ci-heartbeat 30
+    #[arg(long, default_value_t = 10, requires = "ci")]
+    ci_heartbeat: u64,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Lists, shows or deletes stored sessions.
+    Sessions {
+        #[command(subcommand)]
+        action: session::SessionsAction,
+    },
+    /// Renders and sends a single request and prints it in full along with the full response.
+    /// Takes the same options as a normal run, with --word (or --defaults) instead of a wordlist.
+    /// 
+    /// Example: httprepeater one --word admin -u "http://example.com/##path##" -D "##"
+    One {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        options: Vec<String>,
+    },
+    /// Renders the request for --word (or --defaults) and prints it without sending it. With
+    /// --expect it is compared against a golden file instead, failing with a diff when they
+    /// differ, to keep regression tests of complex templates next to their configs.
+    /// 
+    /// Example: httprepeater render --word test --expect golden.txt -u "http://example.com/##path##" -D "##"
+    Render {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        options: Vec<String>,
+    },
+    /// Finds the longest URL, the most headers, the longest header and the largest body the target
+    /// accepts, by binary search. Takes the same options as a normal run, only --url is needed.
+    /// Uses the raw transport unless --transport says otherwise.
+    /// 
+    /// Example: httprepeater limits -u "http://example.com/"
+    Limits {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        options: Vec<String>,
+    },
+    /// Runs the stages of a JSON pipeline config one after the other, each one fed with the words
+    /// or URLs the previous one reported, sharing one session and request budget.
+    /// 
+    /// Example: httprepeater pipeline scan.json
+    Pipeline {
+        config: String,
+    },
+    /// Lists and fetches the built-in wordlists.
+    Wordlists {
+        #[command(subcommand)]
+        action: wordlist_store::WordlistsAction,
+    },
+}
+
+// The run arguments are only optional so that subcommands can be used without them. Clap makes
+// sure they are present whenever no subcommand was given, which is the only time these are used.
+impl Args {
+    fn delim(&self) -> &str {
+        self.delim.as_deref().unwrap_or_default()
+    }
+
+    fn fills_defaults(&self) -> bool {
+        self.defaults || self.benchmark.is_some()
+    }
+
+    fn rounds(&self) -> Option<usize> {
+        self.repeat.or(self.benchmark)
+    }
+
+    // With --duration the time says when the run is over rather than the payloads.
+    fn endless(&self) -> bool {
+        self.forever || (self.duration.is_some() && self.rounds().is_none())
+    }
+
+    fn dumps(&self) -> bool {
+        self.verbose || self.verbose_dir.is_some() || !self.verbose_on.is_empty() || self.save_responses.is_some()
+    }
+
+    fn needs_text_length(&self) -> bool {
+        self.text_length || !self.match_text_length.is_empty() || !self.filter_text_length.is_empty()
+    }
+
+    fn needs_language(&self) -> bool {
+        self.detect_language || !self.match_language.is_empty() || !self.filter_language.is_empty()
+    }
+}
+
+fn parse_tag(tag: &str) -> Result<(String, String), String> {
+    match tag.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), value.to_string())),
+        _ => Err(format!("expected key=value, got \"{}\"", tag)),
+    }
+}
+
+// Runs whatever the command line asks for, a scan or one of the subcommands.
+pub async fn cli(args: Args) {
+    let args = Arc::new(args);
+
+    if let Some(command) = &args.command {
+        match command {
+            Command::Sessions { action } => session::run_command(action).unwrap_or_else(|e| usage::UsageError::new(e).exit(false)),
+            Command::One { options } => {
+                let program = std::env::args().next().unwrap_or_default();
+                one::run(Args::parse_from(std::iter::once(program).chain(options.iter().cloned()))).await
+            }
+            Command::Render { options } => {
+                let program = std::env::args().next().unwrap_or_default();
+                render::run(Args::parse_from(std::iter::once(program).chain(options.iter().cloned())))
+            }
+            Command::Limits { options } => limits::run(options.clone()).await,
+            Command::Pipeline { config } => pipeline::run(config).await,
+            Command::Wordlists { action } => {
+                wordlist_store::run_command(action).await.unwrap_or_else(|e| usage::UsageError::new(e).exit(false))
+            }
+        }
+        return;
+    }
+
+    if let Some(transport) = args.rpc {
+        if let Err(error) = rpc::serve(transport).await {
+            error.exit(false);
+        }
+        return;
+    }
+    if let Err(error) = run(Arc::clone(&args), None).await {
+        error.exit(args.ci);
+    }
+}
+
+// Runs the whole scan. When driven over --rpc or through the library the results also go to the
+// embedding, which can stop the run early.
+async fn run(args: Arc<Args>, embedding: Option<rpc::Embedding>) -> Result<(), usage::UsageError> {
+    if args.ci {
+        ci::install_panic_hook();
+    }
+    usage::check_options(&args)?;
+    // Embedded and --ci runs are repeated on purpose, and --render-only sends nothing.
+    let history = if embedding.is_none() && !args.ci && args.render_only.is_none() {
+        Some(history::RunHistory::check(&args)?)
+    } else {
+        None
+    };
+
+    let payloads = payloads::from_args(&args)?;
+    let targets = get_targets(&args)?;
+    let target_urls: Vec<String> = targets.iter().map(|target| target.text().to_string()).collect();
+    // With --context every target is sent to once per context, as if it were as many targets.
+    let contexts = Arc::new(args.context.clone());
+    let targets = Arc::new(contexts::expand(targets, &contexts));
+    let target_labels = contexts::labels(&targets, &contexts);
+    let sample = payloads.peek();
+    // Every payload is sent to every target, so the queue holds the index of the target with each
+    // payload.
+    let dispatch = payloads::Dispatch::new(payloads, targets.len());
+
+    // Every word gets its own request task, so without --concurrency the whole queue can be in
+    // flight at once. When that is more than the file descriptor limit allows, requests wait for a
+    // free socket instead.
+    // An endless run would otherwise start requests faster than they finish until the memory runs
+    // out. With --rps the timer is what bounds it.
+    let concurrency = args.concurrency.or((args.endless() && args.rps.is_none()).then_some(ENDLESS_CONCURRENCY));
+    let socket_budget = resources::socket_budget();
+    let queued = dispatch.len();
+    if args.render_only.is_none() {
+        resources::warn_over_budget(concurrency.map_or(queued, |concurrency| concurrency.min(queued)), socket_budget);
+    }
+
+    let headers = Arc::new(get_headers(Arc::clone(&args))?);
+    let bodies = Arc::new(get_body(Arc::clone(&args))?);
+    let texts = targets.iter().chain(headers.iter().flat_map(|(key, value)| [key, value])).chain(bodies.iter());
+    usage::check_placeholders(&args, texts, sample.as_ref())?;
+
+    if let Some(dir) = &args.render_only {
+        return corpus::write_all(&args, dir, dispatch, &targets, &headers, &bodies)
+            .map_err(|e| usage::UsageError::new(format!("Could not render the requests to {}: {}", dir, e)));
+    }
+    let js_words = if args.js_discover {
+        js_discover::discover(&target_urls, args.delim()).await
+    } else {
+        Vec::new()
+    };
+    let progress = args.resume.as_ref().map(|path| resume::Progress::open(path, &target_labels)).transpose().map_err(usage::UsageError::new)?;
+    let completed = progress.as_ref().map(resume::Progress::completed);
+    let (queue_sender, queue) = mpsc::channel(QUEUE_SIZE);
+    let harvester = if args.harvest { Some(Arc::new(harvest::Harvester::default())) } else { None };
+    let producer_harvester = harvester.clone();
+    tokio::task::spawn_blocking(move || payloads::produce(dispatch, js_words, producer_harvester, completed, queue_sender));
+    let mut extra_sinks: Vec<Box<dyn output::ResultSink>> = Vec::new();
+    let queue = if args.smart_order {
+        let (queue, feedback) = smart_order::spawn(queue);
+        extra_sinks.push(Box::new(feedback));
+        queue
+    } else {
+        queue
+    };
+    let queue = Arc::new(tokio::sync::Mutex::new(queue));
+
+    if args.asset_fingerprint {
+        assets::report(&target_urls, args.delim()).await;
+    }
+    if args.tls_info {
+        tls_info::report(&target_urls, args.delim(), args.hosts_file.as_ref()).await;
+    }
+    if args.compare_ip_families {
+        families::compare(&args, &targets, &headers, &bodies, queue).await.map_err(usage::UsageError::new)?;
+        return Ok(());
+    }
+    let fingerprints = if args.fingerprint {
+        Some(fingerprint::take_all(&target_urls, args.delim()).await)
+    } else {
+        None
+    };
+
+    let calibration = if args.auto_calibrate {
+        Some(calibrate::Calibration::run(&args, &targets, &headers, &bodies).await.map_err(usage::UsageError::new)?)
+    } else {
+        None
+    };
+
+    let html_baselines = if args.html_diff {
+        htmldiff::baselines(&args, &targets, &headers, &bodies).await.map_err(usage::UsageError::new)?
+    } else {
+        Vec::new()
+    };
+
+    let now = time::Instant::now();
+    let run_stats = embedding.as_ref().map_or_else(Default::default, |embedding| Arc::clone(&embedding.stats));
+    let stop = embedding.as_ref().map_or_else(Default::default, |embedding| Arc::clone(&embedding.stop));
+    let embedded = embedding.is_some();
+    // A run driven over --rpc is stopped by its client instead.
+    let interrupt = match embedding {
+        Some(_) => None,
+        None => Some(tokio::spawn(interrupt::watch(Arc::clone(&stop), Arc::clone(&run_stats)))),
+    };
+
+    let heartbeat = if args.ci {
+        let interval = time::Duration::from_secs(args.ci_heartbeat.max(1));
+        Some(ci::spawn_heartbeat(Arc::clone(&run_stats), queued, now, interval))
+    } else {
+        None
+    };
+    let progress_bar = if args.no_progress || args.ci || embedded {
+        None
+    } else {
+        progress::ProgressBar::start(Arc::clone(&run_stats), queued)
+    };
+
+    let (results_sender, results_receiver) = mpsc::unbounded_channel();
+    if let Some(identify) = args.identify.as_ref().filter(|_| !args.ci) {
+        eprintln!("Every request identifies itself with {}: {}", args.identify_header, identify);
+    }
+    let session = args
+        .session
+        .as_ref()
+        .map(|name| session::Session::open(name, args.identify.as_deref(), redact::Redactor::from_args(&args).as_ref()))
+        .transpose()
+        .map_err(|e| usage::UsageError::new(format!("Could not open the session: {}", e)))?;
+    let matrix = if contexts.is_empty() && !args.matrix {
+        None
+    } else if !contexts.is_empty() && target_urls.len() == 1 {
+        // A single target's columns are better told apart by the names of the contexts alone.
+        Some(matrix::Matrix::new(contexts.iter().map(|context| context.name.clone()).collect()))
+    } else {
+        Some(matrix::Matrix::new(target_labels.clone()))
+    };
+    let webhook = args
+        .result_webhook
+        .as_ref()
+        .map(|url| webhook::Webhook::new(url, args.webhook_filter, &args.tag))
+        .transpose()
+        .map_err(usage::UsageError::new)?;
+    if args.tree {
+        extra_sinks.push(Box::new(tree::Tree::default()));
+    }
+    if args.benchmark.is_some() {
+        extra_sinks.push(Box::new(benchmark::Statuses::default()));
+    }
+    if let Some(embedding) = embedding {
+        extra_sinks.push(Box::new(output::ChannelSink(embedding.results)));
+    }
+    let encryption = encrypt::Encryption::from_args(&args);
+    let output = output::Output::from_args(&args, encryption.as_ref(), extra_sinks)
+        .map_err(|e| usage::UsageError::new(format!("Could not set up the output: {}", e)))?;
+    dump::create_dirs(&args).map_err(usage::UsageError::new)?;
+    // With --context every context has its own rate limit instead, in its lane.
+    let rate_limiter = args
+        .rate
+        .filter(|_| contexts.is_empty())
+        .map(|rate| rate::RateLimiter::new(rate, args.rate_coordination.as_deref()))
+        .transpose()
+        .map_err(usage::UsageError::new)?;
+    let lanes = contexts::Lanes::new(&args, &contexts).map_err(usage::UsageError::new)?;
+    let schedule = Arc::new(load::Schedule::from_args(&args));
+    let proxy_pool = args.proxy_list.as_ref().map(|path| proxies::ProxyPool::load(path, &args, &run_stats)).transpose().map_err(usage::UsageError::new)?;
+    let pause_gate = Arc::new(block::PauseGate::default());
+    let block_detector = if args.block_detect {
+        let pause = time::Duration::from_secs(args.block_pause);
+        Some(block::BlockDetector::new(Arc::clone(&pause_gate), pause, args.rotate_user_agent || !args.rotate_proxy.is_empty()))
+    } else {
+        None
+    };
+    let collector = tokio::spawn(results::collect(
+        Arc::clone(&args),
+        Arc::clone(&run_stats),
+        results_receiver,
+        session,
+        matrix,
+        webhook,
+        block_detector,
+        output,
+        progress,
+        calibration,
+    ));
+
+    let workers = Arc::new(Workers {
+        args: Arc::clone(&args),
+        targets,
+        contexts,
+        lanes,
+        headers,
+        bodies,
+        matcher: matchers::BodyMatcher::new(&args.match_string, &args.match_regex, args.match_context),
+        queue,
+        run_stats: Arc::clone(&run_stats),
+        socket_permits: Semaphore::new(socket_budget.unwrap_or(Semaphore::MAX_PERMITS)),
+        request_permits: Arc::new(Semaphore::new(concurrency.unwrap_or(Semaphore::MAX_PERMITS))),
+        // Finished tasks are let go of as the run goes when there is a limit, and in endless runs
+        // which would keep them all around otherwise.
+        prune_handles: concurrency.is_some() || args.endless(),
+        harvester,
+        stop: Arc::clone(&stop),
+        pause_gate,
+        schedule: Arc::clone(&schedule),
+        rate_limiter,
+        proxy_pool,
+        request_ids: args.request_id_header.as_ref().map(|_| request_id::RequestIds::new()),
+        bandwidth_limiter: args.max_bandwidth.map(|rate| Arc::new(bandwidth::BandwidthLimiter::new(rate))),
+        html_baselines,
+        results: results_sender,
+    });
+    let thread_handles: Vec<_> = (0..args.threads).map(|_| tokio::spawn(work(Arc::clone(&workers)))).collect();
+    // Once every worker is done the last sender of results goes away with them, which lets the
+    // collector finish.
+    drop(workers);
+    for thread_handle in thread_handles {
+        thread_handle.await.unwrap();
+    }
+    let session = collector.await.unwrap();
+    if let Some(progress_bar) = progress_bar {
+        progress_bar.finish();
+    }
+
+    let elapsed = now.elapsed();
+    if let Some(interrupt) = interrupt {
+        interrupt.abort();
+    }
+    let interrupted = stop.load(Ordering::Relaxed);
+    run_stats.print_summary(elapsed, interrupted, &args.tag, &target_labels);
+    if let Some(schedule) = schedule.as_ref() {
+        schedule.print_summary();
+    }
+    run_stats.print_connection_summary();
+    if let Some(before) = fingerprints {
+        let after = fingerprint::take_all(&target_urls, args.delim()).await;
+        fingerprint::print_report(&target_urls, &before, &after);
+    }
+    if let Some(Err(e)) = session.map(|session| session.finish(elapsed)) {
+        eprintln!("Could not close the session: {}", e);
+    }
+    if let Some(history) = history.filter(|_| !interrupted) {
+        history.record(run_stats.reported(), args.session.as_deref());
+    }
+    if let Some(heartbeat) = heartbeat {
+        heartbeat.abort();
+        let exit_code = ci::exit_code(&run_stats, interrupted);
+        ci::print_finished(&run_stats, elapsed, exit_code, interrupted, &args.tag, args.identify.as_deref());
+        std::process::exit(exit_code);
+    }
+    Ok(())
+}
+
+/// What the workers of a run and the request tasks they start share.
+struct Workers {
+    args: Arc<Args>,
+    targets: Arc<Vec<template::Template>>,
+    contexts: Arc<Vec<contexts::Context>>,
+    lanes: contexts::Lanes,
+    headers: Arc<Vec<(template::Template, template::Template)>>,
+    bodies: Arc<Option<template::Template>>,
+    matcher: Option<matchers::BodyMatcher>,
+    queue: Arc<tokio::sync::Mutex<mpsc::Receiver<(usize, payloads::Payload)>>>,
+    run_stats: Arc<stats::RunStats>,
+    socket_permits: Semaphore,
+    request_permits: Arc<Semaphore>,
+    prune_handles: bool,
+    harvester: Option<Arc<harvest::Harvester>>,
+    stop: Arc<AtomicBool>,
+    pause_gate: Arc<block::PauseGate>,
+    schedule: Arc<Option<load::Schedule>>,
+    rate_limiter: Option<rate::RateLimiter>,
+    proxy_pool: Option<proxies::ProxyPool>,
+    request_ids: Option<request_id::RequestIds>,
+    bandwidth_limiter: Option<Arc<bandwidth::BandwidthLimiter>>,
+    html_baselines: Vec<Option<Vec<String>>>,
+    results: mpsc::UnboundedSender<results::RequestResult>,
+}
+
+/// A request a worker took off the queue, handed to the task that sends it.
+// The transport of a worker and the --block-detect rotation it was built for. Failing to build it
+// fails the requests that needed it.
+type WorkerTransport = (usize, Result<Arc<dyn transport::Transport>, String>);
+
+struct Work {
+    target: usize,
+    payload: payloads::Payload,
+    ticket: Option<harvest::Ticket>,
+    transport: WorkerTransport,
+    // With --proxy-list every request goes through the next proxy of the list instead.
+    proxied: Option<Arc<dyn transport::Transport>>,
+    request_id: Option<String>,
+}
+
+// Takes the work off the queue and starts a task for every request, as fast as --concurrency,
+// --rps, --rate and --delay allow.
+async fn work(workers: Arc<Workers>) {
+    let args = &workers.args;
+    let mut async_handles = Vec::new();
+    // Every worker sends its requests through one transport, so that connections and TLS
+    // sessions get reused. It is rebuilt when --block-detect rotates, to pick up the next proxy
+    // and leave the blocked connections behind. With --context there is one per context, so that
+    // no context rides on a connection another one opened.
+    let mut worker_transports: Vec<Option<WorkerTransport>> = vec![None; workers.contexts.len().max(1)];
+    loop {
+        // Taken before the word so that words wait in the queue rather than as tasks.
+        let request_permit = Arc::clone(&workers.request_permits).acquire_owned().await.unwrap();
+        if workers.stop.load(Ordering::Relaxed) {
+            break;
+        }
+        // With --rps requests are started on a timer, the worker waiting for the time of the next
+        // one.
+        if let Some(schedule) = workers.schedule.as_ref() {
+            if !schedule.wait().await {
+                break;
+            }
+        }
+        // Requests are only started as fast as --rate allows, rather than started all at once and
+        // left waiting for a token.
+        if let Some(limiter) = &workers.rate_limiter {
+            limiter.acquire().await;
+        }
+        if let Some(delay) = &args.delay {
+            tokio::time::sleep(delay.pick()).await;
+        }
+        // The lock is only held while waiting for the next item, which never blocks a thread, and
+        // is released right away for the other workers.
+        let next = workers.queue.lock().await.recv().await;
+        let Some((target, payload)) = next else { break };
+        let lane = workers.lanes.of(target);
+        if lane.is_some_and(contexts::Lane::stopped) {
+            continue;
+        }
+        if let Some(limiter) = lane.and_then(|lane| lane.rate_limiter.as_ref()) {
+            limiter.acquire().await;
+        }
+
+        let rotation = workers.pause_gate.rotation();
+        let worker_transport = &mut worker_transports[target % workers.contexts.len().max(1)];
+        if worker_transport.as_ref().is_none_or(|(built_for, _)| *built_for != rotation) {
+            let proxy = workers.pause_gate.proxy(&args.rotate_proxy);
+            *worker_transport = Some((rotation, transport::build(args, &workers.run_stats, proxy).map(Arc::from)));
+        }
+        let work = Work {
+            target,
+            payload,
+            ticket: workers.harvester.as_ref().map(|harvester| harvester.ticket()),
+            transport: worker_transport.clone().unwrap(),
+            proxied: workers.proxy_pool.as_ref().map(proxies::ProxyPool::next),
+            request_id: workers.request_ids.as_ref().map(request_id::RequestIds::next),
+        };
+        let async_handle = tokio::spawn(send(Arc::clone(&workers), work, request_permit));
+
+        // So that only about as many of them as are in flight are ever kept around.
+        if workers.prune_handles {
+            async_handles.retain(|handle: &tokio::task::JoinHandle<()>| !handle.is_finished());
+        }
+        async_handles.push(async_handle);
+    }
+    for async_handle in async_handles {
+        async_handle.await.unwrap();
+    }
+}
+
+// Sends the request of one piece of work, retrying as asked, and hands its result to the
+// collector. The permit of --concurrency is held until the response was read.
+async fn send(workers: Arc<Workers>, work: Work, _request: OwnedSemaphorePermit) {
+    let args = &workers.args;
+    let Work { target: target_index, payload, ticket, transport: (built_for, shared_transport), proxied, request_id } = work;
+    let target = &workers.targets[target_index];
+    let context = contexts::of(&workers.contexts, target_index);
+    // In --defaults and --benchmark modes there is no payload, placeholders are set to their
+    // defaults.
+    let fill = if args.fills_defaults() { None } else { Some(&payload) };
+    let mut rendered = render_request(args, target, &workers.headers, &workers.bodies, fill);
+    if let Some(context) = context {
+        context.apply(&mut rendered);
+    }
+
+    // Sends the response, blocking the thread until receiving a reply.
+    let _socket = workers.socket_permits.acquire().await.unwrap();
+    // While --block-detect has the run paused nothing goes out. A rotation that happened in the
+    // meantime needs a transport going through the new proxy.
+    workers.pause_gate.wait().await;
+    // Requests of a context taken off the queue before it stopped are dropped too.
+    if workers.lanes.of(target_index).is_some_and(contexts::Lane::stopped) {
+        return;
+    }
+    let rotation = workers.pause_gate.rotation();
+    if let Some(user_agent) = workers.pause_gate.user_agent().filter(|_| args.rotate_user_agent) {
+        rendered.headers.retain(|(key, _)| !key.eq_ignore_ascii_case("user-agent"));
+        rendered.headers.push((String::from("User-Agent"), user_agent.to_string()));
+    }
+    if let (Some(name), Some(id)) = (&args.request_id_header, &request_id) {
+        rendered.headers.retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+        rendered.headers.push((name.clone(), id.clone()));
+    }
+    let transport = match proxied {
+        Some(transport) => Ok(transport),
+        None if rotation == built_for => shared_transport,
+        None => transport::build(args, &workers.run_stats, workers.pause_gate.proxy(&args.rotate_proxy)).map(Arc::from),
+    };
+    let sent_url = rendered.url.clone();
+    let request = if args.dumps() { rendered.to_http().ok() } else { None };
+    let fail = |error: transport::TransportError, attempts: u32| {
+        let context_name = context.map(|context| context.name.as_str());
+        results::print_failure(args, &workers.run_stats, &workers.stop, &payload.label(), context_name, request.as_deref(), error.as_ref(), attempts);
+        workers.lanes.fail(target_index, &workers.contexts, args.max_errors, &workers.stop);
+    };
+    let transport = match transport {
+        Ok(transport) => transport,
+        Err(error) => return fail(error.into(), 0),
+    };
+    let started = time::Instant::now();
+    let _in_flight = workers.run_stats.start_request();
+    let mut attempts = 0;
+    let (sent, deadline) = loop {
+        attempts += 1;
+        if let Some(limiter) = &workers.bandwidth_limiter {
+            limiter.consume(rendered.size()).await;
+        }
+        let deadline = args.timeout.map(|timeout| tokio::time::Instant::now() + timeout);
+        let sent = match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline, resources::send_with_backoff(transport.as_ref(), &rendered))
+                .await
+                .unwrap_or_else(|elapsed| Err(Box::new(elapsed))),
+            None => resources::send_with_backoff(transport.as_ref(), &rendered).await,
+        };
+        if attempts > args.retries || !retry::should_retry(&sent, &args.retry_on) {
+            workers.run_stats.retries.record(target_index, attempts, sent.is_err());
+            break (sent, deadline);
+        }
+        // Retries wait their turn with --rate like any other request.
+        tokio::time::sleep(retry::backoff(attempts, sent.as_ref().ok())).await;
+        let lane_limiter = workers.lanes.of(target_index).and_then(|lane| lane.rate_limiter.as_ref());
+        if let Some(limiter) = workers.rate_limiter.as_ref().or(lane_limiter) {
+            limiter.acquire().await;
+        }
+    };
+    let mut resp = match sent {
+        Ok(resp) => resp,
+        Err(error) => return fail(error, attempts),
+    };
+    resp.set_deadline(deadline);
+    if let Some(limiter) = &workers.bandwidth_limiter {
+        limiter.consume(bandwidth::headers_size(&resp.headers)).await;
+        resp.limit(Arc::clone(limiter));
+    }
+
+    // A connection dropped halfway through the body fails the request like one that couldn't be
+    // sent at all.
+    let body = match matchers::read_body(&mut resp, workers.matcher.as_ref(), args).await {
+        Ok(body) => body,
+        Err(error) => return fail(error, attempts),
+    };
+    let duration = started.elapsed();
+    workers.run_stats.latency.record(duration);
+    let text = body.text;
+
+    let signals = if args.score {
+        Some(score::BodySignals::from_body(payload.words(), &text))
+    } else {
+        None
+    };
+    let challenge = challenge::classify(resp.status, &resp.headers, &text);
+    let html_diff = match workers.html_baselines.get(target_index).and_then(Option::as_ref) {
+        Some(baseline) if htmldiff::is_html(&resp.headers, &text) => htmldiff::diff(baseline, &htmldiff::elements(&text)),
+        _ => None,
+    };
+    let text_length = if args.needs_text_length() {
+        Some(text::visible_text_length(&text))
+    } else {
+        None
+    };
+    let language = if args.needs_language() {
+        Some(language::detect(&text::visible_text(&text)))
+    } else {
+        None
+    };
+    if let Some(ticket) = &ticket {
+        ticket.harvest(payload.words(), &text);
+    }
+    let preview = args.preview.map(|max| text::preview(&text, max));
+    let (words, lines) = body.counts.unwrap_or_else(|| (text.split_whitespace().count(), text.lines().count()));
+    let count = args.count_regex.as_ref().map(|regex| regex.find_iter(&text).count());
+
+    workers.results.send(results::RequestResult {
+        url: target.text().to_string(),
+        sent_url,
+        word: payload.label(),
+        target: target_index,
+        attempts,
+        request_id,
+        status: resp.status,
+        length: body.length,
+        words,
+        lines,
+        count,
+        duration,
+        headers: resp.headers,
+        captured_headers: None,
+        request,
+        body: if args.dumps() { Some(text) } else { None },
+        signals,
+        matched: body.matched.as_ref().map(|found| found.pattern.clone()),
+        excerpt: body.matched.and_then(|found| found.excerpt),
+        aborted: body.aborted,
+        peek: body.peek,
+        rotation,
+        challenge,
+        text_length,
+        language,
+        preview,
+        html_diff,
+        source: payload.origin(),
+        context: context.map(|context| context.name.clone()),
+    }).unwrap();
+}
+
+// Builds the request for one word (or for the defaults when there is none) by putting it in place
+// of every delimited part of the URL, headers and body.
+fn render_request(
+    args: &Args,
+    target: &template::Template,
+    headers: &[(template::Template, template::Template)],
+    body_template: &Option<template::Template>,
+    payload: Option<&payloads::Payload>,
+) -> transport::RenderedRequest {
+    let mut rendered = transport::RenderedRequest {
+        method: args.method.clone(),
+        url: urls::render_url(target, payload, args.auto_url_encode),
+        headers: Vec::new(),
+        body: None,
+    };
+    // A header of the same name given with -H takes its place.
+    if let Some(identify) = &args.identify {
+        if !headers.iter().any(|(key, _)| key.text().eq_ignore_ascii_case(&args.identify_header)) {
+            rendered.headers.push((args.identify_header.clone(), identify.clone()));
+        }
+    }
+    for (key, value) in headers.iter() {
+        rendered.headers.push((key.render(payload), value.render(payload)));
+    }
+    if let Some(body) = body_template {
+        rendered.body = Some(body.render(payload).into_bytes());
+    }
+    // A Content-Encoding header given by hand is left as is, so that it can claim another
+    // encoding than the one used.
+    if let (Some(compression), Some(body)) = (args.compress_body, &rendered.body) {
+        rendered.body = Some(compression.compress(body, args.corrupt_compression));
+        if !rendered.headers.iter().any(|(key, _)| key.eq_ignore_ascii_case("content-encoding")) {
+            rendered.headers.push((String::from("Content-Encoding"), compression.name().to_string()));
+        }
+    }
+    rendered
+}
+
+// This loop parses all headers, splits them into key and value, and detects
+// delimiters without replacing them.
+// Headers need to be split into key and value to pass them to the reqwest library.
+// This also verifies that the headers are valid by splitting them into 2 parts.
+fn get_headers(args: Arc<Args>) -> Result<Vec<(template::Template, template::Template)>, usage::UsageError> {
+    let mut headers = Vec::new();
+    for header in &args.header {
+        let splitheader: Vec<&str> = header.split(": ").collect();
+        if splitheader.len() != 2 {
+            return Err(usage::UsageError::new(format!("Header \"{}\" needs to be given as \"Name: value\"", header)));
+        }
+        let key = template::Template::parse(splitheader[0], args.delim());
+        let value = template::Template::parse(splitheader[1], args.delim());
+        usage::check_pairs(&key, "header")?;
+        usage::check_pairs(&value, "header")?;
+        headers.push((key, value));
+    }
+    Ok(headers)
+}
+
+// The URLs requests are sent to: either the one from --url or every line of --url-file. Delimiters
+// are detected in each of them the same way as in the headers and body, so words can be placed in
+// the path or query.
+fn get_targets(args: &Args) -> Result<Vec<template::Template>, usage::UsageError> {
+    let urls = match &args.url_file {
+        Some(path) if !std::path::Path::new(path).is_file() => {
+            return Err(usage::UsageError::new(format!("URL file {} doesn't exist", path)));
+        }
+        Some(path) => {
+            let lines = Arc::new(Mutex::new(Vec::new()));
+            wordlist::load_words_to_memory(path, wordlist::ListEncoding::Utf8, Arc::clone(&lines)).map_err(usage::UsageError::new)?;
+            let urls: Vec<String> = lines
+                .lock()
+                .unwrap()
+                .drain(..)
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty())
+                .collect();
+            if urls.is_empty() {
+                return Err(usage::UsageError::new("The URL file has no URLs"));
+            }
+            urls
+        }
+        None if !args.urls.is_empty() => args.urls.clone(),
+        None => vec![args.url.clone().unwrap_or_default()],
+    };
+
+    let mut targets = Vec::new();
+    for url in urls {
+        let target = template::Template::parse(&url, args.delim());
+        usage::check_pairs(&target, "URL")?;
+        // Checked with a word in every placeholder, which is how it is going to be sent.
+        let sample = urls::render_url(&target, Some(&payloads::Payload::single(String::from("a"))), true);
+        if let Err(e) = reqwest::Url::parse(&sample) {
+            return Err(usage::UsageError::new(format!("Invalid URL {}: {}", url, e)));
+        }
+        targets.push(target);
+    }
+    Ok(targets)
+}
+
+// Parses the body, detecting the delimiters, same as the headers function.
+fn get_body(args: Arc<Args>) -> Result<Option<template::Template>, usage::UsageError> {
+    let mut bodies = None;
+    if let Some(body) = &args.body {
+        let template = template::Template::parse(body, args.delim());
+        usage::check_pairs(&template, "body")?;
+        bodies = Some(template);
+    }
+    Ok(bodies)
+}
@@ -0,0 +1,165 @@
+use clap::Parser;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::failures;
+use crate::fingerprint::canonical_url;
+use crate::stats::RunStats;
+use crate::transport::{self, RenderedRequest, Transport};
+use crate::usage::UsageError;
+use crate::Args;
+
+// Time each probe gets when --timeout wasn't given, a server choking on a probe shouldn't hang
+// the search.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// What gets probed, from the smallest to the largest value tried.
+#[derive(Clone, Copy)]
+enum Dimension {
+    UrlLength,
+    HeaderCount,
+    HeaderSize,
+    BodySize,
+}
+
+const DIMENSIONS: [(Dimension, &str, usize); 4] = [
+    (Dimension::UrlLength, "URL length", 1 << 20),
+    (Dimension::HeaderCount, "Header count", 10_000),
+    (Dimension::HeaderSize, "Header size", 1 << 20),
+    (Dimension::BodySize, "Body size", 16 << 20),
+];
+
+// Finds the largest URL, header count, header and body the target accepts, by binary search.
+// Takes the options of a normal run, only the URL is needed. Delimited parts of the URL are left
+// out and the raw transport is used unless another one is asked for, so that the client's own
+// limits don't get in the way.
+pub async fn run(mut options: Vec<String>) {
+    if !has_any(&options, &["--word", "--defaults", "-l", "--list"]) {
+        options.push(String::from("--defaults"));
+    }
+    if !has_any(&options, &["-D", "--delim"]) {
+        options.extend([String::from("-D"), String::from("##")]);
+    }
+    if !has_any(&options, &["--transport"]) {
+        options.extend([String::from("--transport"), String::from("raw")]);
+    }
+    let program = std::env::args().next().unwrap_or_default();
+    let args = Args::parse_from(std::iter::once(program).chain(options));
+    let url = match &args.url {
+        Some(url) => canonical_url(url, args.delim()),
+        None => UsageError::new("limits probes a single target, use --url").exit(false),
+    };
+    let headers: Vec<(String, String)> = args
+        .header
+        .iter()
+        .filter_map(|header| header.split_once(": ").map(|(key, value)| (key.to_string(), value.to_string())))
+        .collect();
+    let prober = Prober {
+        transport: transport::build(&args, &Arc::new(RunStats::default()), None).unwrap_or_else(|e| UsageError::new(e).exit(false)),
+        url: url.clone(),
+        headers,
+        method: args.method.clone(),
+        timeout: args.timeout.unwrap_or(DEFAULT_TIMEOUT),
+    };
+
+    println!("Limits of {}", url);
+    for (dimension, name, max) in DIMENSIONS {
+        println!("{}: {}", name, prober.search(dimension, max).await);
+    }
+}
+
+fn has_any(options: &[String], flags: &[&str]) -> bool {
+    options.iter().any(|option| flags.iter().any(|flag| option == flag || option.starts_with(&format!("{}=", flag))))
+}
+
+struct Prober {
+    transport: Box<dyn Transport>,
+    url: String,
+    headers: Vec<(String, String)>,
+    method: String,
+    timeout: Duration,
+}
+
+impl Prober {
+    // The probe request for the value, such as a URL of that many characters.
+    fn request(&self, dimension: Dimension, value: usize) -> RenderedRequest {
+        let mut request =
+            RenderedRequest { method: self.method.clone(), url: self.url.clone(), headers: self.headers.clone(), body: None };
+        match dimension {
+            Dimension::UrlLength => {
+                let separator = if self.url.contains('?') { '&' } else { '?' };
+                let prefix = format!("{}{}limit=", self.url, separator);
+                request.url = format!("{}{}", prefix, "A".repeat(value.saturating_sub(prefix.len())));
+            }
+            Dimension::HeaderCount => {
+                let count = value.saturating_sub(self.headers.len());
+                request.headers.extend((0..count).map(|index| (format!("X-Limit-{}", index), String::from("a"))));
+            }
+            Dimension::HeaderSize => request.headers.push((String::from("X-Limit"), "A".repeat(value))),
+            Dimension::BodySize => {
+                if request.method == "GET" {
+                    request.method = String::from("POST");
+                }
+                request.body = Some(vec![b'A'; value]);
+            }
+        }
+        request
+    }
+
+    // Sends the probe and returns the status, or what went wrong.
+    async fn send(&self, request: &RenderedRequest) -> Result<reqwest::StatusCode, String> {
+        let sent = async {
+            let mut response = self.transport.send(request).await?;
+            // The body is read so that a server that only errors out halfway is caught too.
+            response.bytes().await?;
+            Ok::<_, transport::TransportError>(response.status)
+        };
+        match tokio::time::timeout(self.timeout, sent).await {
+            Ok(Ok(status)) => Ok(status),
+            Ok(Err(error)) => Err(failures::describe(error.as_ref())),
+            Err(_) => Err(String::from("timed out")),
+        }
+    }
+
+    // The largest accepted value, along with how the smallest rejected one was turned down.
+    // Anything answered like the smallest probe, or with a success or a redirect, is accepted.
+    async fn search(&self, dimension: Dimension, max: usize) -> String {
+        let smallest = self.minimum(dimension);
+        let baseline = match self.send(&self.request(dimension, smallest)).await {
+            Ok(status) => status,
+            Err(error) => return format!("could not be probed, the smallest request failed: {}", error),
+        };
+        let accepted = |outcome: &Result<reqwest::StatusCode, String>| match outcome {
+            Ok(status) => *status == baseline || status.is_success() || status.is_redirection(),
+            Err(_) => false,
+        };
+
+        let top = self.send(&self.request(dimension, max)).await;
+        if accepted(&top) {
+            return format!("at least {} accepted", max);
+        }
+        let (mut low, mut high, mut rejection) = (smallest, max, top);
+        while high - low > 1 {
+            let middle = low + (high - low) / 2;
+            let outcome = self.send(&self.request(dimension, middle)).await;
+            if accepted(&outcome) {
+                low = middle;
+            } else {
+                (high, rejection) = (middle, outcome);
+            }
+        }
+        let rejection = match rejection {
+            Ok(status) => format!("got {}", status),
+            Err(error) => format!("failed: {}", error),
+        };
+        format!("{} accepted, {} {}", low, high, rejection)
+    }
+
+    fn minimum(&self, dimension: Dimension) -> usize {
+        match dimension {
+            Dimension::UrlLength => self.url.len() + 7,
+            Dimension::HeaderCount => self.headers.len(),
+            Dimension::HeaderSize | Dimension::BodySize => 1,
+        }
+    }
+}
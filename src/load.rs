@@ -0,0 +1,141 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::delay;
+use crate::Args;
+
+// Requests started this long after their time count as late: the client couldn't keep up with
+// --rps.
+const LATE: Duration = Duration::from_millis(10);
+
+/// When the requests of a run with --rps, --ramp and --duration are due. Requests go out on a
+/// timer whether the responses to the earlier ones came back or not (an open model), so a server
+/// slowing down gets more requests in flight rather than fewer requests.
+pub struct Schedule {
+    // Set when the first request is started, setting up the run doesn't make it late.
+    started: OnceLock<Instant>,
+    rps: Option<f64>,
+    ramp: Duration,
+    duration: Option<Duration>,
+    next: AtomicU64,
+    late: AtomicUsize,
+}
+
+impl Schedule {
+    pub fn from_args(args: &Args) -> Option<Schedule> {
+        if args.rps.is_none() && args.duration.is_none() {
+            return None;
+        }
+        Some(Schedule {
+            started: OnceLock::new(),
+            rps: args.rps,
+            ramp: args.ramp.unwrap_or_default(),
+            duration: args.duration,
+            next: AtomicU64::new(0),
+            late: AtomicUsize::new(0),
+        })
+    }
+
+    // Waits for the time of the next request. False once --duration is over, when nothing should
+    // be started anymore.
+    pub async fn wait(&self) -> bool {
+        let started = *self.started.get_or_init(Instant::now);
+        let due = match self.rps {
+            Some(rps) => started + self.due(self.next.fetch_add(1, Ordering::Relaxed), rps),
+            None => Instant::now(),
+        };
+        if self.duration.is_some_and(|duration| due >= started + duration) {
+            return false;
+        }
+        if Instant::now() > due + LATE {
+            self.late.fetch_add(1, Ordering::Relaxed);
+        }
+        tokio::time::sleep_until(due).await;
+        true
+    }
+
+    // Time from the start at which the request of this index is due. The rate climbs in a straight
+    // line from 0 to rps during the ramp, the requests due by then adding up to rps * t² / (2 *
+    // ramp), and holds from there on.
+    fn due(&self, index: u64, rps: f64) -> Duration {
+        let ramp = self.ramp.as_secs_f64();
+        let ramped = rps * ramp / 2.0;
+        let index = index as f64;
+        let seconds = if index < ramped { (2.0 * ramp * index / rps).sqrt() } else { ramp + (index - ramped) / rps };
+        Duration::from_secs_f64(seconds)
+    }
+
+    pub fn print_summary(&self) {
+        let late = self.late.load(Ordering::Relaxed);
+        if let (Some(rps), true) = (self.rps, late > 0) {
+            println!(
+                "Load: {} requests started more than {}ms after their time, the client couldn't hold {} requests/s",
+                late,
+                LATE.as_millis(),
+                rps
+            );
+        }
+    }
+}
+
+pub fn parse_duration(spec: &str) -> Result<Duration, String> {
+    let duration = delay::parse_time(spec)?;
+    if duration.is_zero() {
+        return Err(String::from("the duration needs to be more than 0"));
+    }
+    Ok(duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(rps: f64, ramp: u64, duration: Option<Duration>) -> Schedule {
+        Schedule {
+            started: OnceLock::new(),
+            rps: Some(rps),
+            ramp: Duration::from_secs(ramp),
+            duration,
+            next: AtomicU64::new(0),
+            late: AtomicUsize::new(0),
+        }
+    }
+
+    fn seconds(schedule: &Schedule, index: u64) -> f64 {
+        schedule.due(index, schedule.rps.unwrap()).as_secs_f64()
+    }
+
+    #[test]
+    fn steady_rate() {
+        let steady = schedule(10.0, 0, None);
+        assert_eq!(seconds(&steady, 0), 0.0);
+        assert!((seconds(&steady, 10) - 1.0).abs() < 1e-9);
+        assert!((seconds(&steady, 25) - 2.5).abs() < 1e-9);
+    }
+
+    // Over a 10s ramp to 10 requests/s, 50 requests are due, then one every 100ms.
+    #[test]
+    fn ramp() {
+        let ramped = schedule(10.0, 10, None);
+        assert_eq!(seconds(&ramped, 0), 0.0);
+        assert!((seconds(&ramped, 5) - 10f64.sqrt()).abs() < 1e-9);
+        assert!((seconds(&ramped, 50) - 10.0).abs() < 1e-9);
+        assert!((seconds(&ramped, 60) - 11.0).abs() < 1e-9);
+        for index in 1..100 {
+            assert!(seconds(&ramped, index) > seconds(&ramped, index - 1));
+        }
+    }
+
+    #[tokio::test]
+    async fn nothing_starts_after_the_duration() {
+        let short = schedule(1000.0, 0, Some(Duration::from_millis(10)));
+        let mut started = 0;
+        while short.wait().await {
+            started += 1;
+        }
+        assert_eq!(started, 10);
+        assert!(!short.wait().await);
+    }
+}
@@ -0,0 +1,342 @@
+use regex::bytes::Regex;
+
+use crate::peek::Peek;
+use crate::transport::{TransportError, TransportResponse};
+use crate::Args;
+
+// How much of the previous chunks is kept around when matching a stream. Regex matches longer
+// than this that cross a chunk boundary can be missed.
+const STREAM_OVERLAP: usize = 4096;
+
+/// Substrings and regular expressions looked for in response bodies. A response matches when any
+/// of them is found.
+pub struct BodyMatcher {
+    strings: Vec<String>,
+    regexes: Vec<Regex>,
+    // Bytes of the body kept on each side of a match in its excerpt, none when 0.
+    context: usize,
+}
+
+/// What a body matched: the string or regex that hit, and the part of the body around the hit.
+#[derive(Debug)]
+pub struct Match {
+    pub pattern: String,
+    pub excerpt: Option<String>,
+}
+
+impl BodyMatcher {
+    // Returns None when no body matchers were configured, so that callers can tell "nothing
+    // matched" apart from "nothing to match against".
+    pub fn new(strings: &[String], regexes: &[Regex], context: usize) -> Option<BodyMatcher> {
+        if strings.is_empty() && regexes.is_empty() {
+            return None;
+        }
+        Some(BodyMatcher { strings: strings.to_vec(), regexes: regexes.to_vec(), context })
+    }
+
+    // Returns a short description of the first matcher that was found in the body.
+    pub fn find(&self, body: &[u8]) -> Option<Match> {
+        for string in &self.strings {
+            if let Some(start) = position(body, string.as_bytes()) {
+                return Some(self.found(format!("\"{}\"", string), body, start, start + string.len()));
+            }
+        }
+        for regex in &self.regexes {
+            if let Some(hit) = regex.find(body) {
+                return Some(self.found(format!("/{}/", regex.as_str()), body, hit.start(), hit.end()));
+            }
+        }
+        None
+    }
+
+    fn found(&self, pattern: String, body: &[u8], start: usize, end: usize) -> Match {
+        let excerpt = (self.context > 0).then(|| excerpt(body, start, end, self.context));
+        Match { pattern, excerpt }
+    }
+
+    fn overlap(&self) -> usize {
+        let longest = self.strings.iter().map(|string| string.len()).max().unwrap_or(0);
+        if self.regexes.is_empty() {
+            longest
+        } else {
+            longest.max(STREAM_OVERLAP)
+        }
+    }
+}
+
+/// Runs a BodyMatcher over a body that arrives in chunks, without keeping the whole body.
+pub struct StreamMatcher<'a> {
+    matcher: &'a BodyMatcher,
+    window: Vec<u8>,
+    pub bytes_read: usize,
+}
+
+impl<'a> StreamMatcher<'a> {
+    pub fn new(matcher: &'a BodyMatcher) -> StreamMatcher<'a> {
+        StreamMatcher { matcher, window: Vec::new(), bytes_read: 0 }
+    }
+
+    // Matches the newest chunk together with the tail of the previous ones, so that a match
+    // split across two chunks is still found.
+    pub fn feed(&mut self, chunk: &[u8]) -> Option<Match> {
+        self.bytes_read += chunk.len();
+        self.window.extend_from_slice(chunk);
+
+        let found = self.matcher.find(&self.window);
+        let overlap = self.matcher.overlap();
+        if self.window.len() > overlap {
+            self.window.drain(..self.window.len() - overlap);
+        }
+        found
+    }
+}
+
+/// Counts the words and lines of a body that arrives in chunks, the same as split_whitespace and
+/// lines would over the whole of it.
+#[derive(Default)]
+pub struct TextCounter {
+    words: usize,
+    newlines: usize,
+    in_word: bool,
+    // Whether the text so far ends with a line that has no line break yet.
+    open_line: bool,
+    // Start of a character cut by the end of the last chunk.
+    partial: Vec<u8>,
+}
+
+impl TextCounter {
+    pub fn feed(&mut self, chunk: &[u8]) {
+        let mut bytes = std::mem::take(&mut self.partial);
+        bytes.extend_from_slice(chunk);
+        let complete = bytes.len() - incomplete_tail(&bytes);
+        self.partial = bytes.split_off(complete);
+        self.count(&String::from_utf8_lossy(&bytes));
+    }
+
+    // Words and lines of everything fed.
+    pub fn finish(mut self) -> (usize, usize) {
+        let partial = std::mem::take(&mut self.partial);
+        self.count(&String::from_utf8_lossy(&partial));
+        (self.words, self.newlines + self.open_line as usize)
+    }
+
+    fn count(&mut self, text: &str) {
+        for c in text.chars() {
+            if c.is_whitespace() {
+                self.in_word = false;
+            } else if !self.in_word {
+                self.in_word = true;
+                self.words += 1;
+            }
+            if c == '\n' {
+                self.newlines += 1;
+            }
+            self.open_line = c != '\n';
+        }
+    }
+}
+
+// Length of the UTF-8 character the bytes end in the middle of, 0 when they don't.
+fn incomplete_tail(bytes: &[u8]) -> usize {
+    for back in 1..=bytes.len().min(3) {
+        let byte = bytes[bytes.len() - back];
+        if byte & 0xC0 == 0x80 {
+            continue;
+        }
+        let needed = match byte {
+            0xF0.. => 4,
+            0xE0.. => 3,
+            0xC0.. => 2,
+            _ => 1,
+        };
+        return if needed > back { back } else { 0 };
+    }
+    0
+}
+
+/// A response body as the result needs it, with what the matchers found in it.
+pub struct Body {
+    pub text: String,
+    // Bytes read, even when the text itself wasn't kept.
+    pub length: usize,
+    // Words and lines counted while the body streamed by, which the text doesn't always have.
+    pub counts: Option<(usize, usize)>,
+    pub matched: Option<Match>,
+    // Whether --stream-match dropped the download once something matched.
+    pub aborted: bool,
+    pub peek: Option<Peek>,
+}
+
+// Reads the body of the response. With --peek-bytes only its start is read, and in stream mode it
+// is read chunk by chunk and the download is dropped as soon as a matcher hits, the body itself
+// only kept if something needs it. Dropping the response before the body was fully read closes
+// the connection instead of downloading the rest of it.
+pub async fn read_body(response: &mut TransportResponse, matcher: Option<&BodyMatcher>, args: &Args) -> Result<Body, TransportError> {
+    if let Some(limit) = args.peek_bytes {
+        let mut body = Vec::new();
+        let mut complete = true;
+        while let Some(chunk) = response.chunk().await? {
+            body.extend_from_slice(&chunk);
+            if body.len() >= limit {
+                complete = false;
+                body.truncate(limit);
+                break;
+            }
+        }
+        return Ok(Body {
+            text: String::from_utf8_lossy(&body).to_string(),
+            length: body.len(),
+            counts: None,
+            matched: matcher.and_then(|matcher| matcher.find(&body)),
+            aborted: false,
+            peek: Some(Peek::inspect(&response.headers, &body, complete)),
+        });
+    }
+    match matcher {
+        Some(matcher) if args.stream_match => {
+            let keep_body = args.dumps() || args.score;
+            let mut stream = StreamMatcher::new(matcher);
+            let mut counter = TextCounter::default();
+            let mut body = Vec::new();
+            let mut matched = None;
+            while let Some(chunk) = response.chunk().await? {
+                if keep_body {
+                    body.extend_from_slice(&chunk);
+                }
+                counter.feed(&chunk);
+                matched = stream.feed(&chunk);
+                if matched.is_some() {
+                    break;
+                }
+            }
+            let aborted = matched.is_some();
+            Ok(Body {
+                text: String::from_utf8_lossy(&body).to_string(),
+                length: stream.bytes_read,
+                counts: Some(counter.finish()),
+                matched,
+                aborted,
+                peek: None,
+            })
+        }
+        _ => {
+            let body = response.bytes().await?;
+            Ok(Body {
+                text: String::from_utf8_lossy(&body).to_string(),
+                length: body.len(),
+                counts: None,
+                matched: matcher.and_then(|matcher| matcher.find(&body)),
+                aborted: false,
+                peek: None,
+            })
+        }
+    }
+}
+
+fn position(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+// The match with `context` bytes on each side, on a single line. Characters cut in half at the
+// edges are dropped.
+fn excerpt(body: &[u8], start: usize, end: usize, context: usize) -> String {
+    let from = start.saturating_sub(context);
+    let to = (end + context).min(body.len());
+    let text = String::from_utf8_lossy(&body[from..to]);
+    let text = text.trim_matches('\u{FFFD}');
+    let mut excerpt = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+    if from > 0 {
+        excerpt.insert_str(0, "...");
+    }
+    if to < body.len() {
+        excerpt.push_str("...");
+    }
+    excerpt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(strings: &[&str], regexes: &[&str]) -> BodyMatcher {
+        let strings: Vec<String> = strings.iter().map(|string| string.to_string()).collect();
+        let regexes: Vec<Regex> = regexes.iter().map(|regex| Regex::new(regex).unwrap()).collect();
+        BodyMatcher::new(&strings, &regexes, 0).unwrap()
+    }
+
+    // However the body is cut, a needle is found in the chunk where it ends and not before.
+    #[test]
+    fn needle_split_across_chunks() {
+        let matcher = matcher(&["needle"], &[]);
+        let body = b"haystack needle haystack";
+        for split in 0..=body.len() {
+            let mut stream = StreamMatcher::new(&matcher);
+            let first = stream.feed(&body[..split]);
+            let second = stream.feed(&body[split..]);
+            assert_eq!(first.is_some(), split >= 15, "split at {}", split);
+            assert!(first.or(second).is_some_and(|found| found.pattern == "\"needle\""), "split at {}", split);
+            assert_eq!(stream.bytes_read, body.len());
+        }
+        let mut stream = StreamMatcher::new(&matcher);
+        for byte in body {
+            if stream.feed(std::slice::from_ref(byte)).is_some() {
+                return;
+            }
+        }
+        panic!("needle fed byte by byte not found");
+    }
+
+    // Only what a match can still need is kept between chunks: the longest string, or
+    // STREAM_OVERLAP bytes when there are regexes.
+    #[test]
+    fn window_overlap() {
+        let strings = matcher(&["needle", "pin"], &[]);
+        let mut stream = StreamMatcher::new(&strings);
+        assert!(stream.feed(&[b'x'; 10000]).is_none());
+        assert_eq!(stream.window.len(), 6);
+        assert!(stream.feed(b"needl").is_none());
+        assert!(stream.feed(b"e").is_some());
+
+        let mixed = matcher(&["needle"], &["start[a-z]*end"]);
+        let mut stream = StreamMatcher::new(&mixed);
+        assert!(stream.feed(&[b'x'; 10000]).is_none());
+        assert_eq!(stream.window.len(), STREAM_OVERLAP);
+        let long = [&b"start"[..], &[b'a'; 3000], b"end"].concat();
+        let (head, tail) = long.split_at(2000);
+        assert!(stream.feed(head).is_none());
+        assert!(stream.feed(tail).is_some_and(|found| found.pattern == "/start[a-z]*end/"));
+
+        // A match longer than the overlap that crosses a boundary is missed.
+        let mut stream = StreamMatcher::new(&mixed);
+        let longer = [&b"start"[..], &[b'a'; 2 * STREAM_OVERLAP], b"end"].concat();
+        let (head, tail) = longer.split_at(longer.len() - 10);
+        assert!(stream.feed(head).is_none());
+        assert!(stream.feed(tail).is_none());
+    }
+
+    // Fed in every possible pair of chunks, the counts are those of the whole text.
+    #[test]
+    fn text_counts_across_chunks() {
+        for text in ["", "\n", "one", "one two\nthree\n", "a\u{a0}b\u{2003}c\r\nd", "  é ü\n\n€ 😀x"] {
+            let bytes = text.as_bytes();
+            let expected = (text.split_whitespace().count(), text.lines().count());
+            for split in 0..=bytes.len() {
+                let mut counter = TextCounter::default();
+                counter.feed(&bytes[..split]);
+                counter.feed(&bytes[split..]);
+                assert_eq!(counter.finish(), expected, "{:?} split at {}", text, split);
+            }
+        }
+    }
+
+    #[test]
+    fn characters_cut_at_the_end() {
+        assert_eq!(incomplete_tail("a€".as_bytes()), 0);
+        assert_eq!(incomplete_tail(&"a€".as_bytes()[..3]), 2);
+        assert_eq!(incomplete_tail(&"😀".as_bytes()[..1]), 1);
+        assert_eq!(incomplete_tail(b"\x80\x80"), 0);
+    }
+}
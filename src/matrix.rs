@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::encrypt::{self, Encryption};
+use crate::results::RequestResult;
+
+/// Status and length of every word against every target, or every --context of the targets.
+/// Rows are kept in the order the words first came back in, columns in the order of the URL file
+/// and of the contexts.
+pub struct Matrix {
+    words: Vec<String>,
+    rows: HashMap<String, usize>,
+    targets: Vec<String>,
+    cells: HashMap<(usize, usize), String>,
+}
+
+impl Matrix {
+    pub fn new(targets: Vec<String>) -> Matrix {
+        Matrix { words: Vec::new(), rows: HashMap::new(), targets, cells: HashMap::new() }
+    }
+
+    pub fn record(&mut self, result: &RequestResult) {
+        let row = match self.rows.get(&result.word) {
+            Some(row) => *row,
+            None => {
+                self.words.push(result.word.clone());
+                self.rows.insert(result.word.clone(), self.words.len() - 1);
+                self.words.len() - 1
+            }
+        };
+        self.cells.insert((row, result.target), format!("{}/{}", result.status.as_u16(), result.length));
+    }
+
+    // Prints the matrix as tab separated columns. Targets are numbered in the header and listed
+    // below the table since full URLs would make the columns unreadable.
+    pub fn print(&self) {
+        let header: Vec<String> = (1..=self.targets.len()).map(|n| format!("#{}", n)).collect();
+        println!("\n  word\t{}", header.join("\t"));
+        for (row, word) in self.words.iter().enumerate() {
+            let cells = self.row(row);
+            let marker = if self.diverges(&cells) { "*" } else { " " };
+            println!("{} {}\t{}", marker, word, cells.join("\t"));
+        }
+        println!();
+        for (column, target) in self.targets.iter().enumerate() {
+            println!("#{}: {}", column + 1, target);
+        }
+    }
+
+    pub fn write_csv(&self, path: &str, encryption: Option<&Encryption>) -> io::Result<()> {
+        let mut file = encrypt::create(path, encryption)?;
+        let header: Vec<String> = self.targets.iter().map(|target| csv_field(target)).collect();
+        writeln!(file, "word,{}", header.join(","))?;
+        for (row, word) in self.words.iter().enumerate() {
+            writeln!(file, "{},{}", csv_field(word), self.row(row).join(","))?;
+        }
+        file.finish()
+    }
+
+    fn row(&self, row: usize) -> Vec<String> {
+        (0..self.targets.len())
+            .map(|column| self.cells.get(&(row, column)).cloned().unwrap_or_else(|| String::from("-")))
+            .collect()
+    }
+
+    // A row diverges when the targets (or contexts) didn't all answer with the same status code.
+    fn diverges(&self, cells: &[String]) -> bool {
+        let mut statuses = cells.iter().map(|cell| cell.split('/').next().unwrap_or_default());
+        let first = statuses.next();
+        statuses.any(|status| Some(status) != first)
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        return format!("\"{}\"", value.replace('"', "\"\""));
+    }
+    value.to_string()
+}
@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use crate::encode;
+use crate::redact::Redactor;
+use crate::stats::RunStats;
+use crate::transport::TransportError;
+use crate::usage::UsageError;
+use crate::{ci, failures, get_body, get_headers, get_targets, payloads, placeholders, render_request, transport, Args};
+
+// Sends exactly one request, for --word or for the defaults, and prints both the request and the
+// response in full. Meant for debugging a request or re-testing a single hit.
+pub async fn run(args: Args) {
+    if args.url_file.is_some() {
+        UsageError::new("one sends a single request, use --url instead of --url-file").exit(false);
+    }
+    let args = Arc::new(args);
+    // --word fills every placeholder, whatever its name.
+    let payload = match (&args.word, args.defaults) {
+        (Some(word), _) => Some(payloads::Payload::single(word.clone()).encoded(&Arc::new(encode::Encoding::from_args(&args)))),
+        (None, true) => None,
+        (None, false) => UsageError::new("one needs --word or --defaults").exit(false),
+    };
+
+    let target = get_targets(&args).unwrap_or_else(|e| e.exit(false)).remove(0);
+    let headers = get_headers(Arc::clone(&args)).unwrap_or_else(|e| e.exit(false));
+    let body = get_body(Arc::clone(&args)).unwrap_or_else(|e| e.exit(false));
+    if payload.is_none() {
+        placeholders::check_defaults(&target).unwrap_or_else(|e| e.exit(false));
+    }
+    let rendered = render_request(&args, &target, &headers, &body, payload.as_ref());
+    let redactor = Redactor::from_args(&args);
+    let http = rendered
+        .to_http()
+        .unwrap_or_else(|e| UsageError::new(format!("Invalid URL {}: {}", rendered.url, e)).exit(false));
+    println!("{}", redactor.as_ref().map_or_else(|| http.to_string(), |redactor| redactor.http(&http)));
+
+    let transport = transport::build(&args, &Arc::new(RunStats::default()), None).unwrap_or_else(|e| UsageError::new(e).exit(false));
+    let mut response = transport.send(&rendered).await.unwrap_or_else(|e| fail(e, redactor.as_ref()));
+    let headers = match &redactor {
+        Some(redactor) => redactor.headers(&response.headers),
+        None => response.headers.clone(),
+    };
+    let body = response.bytes().await.unwrap_or_else(|e| fail(e, redactor.as_ref()));
+    let body = String::from_utf8_lossy(&body).to_string();
+    println!("\nHTTP/1.1 {}", response.status);
+    for (name, value) in &headers {
+        println!("{}: {}", name, String::from_utf8_lossy(value.as_bytes()));
+    }
+    println!("\n{}", redactor.as_ref().map_or(body.clone(), |redactor| redactor.text(&body)));
+}
+
+// The request got no complete response, there is nothing to print. The failure is told the same
+// way as the failed requests of a run.
+fn fail(error: TransportError, redactor: Option<&Redactor>) -> ! {
+    let line = format!("Failed ({}). Error: {}", failures::Failure::classify(error.as_ref()), failures::describe(error.as_ref()));
+    eprintln!("{}", redactor.map_or(line.clone(), |redactor| redactor.text(&line)));
+    std::process::exit(ci::EXIT_ERROR);
+}
@@ -0,0 +1,112 @@
+use crate::results::RequestResult;
+
+// Two lengths belong to the same cluster when they are this close to each other. Dynamic pages
+// (timestamps, CSRF tokens, reflected words) wobble by a few bytes between responses.
+fn tolerance(length: usize) -> usize {
+    (length / 50).max(10)
+}
+
+/// Response length ranges that made up a meaningful share of the calibration sample.
+#[derive(Debug)]
+pub struct LengthClusters {
+    dominant: Vec<Cluster>,
+}
+
+#[derive(Debug)]
+struct Cluster {
+    min: usize,
+    max: usize,
+    count: usize,
+}
+
+impl LengthClusters {
+    // Sorts the sampled lengths and groups neighbours closer than the tolerance. Clusters holding
+    // at least 10% of the sample are considered the "normal" responses of the target.
+    pub fn from_sample(lengths: &[usize]) -> LengthClusters {
+        let mut sorted = lengths.to_vec();
+        sorted.sort_unstable();
+
+        let mut clusters: Vec<Cluster> = Vec::new();
+        for length in sorted {
+            match clusters.last_mut() {
+                Some(cluster) if length - cluster.max <= tolerance(cluster.max) => {
+                    cluster.max = length;
+                    cluster.count += 1;
+                }
+                _ => clusters.push(Cluster { min: length, max: length, count: 1 }),
+            }
+        }
+
+        let threshold = (lengths.len() / 10).max(2);
+        LengthClusters {
+            dominant: clusters.into_iter().filter(|cluster| cluster.count >= threshold).collect(),
+        }
+    }
+
+    pub fn is_outlier(&self, length: usize) -> bool {
+        !self.dominant.iter().any(|cluster| {
+            length + tolerance(cluster.min) >= cluster.min && length <= cluster.max + tolerance(cluster.max)
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        if self.dominant.is_empty() {
+            return String::from("none");
+        }
+        self.dominant
+            .iter()
+            .map(|cluster| format!("{}-{} ({} responses)", cluster.min, cluster.max, cluster.count))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+/// Holds back the first results of the run until enough of them have arrived to know what the
+/// target's usual response lengths are, then only lets outliers through.
+pub struct OutlierFilter {
+    sample_size: usize,
+    sample: Vec<RequestResult>,
+    clusters: Option<LengthClusters>,
+}
+
+impl OutlierFilter {
+    pub fn new(sample_size: usize) -> OutlierFilter {
+        OutlierFilter { sample_size, sample: Vec::new(), clusters: None }
+    }
+
+    // Returns the results that should be reported now. During calibration nothing is returned,
+    // and the sampled outliers are released all at once when calibration completes.
+    pub fn push(&mut self, result: RequestResult) -> Vec<RequestResult> {
+        if let Some(clusters) = &self.clusters {
+            return if clusters.is_outlier(result.length) { vec![result] } else { Vec::new() };
+        }
+
+        self.sample.push(result);
+        if self.sample.len() < self.sample_size {
+            return Vec::new();
+        }
+        self.calibrate()
+    }
+
+    // Called once the run ended. If the run was shorter than the sample size, whatever arrived is
+    // used as the sample.
+    pub fn finish(&mut self) -> Vec<RequestResult> {
+        if self.clusters.is_none() {
+            return self.calibrate();
+        }
+        Vec::new()
+    }
+
+    fn calibrate(&mut self) -> Vec<RequestResult> {
+        let lengths: Vec<usize> = self.sample.iter().map(|result| result.length).collect();
+        let clusters = LengthClusters::from_sample(&lengths);
+        eprintln!("Calibrated on {} responses. Dominant lengths: {}", lengths.len(), clusters.describe());
+
+        let outliers = std::mem::take(&mut self.sample)
+            .into_iter()
+            .filter(|result| clusters.is_outlier(result.length))
+            .collect();
+        self.clusters = Some(clusters);
+        outliers
+    }
+}
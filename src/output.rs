@@ -0,0 +1,165 @@
+use clap::ValueEnum;
+use serde_json::Value;
+use std::io::{self, BufWriter, Write};
+use tokio::sync::mpsc;
+
+use crate::database::DbSink;
+use crate::encrypt::{self, Encryption, Sink};
+use crate::formatter::LineTemplate;
+use crate::queues::{BackgroundSink, NatsSink, RedisSink};
+use crate::redact::Redactor;
+use crate::results::{tags_json, RequestResult};
+use crate::score::Score;
+use crate::Args;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// A single JSON array holding every record.
+    Json,
+    /// One JSON record per line.
+    Jsonl,
+}
+
+/// Somewhere the reported results are sent to as structured records.
+pub trait ResultSink: Send {
+    // Shown in error messages.
+    fn name(&self) -> String;
+    fn write(&mut self, record: &Value) -> io::Result<()>;
+    // Called once the run is over, for whatever needs closing.
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+/// Writes every reported result as a structured record to every configured sink. Only the
+/// collector writes to it, so results coming from concurrent workers never interleave.
+pub struct Output {
+    sinks: Vec<Box<dyn ResultSink>>,
+    tags: Vec<(String, String)>,
+}
+
+impl Output {
+    // Sets up the sinks asked for with --output, --output-db and the message queue options, along with the
+    // ones the run needs for itself (--rpc, --smart-order, --tree). Returns None when there is nowhere to
+    // write to.
+    pub fn from_args(
+        args: &Args,
+        encryption: Option<&Encryption>,
+        extra: Vec<Box<dyn ResultSink>>,
+    ) -> io::Result<Option<Output>> {
+        let mut sinks: Vec<Box<dyn ResultSink>> = Vec::new();
+        if let Some(path) = &args.output {
+            sinks.push(Box::new(FileSink::create(path, args.output_format, args.output_template.clone(), encryption)?));
+        }
+        if let Some(path) = &args.output_db {
+            sinks.push(Box::new(DbSink::open(path, &tags_json(&args.tag), Redactor::from_args(args).as_ref())?));
+        }
+        if let Some(url) = &args.output_nats {
+            sinks.push(Box::new(BackgroundSink::spawn(Box::new(NatsSink::connect(url)?))));
+        }
+        if let Some(url) = &args.output_redis {
+            sinks.push(Box::new(BackgroundSink::spawn(Box::new(RedisSink::connect(url)?))));
+        }
+        sinks.extend(extra);
+        if sinks.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Output { sinks, tags: args.tag.clone() }))
+    }
+
+    // A sink failing doesn't stop the run nor the other sinks.
+    pub fn write(&mut self, result: &RequestResult, score: Option<&Score>) {
+        let record = result.to_json(score, &self.tags);
+        for sink in &mut self.sinks {
+            if let Err(e) = sink.write(&record) {
+                eprintln!("Could not write to {}: {}", sink.name(), e);
+            }
+        }
+    }
+
+    pub fn finish(self) {
+        for sink in self.sinks {
+            let name = sink.name();
+            if let Err(e) = sink.finish() {
+                eprintln!("Could not finish writing to {}: {}", name, e);
+            }
+        }
+    }
+}
+
+/// The --output file.
+pub struct FileSink {
+    path: String,
+    file: BufWriter<Sink>,
+    format: OutputFormat,
+    // With --output-template the records are written as lines of text instead, whatever the
+    // format.
+    template: Option<LineTemplate>,
+    records: usize,
+}
+
+impl FileSink {
+    pub fn create(
+        path: &str,
+        format: OutputFormat,
+        template: Option<LineTemplate>,
+        encryption: Option<&Encryption>,
+    ) -> io::Result<FileSink> {
+        let mut file = BufWriter::new(encrypt::create(path, encryption)?);
+        if format == OutputFormat::Json && template.is_none() {
+            file.write_all(b"[")?;
+        }
+        Ok(FileSink { path: path.to_string(), file, format, template, records: 0 })
+    }
+}
+
+impl ResultSink for FileSink {
+    fn name(&self) -> String {
+        self.path.clone()
+    }
+
+    fn write(&mut self, record: &Value) -> io::Result<()> {
+        if let Some(template) = &self.template {
+            writeln!(self.file, "{}", template.render(record))?;
+            self.file.flush()?;
+            self.records += 1;
+            return Ok(());
+        }
+        match self.format {
+            OutputFormat::Json => {
+                let separator = if self.records == 0 { "\n" } else { ",\n" };
+                write!(self.file, "{}  {}", separator, record)?;
+            }
+            // Flushed line by line so the file can be followed while the run goes on.
+            OutputFormat::Jsonl => {
+                writeln!(self.file, "{}", record)?;
+                self.file.flush()?;
+            }
+        }
+        self.records += 1;
+        Ok(())
+    }
+
+    // Closes the JSON array, the file isn't valid JSON before this.
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        if self.format == OutputFormat::Json && self.template.is_none() {
+            self.file.write_all(if self.records == 0 { b"]\n" } else { b"\n]\n" })?;
+        }
+        self.file.into_inner().map_err(|e| e.into_error())?.finish()
+    }
+}
+
+/// Hands the records over as they come to whoever is driving the run in --rpc mode.
+pub struct ChannelSink(pub mpsc::UnboundedSender<Value>);
+
+impl ResultSink for ChannelSink {
+    fn name(&self) -> String {
+        String::from("the RPC client")
+    }
+
+    fn write(&mut self, record: &Value) -> io::Result<()> {
+        self.0.send(record.clone()).map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,691 @@
+use clap::ValueEnum;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+use crate::brute::Brute;
+use crate::encode::Encoding;
+use crate::harvest::Harvester;
+use crate::resume::Completed;
+use crate::usage::{self, UsageError};
+use crate::wordlist::{self, WordStream, Words};
+use crate::{benchmark, delay, placeholders, session, shuffle, sweep, wordlist_store, Args};
+
+/// The words used for a single request, one from each wordlist. Every wordlist can be tied to a
+/// placeholder name, the unnamed one feeds every placeholder no other wordlist was named after.
+#[derive(Debug, Clone)]
+pub struct Payload {
+    names: Arc<[Option<String>]>,
+    words: Vec<String>,
+    // Where the word came from, when it wasn't a wordlist.
+    origin: Option<&'static str>,
+    // What the words go through when they are put in the request.
+    encoding: Arc<Encoding>,
+}
+
+impl Payload {
+    // A payload with a single word that fills every placeholder.
+    pub fn single(word: String) -> Payload {
+        Payload { names: Arc::from(vec![None]), words: vec![word], origin: None, encoding: Arc::default() }
+    }
+
+    // The same payload with its words going through the encoding when they are put in the
+    // request.
+    pub fn encoded(self, encoding: &Arc<Encoding>) -> Payload {
+        Payload { encoding: Arc::clone(encoding), ..self }
+    }
+
+    // A single word found by the run itself rather than read from a wordlist, such as "js" for
+    // --js-discover.
+    pub fn derived(word: String, origin: &'static str) -> Payload {
+        Payload { origin: Some(origin), ..Payload::single(word) }
+    }
+
+    pub fn origin(&self) -> Option<&'static str> {
+        self.origin
+    }
+
+    // Word for the placeholder with this name.
+    pub fn word_for(&self, placeholder: &str) -> Option<&str> {
+        let named = self.names.iter().position(|name| name.as_deref() == Some(placeholder));
+        let index = named.or_else(|| self.names.iter().position(|name| name.is_none()))?;
+        Some(&self.words[index])
+    }
+
+    // What goes in the request for the placeholder: its word with the --prefix and --suffix, run
+    // through the --encode chain.
+    pub fn insert_for(&self, placeholder: &str) -> Option<String> {
+        self.word_for(placeholder).map(|word| self.encoding.apply(word))
+    }
+
+    pub fn words(&self) -> &[String] {
+        &self.words
+    }
+
+    // How the payload is shown in results: the word itself when there is only one wordlist,
+    // otherwise every word along with the name of its wordlist.
+    pub fn label(&self) -> String {
+        if self.words.len() == 1 {
+            return self.words[0].clone();
+        }
+        self.names
+            .iter()
+            .zip(&self.words)
+            .map(|(name, word)| format!("{}={}", name.as_deref().unwrap_or("*"), word))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
+
+// Takes a wordlist given as "path" or "path:NAME". The name ties the list to the ##NAME##
+// placeholders. Anything after the last ':' that isn't a plain name is taken as part of the path,
+// so Windows drive letters keep working. The file is only read once the run knows whether it can
+// be streamed. "brute:CHARSET:MIN-MAX[:NAME]" generates the words instead.
+pub fn load_list(spec: &str, encoding: wordlist::ListEncoding) -> Result<(Option<String>, Words), UsageError> {
+    if let Some(brute) = spec.strip_prefix("brute:") {
+        return load_brute(brute);
+    }
+    let (path, name) = match spec.rsplit_once(':') {
+        Some((path, name)) if is_name(name) && !path.is_empty() => (path, Some(name.to_string())),
+        _ => (spec, None),
+    };
+    if path == "-" {
+        return Ok((name, Words::Stdin { encoding }));
+    }
+    let path = wordlist_store::resolve(path).map_err(UsageError::new)?;
+    Ok((name, Words::File { path, encoding }))
+}
+
+// Lengths like "1-4" would pass for a name, so the last part is only the name when it isn't made
+// of digits.
+fn load_brute(spec: &str) -> Result<(Option<String>, Words), UsageError> {
+    let (brute, name) = match spec.rsplit_once(':') {
+        Some((brute, name)) if is_name(name) && !name.chars().all(|c| c.is_ascii_digit() || c == '-') => {
+            (brute, Some(name.to_string()))
+        }
+        _ => (spec, None),
+    };
+    let brute = Brute::parse(brute).map_err(|e| UsageError::new(format!("Invalid wordlist brute:{}: {}", spec, e)))?;
+    Ok((name, Words::Brute(brute)))
+}
+
+// Whether a --list spec reads the words from stdin, with or without a placeholder name.
+pub fn is_stdin(spec: &str) -> bool {
+    spec == "-" || spec.strip_prefix("-:").is_some_and(is_name)
+}
+
+fn is_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// How the words of several wordlists are combined into payloads.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Every combination of one word from each wordlist.
+    Clusterbomb,
+    /// The first words of every wordlist together, then the second ones and so on, until the
+    /// shortest wordlist runs out.
+    Pitchfork,
+}
+
+enum List {
+    Memory(Vec<String>),
+    Streamed(WordStream),
+}
+
+impl List {
+    fn len(&self) -> usize {
+        match self {
+            List::Memory(words) => words.len(),
+            List::Streamed(words) => words.len,
+        }
+    }
+}
+
+/// Produces the payloads of a run one at a time, so that a large combination of wordlists never
+/// has to be held in memory. Lists only gone through once (the first one of a clusterbomb run,
+/// every one of a pitchfork run) are streamed from disk, the others are repeated and held in
+/// memory.
+pub struct Payloads {
+    names: Arc<[Option<String>]>,
+    lists: Vec<List>,
+    mode: Mode,
+    // Position in each list of the next payload's words, and the words themselves.
+    positions: Vec<usize>,
+    current: Vec<String>,
+    remaining: usize,
+    encoding: Arc<Encoding>,
+    // With -x, every payload is handed out once per extension, the first one being the bare word.
+    // The extension goes to the word of the unnamed wordlist, or of the first one.
+    extensions: Vec<String>,
+    extension: usize,
+    extended_list: usize,
+    // Payloads still to hand out with --limit or --shard, however many the lists have left.
+    budget: Option<usize>,
+    // With --shuffle and --repeat every payload is made up front and handed out from memory.
+    held: Option<Held>,
+}
+
+/// Payloads made up front, handed out round after round.
+struct Held {
+    payloads: Vec<Payload>,
+    next: usize,
+    // Rounds left, the current one included. None goes on until the run is stopped.
+    rounds: Option<usize>,
+}
+
+impl Held {
+    fn len(&self) -> usize {
+        match self.rounds {
+            _ if self.payloads.is_empty() => 0,
+            Some(0) => 0,
+            None => usize::MAX,
+            Some(rounds) => (self.payloads.len() - self.next).saturating_add(self.payloads.len().saturating_mul(rounds.saturating_sub(1))),
+        }
+    }
+}
+
+impl Payloads {
+    // The names of the lists were checked by usage::check_wordlists. Fails when a wordlist can't be
+    // read.
+    pub fn new(lists: Vec<(Option<String>, Words)>, mode: Mode) -> Result<Payloads, UsageError> {
+        let names: Arc<[Option<String>]> = lists.iter().map(|(name, _)| name.clone()).collect();
+        let mut lists: Vec<List> = lists
+            .into_iter()
+            .enumerate()
+            .map(|(index, (_, words))| match mode {
+                Mode::Clusterbomb if index > 0 => words.load().map(List::Memory),
+                _ => words.stream().map(List::Streamed),
+            })
+            .collect::<Result<_, _>>()
+            .map_err(UsageError::new)?;
+        let remaining = match mode {
+            Mode::Clusterbomb => lists
+                .iter()
+                .try_fold(1usize, |product, list| product.checked_mul(list.len()))
+                .ok_or_else(|| UsageError::new("The wordlists make too many payloads to count in clusterbomb mode"))?,
+            Mode::Pitchfork => lists.iter().map(List::len).min().unwrap_or(0),
+        };
+        let current = if remaining == 0 {
+            Vec::new()
+        } else {
+            lists
+                .iter_mut()
+                .map(|list| match list {
+                    List::Memory(words) => words[0].clone(),
+                    List::Streamed(words) => words.next().unwrap_or_default(),
+                })
+                .collect()
+        };
+        Ok(Payloads { names, positions: vec![0; lists.len()], lists, mode, current, remaining, encoding: Arc::default(), extensions: Vec::new(), extension: 0, extended_list: 0, budget: None, held: None })
+    }
+
+    // Every payload gets its words through the encoding when they are put in the request.
+    pub fn encoded(self, encoding: Encoding) -> Payloads {
+        Payloads { encoding: Arc::new(encoding), ..self }
+    }
+
+    // Every payload is also sent with each extension appended to its word, "php" and ".php" both
+    // giving "word.php". Fails when that makes too many payloads to count.
+    pub fn extended(self, extensions: &[String]) -> Result<Payloads, UsageError> {
+        if extensions.is_empty() {
+            return Ok(self);
+        }
+        if self.remaining.checked_mul(extensions.len() + 1).is_none() {
+            return Err(UsageError::new("The wordlists make too many payloads to count with every extension"));
+        }
+        let extensions = std::iter::once(String::new())
+            .chain(extensions.iter().map(|extension| match extension.starts_with(|c: char| c.is_alphanumeric()) {
+                true => format!(".{}", extension),
+                false => extension.clone(),
+            }))
+            .collect();
+        let extended_list = self.names.iter().position(Option::is_none).unwrap_or(0);
+        Ok(Payloads { extensions, extended_list, ..self })
+    }
+
+    // Only hands out a part of the payloads: the shard of this machine, split in contiguous parts
+    // of the same size, then from --skip on within it, at most --limit of them. Skipped words are
+    // still read, streamed lists have no other way to get to the ones after them.
+    pub fn sliced(mut self, skip: usize, limit: Option<usize>, shard: Option<Shard>) -> Payloads {
+        let total = self.len();
+        let (start, end) = match shard {
+            Some(shard) => shard.range(total),
+            None => (0, total),
+        };
+        let start = start.saturating_add(skip).min(end);
+        let end = limit.map_or(end, |limit| end.min(start.saturating_add(limit)));
+        for _ in 0..start {
+            self.forward();
+        }
+        Payloads { budget: Some(end - start), ..self }
+    }
+
+    // Hands the payloads out in an order only the seed decides, which takes holding all of them
+    // in memory.
+    pub fn shuffled(self, seed: u64) -> Payloads {
+        let mut held = self.hold();
+        if let Some(held) = held.held.as_mut() {
+            shuffle::shuffle(&mut held.payloads, seed);
+        }
+        held
+    }
+
+    // Hands the whole set of payloads out again and again, rounds times or until the run is
+    // stopped when None. Every round is in the same order.
+    pub fn repeated(self, rounds: Option<usize>) -> Payloads {
+        if rounds == Some(1) {
+            return self;
+        }
+        let mut held = self.hold();
+        if let Some(held) = held.held.as_mut() {
+            held.rounds = rounds;
+        }
+        held
+    }
+
+    fn hold(mut self) -> Payloads {
+        if self.held.is_some() {
+            return self;
+        }
+        let payloads = self.by_ref().collect();
+        Payloads { held: Some(Held { payloads, next: 0, rounds: Some(1) }), ..self }
+    }
+
+    // The payload that would come next, without moving on.
+    pub fn peek(&self) -> Option<Payload> {
+        if let Some(held) = &self.held {
+            return held.payloads.get(held.next).filter(|_| held.len() > 0).cloned();
+        }
+        if self.remaining == 0 || self.budget == Some(0) {
+            return None;
+        }
+        let mut words = self.current.clone();
+        if let Some(extension) = self.extensions.get(self.extension) {
+            words[self.extended_list].push_str(extension);
+        }
+        Some(Payload {
+            names: Arc::clone(&self.names),
+            words,
+            origin: None,
+            encoding: Arc::clone(&self.encoding),
+        })
+    }
+
+    // Moves a single list on to its next word, wrapping around when it runs out. Streamed lists
+    // never need to wrap.
+    fn step(&mut self, index: usize) -> bool {
+        self.positions[index] += 1;
+        let wrapped = self.positions[index] >= self.lists[index].len();
+        if wrapped {
+            self.positions[index] = 0;
+        }
+        self.current[index] = match &mut self.lists[index] {
+            List::Memory(words) => words[self.positions[index]].clone(),
+            List::Streamed(words) => words.next().unwrap_or_default(),
+        };
+        wrapped
+    }
+
+    fn advance(&mut self) {
+        match self.mode {
+            // Counts like an odometer, the last list turning the fastest.
+            Mode::Clusterbomb => {
+                for index in (0..self.lists.len()).rev() {
+                    if !self.step(index) {
+                        break;
+                    }
+                }
+            }
+            Mode::Pitchfork => (0..self.lists.len()).for_each(|index| {
+                self.step(index);
+            }),
+        }
+    }
+
+    // Moves on to the next payload without making the current one.
+    fn forward(&mut self) {
+        if self.remaining == 0 {
+            return;
+        }
+        self.extension += 1;
+        if self.extension < self.extensions.len() {
+            return;
+        }
+        self.extension = 0;
+        self.remaining -= 1;
+        if self.remaining > 0 {
+            self.advance();
+        }
+    }
+}
+
+impl Iterator for Payloads {
+    type Item = Payload;
+
+    fn next(&mut self) -> Option<Payload> {
+        if let Some(held) = &mut self.held {
+            if held.len() == 0 {
+                return None;
+            }
+            let payload = held.payloads[held.next].clone();
+            held.next += 1;
+            if held.next == held.payloads.len() {
+                held.next = 0;
+                held.rounds = held.rounds.map(|rounds| rounds - 1);
+            }
+            return Some(payload);
+        }
+        let payload = self.peek()?;
+        self.forward();
+        if let Some(budget) = &mut self.budget {
+            *budget -= 1;
+        }
+        Some(payload)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if let Some(held) = &self.held {
+            return (held.len(), Some(held.len()));
+        }
+        let left = (self.remaining * self.extensions.len().max(1) - self.extension).min(self.budget.unwrap_or(usize::MAX));
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for Payloads {}
+
+/// The part of the payloads one machine sends with --shard, "2/4" being the second of four.
+#[derive(Debug, Clone, Copy)]
+pub struct Shard {
+    index: usize,
+    count: usize,
+}
+
+impl Shard {
+    pub fn parse(spec: &str) -> Result<Shard, String> {
+        let (index, count) = spec.split_once('/').ok_or("expected INDEX/COUNT, such as 2/4")?;
+        let index: usize = index.trim().parse().map_err(|_| format!("invalid shard index {}", index))?;
+        let count: usize = count.trim().parse().map_err(|_| format!("invalid shard count {}", count))?;
+        if count == 0 || index == 0 || index > count {
+            return Err(format!("the shard index goes from 1 to the count, {} isn't one of 1 to {}", index, count));
+        }
+        Ok(Shard { index, count })
+    }
+
+    // Start and end of the shard among total payloads. The shards of a run cover all of them
+    // without overlapping, their sizes differing by one at most.
+    fn range(self, total: usize) -> (usize, usize) {
+        let bound = |index: usize| (total as u128 * index as u128 / self.count as u128) as usize;
+        (bound(self.index - 1), bound(self.index))
+    }
+}
+
+/// Hands out every payload for every target, which is the unit of work of the workers.
+pub struct Dispatch {
+    payloads: Payloads,
+    targets: usize,
+    current: Option<Payload>,
+    next_target: usize,
+}
+
+impl Dispatch {
+    pub fn new(payloads: Payloads, targets: usize) -> Dispatch {
+        Dispatch { payloads, targets, current: None, next_target: targets }
+    }
+}
+
+impl Iterator for Dispatch {
+    type Item = (usize, Payload);
+
+    fn next(&mut self) -> Option<(usize, Payload)> {
+        if self.next_target == self.targets {
+            self.current = Some(self.payloads.next()?);
+            self.next_target = 0;
+        }
+        let target = self.next_target;
+        self.next_target += 1;
+        Some((target, self.current.clone()?))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // Saturates with --forever, which has no end.
+        let left = self.payloads.len().saturating_mul(self.targets).saturating_add(self.targets - self.next_target);
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for Dispatch {}
+
+// Makes sure every placeholder of a text has a wordlist to draw from, so that a run fails before
+// sending anything rather than on every request.
+pub fn check_placeholders(template: &crate::template::Template, sample: &Payload) -> Result<(), UsageError> {
+    for inner in template.placeholders() {
+        let name = crate::placeholders::name(inner);
+        if sample.word_for(name).is_none() {
+            return Err(UsageError::new(format!("No wordlist for placeholder {}, add one with -l file:{}", name, name)));
+        }
+    }
+    Ok(())
+}
+
+// The payloads of the run: its wordlists, or the single word of the modes that don't take any,
+// cleaned up, mangled, combined and handed out in the order the options ask for.
+pub fn from_args(args: &Args) -> Result<Payloads, UsageError> {
+    // Every wordlist with the placeholder name it feeds, None feeding the unnamed placeholders.
+    let mut lists: Vec<(Option<String>, Words)> = match &args.from_session {
+        _ if args.defaults => vec![(None, Words::Memory(vec![placeholders::DEFAULTS_WORD.to_string()]))],
+        _ if args.benchmark.is_some() => vec![(None, Words::Memory(vec![benchmark::WORD.to_string()]))],
+        _ if args.word.is_some() => vec![(None, Words::Memory(vec![args.word.clone().unwrap_or_default()]))],
+        _ if args.length_sweep.is_some() => {
+            vec![(None, Words::Memory(args.length_sweep.as_ref().map(sweep::LengthSweep::words).unwrap_or_default()))]
+        }
+        Some(source) => {
+            let words = session::load_session_words(source).map_err(UsageError::new)?;
+            vec![(None, Words::Memory(words))]
+        }
+        None => args
+            .list
+            .iter()
+            .map(|spec| load_list(spec, args.list_encoding))
+            .chain(args.words.iter().map(|(name, words)| Ok((name.clone(), Words::Memory(words.clone())))))
+            .collect::<Result<_, _>>()?,
+    };
+    usage::check_wordlists(&lists)?;
+    let cleanup = wordlist::Cleanup::from_args(args);
+    if cleanup.is_active() {
+        for (_, words) in lists.iter_mut() {
+            let raw = std::mem::replace(words, Words::Memory(Vec::new()));
+            *words = Words::Cleaned { words: Box::new(raw), cleanup };
+        }
+    }
+    if let Some(rules) = &args.rules {
+        let mangled = lists.iter().position(|(name, _)| name.is_none()).unwrap_or(0);
+        if let Some((_, words)) = lists.get_mut(mangled) {
+            let unmangled = std::mem::replace(words, Words::Memory(Vec::new()));
+            *words = Words::Mangled { words: Box::new(unmangled), rules: Arc::clone(rules) };
+        }
+    }
+    let payloads = Payloads::new(lists, args.mode)?
+        .encoded(Encoding::from_args(args))
+        .extended(&args.extensions)?
+        .sliced(args.skip, args.limit, args.shard);
+    let payloads = match args.shuffle {
+        Some(seed) => {
+            let seed = seed.unwrap_or_else(|| {
+                let seed = delay::random();
+                eprintln!("Shuffling with seed {}, --shuffle {} gives the same order again.", seed, seed);
+                seed
+            });
+            payloads.shuffled(seed)
+        }
+        None => payloads,
+    };
+    Ok(match (args.endless(), args.rounds()) {
+        (true, _) => payloads.repeated(None),
+        (false, Some(rounds)) => payloads.repeated(Some(rounds)),
+        (false, None) => payloads,
+    })
+}
+
+// Hands the work out through the queue, in wordlist order and only as fast as the workers take
+// it: the words --js-discover found, the payloads, then the rounds of words --harvest collects
+// from the responses, each for every target. Streamed wordlists are read from disk as the
+// payloads are made, so this blocks. With --resume whatever an earlier run completed is left out.
+pub fn produce(
+    dispatch: Dispatch,
+    js_words: Vec<String>,
+    harvester: Option<Arc<Harvester>>,
+    completed: Option<Completed>,
+    queue: mpsc::Sender<(usize, Payload)>,
+) {
+    let targets = dispatch.targets;
+    let done = |target: usize, payload: &Payload| completed.as_ref().is_some_and(|done| done.is_done(target, &payload.label()));
+    let js_items = js_words
+        .into_iter()
+        .flat_map(|word| (0..targets).map(move |target| (target, Payload::derived(word.clone(), "js"))));
+    for item in js_items.chain(dispatch).filter(|(target, payload)| !done(*target, payload)) {
+        if let Some(harvester) = &harvester {
+            harvester.queued();
+        }
+        // Fails once every worker is gone, after a stop.
+        if queue.blocking_send(item).is_err() {
+            return;
+        }
+    }
+    let Some(harvester) = harvester else { return };
+    while harvester.wait_idle(|| queue.is_closed()) {
+        let words = harvester.next_round();
+        if words.is_empty() {
+            return;
+        }
+        for word in words {
+            for target in 0..targets {
+                let payload = Payload::derived(word.clone(), "harvested");
+                if done(target, &payload) {
+                    continue;
+                }
+                harvester.queued();
+                if queue.blocking_send((target, payload)).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(words: impl IntoIterator<Item = impl ToString>) -> Words {
+        Words::Memory(words.into_iter().map(|word| word.to_string()).collect())
+    }
+
+    fn combined(lists: Vec<(Option<String>, Words)>, mode: Mode) -> Payloads {
+        Payloads::new(lists, mode).unwrap()
+    }
+
+    fn numbers(count: usize) -> Payloads {
+        combined(vec![(None, list(0..count))], Mode::Clusterbomb)
+    }
+
+    fn labels(payloads: impl Iterator<Item = Payload>) -> Vec<String> {
+        payloads.map(|payload| payload.label()).collect()
+    }
+
+    #[test]
+    fn clusterbomb_and_pitchfork() {
+        let lists = || vec![(Some(String::from("a")), list(["x", "y"])), (Some(String::from("b")), list(["1", "2", "3"]))];
+        let clusterbomb = combined(lists(), Mode::Clusterbomb);
+        assert_eq!(clusterbomb.len(), 6);
+        assert_eq!(labels(clusterbomb), ["a=x b=1", "a=x b=2", "a=x b=3", "a=y b=1", "a=y b=2", "a=y b=3"]);
+        let pitchfork = combined(lists(), Mode::Pitchfork);
+        assert_eq!(pitchfork.len(), 2);
+        assert_eq!(labels(pitchfork), ["a=x b=1", "a=y b=2"]);
+    }
+
+    #[test]
+    fn dispatch_sends_every_payload_to_every_target() {
+        let dispatch = Dispatch::new(numbers(2), 3);
+        assert_eq!(dispatch.len(), 6);
+        let items: Vec<(usize, String)> = dispatch.map(|(target, payload)| (target, payload.label())).collect();
+        assert_eq!(items, [(0, "0"), (1, "0"), (2, "0"), (0, "1"), (1, "1"), (2, "1")].map(|(target, word)| (target, word.to_string())));
+        assert_eq!(Dispatch::new(numbers(2).repeated(None), 3).len(), usize::MAX);
+    }
+
+    #[test]
+    fn extensions_multiply_the_payloads() {
+        let extended = numbers(2).extended(&[String::from("php"), String::from("~")]).unwrap();
+        assert_eq!(extended.len(), 6);
+        assert_eq!(labels(extended.skip(1).take(4)), ["0.php", "0~", "1", "1.php"]);
+    }
+
+    #[test]
+    fn skip_and_limit() {
+        assert_eq!(labels(numbers(10).sliced(2, Some(3), None)), ["2", "3", "4"]);
+        assert_eq!(numbers(10).sliced(2, Some(3), None).len(), 3);
+        assert_eq!(labels(numbers(10).sliced(8, Some(5), None)), ["8", "9"]);
+        assert_eq!(numbers(10).sliced(8, Some(5), None).len(), 2);
+        assert_eq!(numbers(10).sliced(10, None, None).len(), 0);
+        assert_eq!(numbers(10).sliced(usize::MAX, Some(usize::MAX), None).count(), 0);
+        assert_eq!(numbers(10).sliced(0, Some(0), None).peek().map(|payload| payload.label()), None);
+    }
+
+    // The shards cover every payload once, their sizes at most one apart.
+    #[test]
+    fn shards_split_the_payloads() {
+        let mut seen = Vec::new();
+        for index in 1..=3 {
+            let shard = Shard::parse(&format!("{}/3", index)).unwrap();
+            let payloads = numbers(10).sliced(0, None, Some(shard));
+            assert!((3..=4).contains(&payloads.len()));
+            seen.extend(labels(payloads));
+        }
+        assert_eq!(seen, labels(numbers(10)));
+        assert_eq!(numbers(2).sliced(0, None, Some(Shard::parse("1/3").unwrap())).len(), 0);
+    }
+
+    #[test]
+    fn skip_and_limit_apply_within_the_shard() {
+        let shard = Shard::parse("2/2").unwrap();
+        assert_eq!(labels(numbers(10).sliced(1, Some(2), Some(shard))), ["6", "7"]);
+        assert_eq!(labels(numbers(10).sliced(9, None, Some(shard))), Vec::<String>::new());
+    }
+
+    #[test]
+    fn invalid_shards() {
+        for spec in ["0/3", "4/3", "1/0", "1", "a/3"] {
+            assert!(Shard::parse(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn held_len() {
+        let held = |count: usize, next: usize, rounds: Option<usize>| Held { payloads: vec![Payload::single(String::new()); count], next, rounds };
+        assert_eq!(held(3, 0, Some(1)).len(), 3);
+        assert_eq!(held(3, 1, Some(2)).len(), 5);
+        assert_eq!(held(3, 2, Some(0)).len(), 0);
+        assert_eq!(held(0, 0, Some(5)).len(), 0);
+        assert_eq!(held(0, 0, None).len(), 0);
+        assert_eq!(held(3, 1, None).len(), usize::MAX);
+        assert_eq!(held(3, 0, Some(usize::MAX)).len(), usize::MAX);
+    }
+
+    #[test]
+    fn repeated_rounds() {
+        let repeated = numbers(3).sliced(1, None, None).repeated(Some(2));
+        assert_eq!(repeated.len(), 4);
+        assert_eq!(labels(repeated), ["1", "2", "1", "2"]);
+        assert_eq!(numbers(3).repeated(None).len(), usize::MAX);
+    }
+
+    #[test]
+    fn payload_counts_that_overflow_are_rejected() {
+        // 2^60 words.
+        let brute = || Words::Brute(Brute::parse("0123456789abcdef:15").unwrap());
+        let lists = vec![(None, brute()), (Some(String::from("n")), list(0..32))];
+        assert!(Payloads::new(lists, Mode::Clusterbomb).is_err());
+        let extensions: Vec<String> = (0..16).map(|number| number.to_string()).collect();
+        assert!(combined(vec![(None, brute())], Mode::Clusterbomb).extended(&extensions).is_err());
+    }
+}
@@ -0,0 +1,63 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+// Fragments that identify well known kinds of pages from the first bytes of their body.
+const SIGNATURES: [(&str, &str); 14] = [
+    ("directory listing", "<title>Index of /"),
+    ("php source", "<?php"),
+    ("spring whitelabel error", "Whitelabel Error Page"),
+    ("django debug", "You're seeing this error because you have <code>DEBUG = True</code>"),
+    ("python traceback", "Traceback (most recent call last)"),
+    ("java stack trace", "at java."),
+    ("asp.net error", "Server Error in '/' Application"),
+    ("tomcat", "Apache Tomcat"),
+    ("iis default", "IIS Windows Server"),
+    ("nginx default", "Welcome to nginx!"),
+    ("apache default", "<h1>It works!</h1>"),
+    ("wordpress", "/wp-content/"),
+    ("git config", "[core]\n\trepositoryformatversion"),
+    ("env file", "DB_PASSWORD="),
+];
+
+/// What could be learned about a response from the first bytes of its body.
+#[derive(Debug)]
+pub struct Peek {
+    pub length_class: String,
+    pub title: Option<String>,
+    pub signatures: Vec<&'static str>,
+}
+
+impl Peek {
+    // The full length is only known when the server sent a Content-Length or when the whole body
+    // fit in the peeked bytes. Otherwise all that can be said is that it's at least that long.
+    pub fn inspect(headers: &reqwest::header::HeaderMap, bytes: &[u8], complete: bool) -> Peek {
+        let content_length = headers
+            .get(reqwest::header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<usize>().ok());
+        let length_class = match content_length {
+            Some(length) => length.to_string(),
+            None if complete => bytes.len().to_string(),
+            None => format!(">={}", bytes.len()),
+        };
+
+        let text = String::from_utf8_lossy(bytes);
+        Peek {
+            length_class,
+            title: title(&text),
+            signatures: SIGNATURES
+                .iter()
+                .filter(|(_, fragment)| text.contains(fragment))
+                .map(|(name, _)| *name)
+                .collect(),
+        }
+    }
+}
+
+fn title(text: &str) -> Option<String> {
+    static TITLE: OnceLock<Regex> = OnceLock::new();
+    let regex = TITLE.get_or_init(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap());
+    regex.captures(text).map(|captures| {
+        captures[1].split_whitespace().collect::<Vec<&str>>().join(" ")
+    })
+}
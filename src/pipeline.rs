@@ -0,0 +1,159 @@
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::embed::{parse_args, Runner};
+use crate::usage::UsageError;
+
+// How often the shared request budget is checked while a stage runs.
+const BUDGET_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Where a stage gets its input from the results of the stage before it.
+#[derive(Debug)]
+enum Feed {
+    // The reported words become the wordlist, feeding the placeholders with this name if any.
+    Words(Option<String>),
+    // The URLs the reported requests went to become the targets, each put in place of {url} in
+    // the pattern.
+    Urls(String),
+}
+
+impl Feed {
+    fn parse(feed: &str) -> Result<Feed, UsageError> {
+        match feed.split_once(':') {
+            None if feed == "words" => Ok(Feed::Words(None)),
+            None if feed == "urls" => Ok(Feed::Urls(String::from("{url}"))),
+            Some(("words", name)) => Ok(Feed::Words(Some(name.to_string()))),
+            Some(("urls", pattern)) if pattern.contains("{url}") => Ok(Feed::Urls(pattern.to_string())),
+            _ => Err(UsageError::new(format!(
+                "Invalid feed \"{}\", use words, words:NAME, urls or urls:PATTERN with {{url}} in the pattern",
+                feed
+            ))),
+        }
+    }
+}
+
+struct Stage {
+    name: String,
+    options: Vec<String>,
+    feed: Option<Feed>,
+}
+
+/// Runs that follow each other, each one fed with what the one before reported: paths found by a
+/// discovery run get their parameters fuzzed by the next, and so on. Every stage shares the same
+/// session and request budget. The config is a JSON file:
+///
+/// {"session": "acme", "max_requests": 20000, "stages": [
+///   {"name": "paths", "options": ["-u", "https://example.com/##p##", "-D", "##", "-l", "paths.txt", "--match-status", "200-399"]},
+///   {"name": "params", "feed": "urls:{url}?##q##=1", "options": ["-D", "##", "-l", "params.txt", "--filter-status", "400"]},
+///   {"name": "methods", "feed": "urls", "options": ["-D", "##", "-m", "PUT", "--defaults"]}
+/// ]}
+struct Pipeline {
+    session: Option<String>,
+    max_requests: Option<usize>,
+    stages: Vec<Stage>,
+}
+
+impl Pipeline {
+    fn load(path: &str) -> Result<Pipeline, UsageError> {
+        let text = crate::wordlist::read_text(path).map_err(|e| UsageError::new(format!("Could not read {}: {}", path, e)))?;
+        let config: Value = serde_json::from_str(&text).map_err(|e| UsageError::new(format!("Invalid pipeline {}: {}", path, e)))?;
+        let invalid = |what: &str| UsageError::new(format!("Invalid pipeline {}: {}", path, what));
+
+        let mut stages = Vec::new();
+        for (number, stage) in config["stages"].as_array().ok_or_else(|| invalid("\"stages\" needs to be an array"))?.iter().enumerate() {
+            let options = stage["options"]
+                .as_array()
+                .and_then(|options| options.iter().map(|option| option.as_str().map(str::to_string)).collect::<Option<Vec<String>>>())
+                .ok_or_else(|| invalid("the \"options\" of every stage need to be an array of strings"))?;
+            let feed = match stage["feed"].as_str() {
+                _ if number == 0 => None,
+                Some(feed) => Some(Feed::parse(feed)?),
+                None => return Err(invalid("every stage but the first needs a \"feed\"")),
+            };
+            let name = stage["name"].as_str().map_or_else(|| (number + 1).to_string(), str::to_string);
+            stages.push(Stage { name, options, feed });
+        }
+        if stages.is_empty() {
+            return Err(invalid("there are no stages"));
+        }
+        Ok(Pipeline {
+            session: config["session"].as_str().map(str::to_string),
+            max_requests: config["max_requests"].as_u64().map(|max| max as usize),
+            stages,
+        })
+    }
+}
+
+// Runs every stage of the pipeline in order. The pipeline ends early when a stage reports
+// nothing, leaving the next one without input, or when the request budget is used up.
+pub async fn run(path: &str) {
+    let pipeline = Pipeline::load(path).unwrap_or_else(|e| e.exit(false));
+    let mut reported: Vec<Value> = Vec::new();
+    let mut sent = 0;
+    let count = pipeline.stages.len();
+    for (number, stage) in pipeline.stages.into_iter().enumerate() {
+        let (words, urls) = match &stage.feed {
+            None => (Vec::new(), Vec::new()),
+            Some(Feed::Words(name)) => (vec![(name.clone(), unique(&reported, "word"))], Vec::new()),
+            Some(Feed::Urls(pattern)) => {
+                let urls = unique(&reported, "sent_url").iter().map(|url| pattern.replace("{url}", url)).collect();
+                (Vec::new(), urls)
+            }
+        };
+        let mut argv = vec![String::from("httprepeater")];
+        argv.extend(stage.options);
+        if let Some(session) = pipeline.session.as_ref().filter(|_| !argv.iter().any(|option| option == "--session")) {
+            argv.extend([String::from("--session"), session.clone()]);
+        }
+        let args = parse_args(argv, words, urls).unwrap_or_else(|e| {
+            UsageError::new(format!("Stage {}: {}", stage.name, e)).exit(false)
+        });
+
+        println!("Stage {}/{} ({})", number + 1, count, stage.name);
+        let budget = pipeline.max_requests.map(|max| max.saturating_sub(sent));
+        let (runner, mut results) = Runner::start_args(args);
+        reported.clear();
+        let mut ticker = tokio::time::interval(BUDGET_CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                record = results.next() => match record {
+                    Some(record) => reported.push(record),
+                    None => break,
+                },
+                _ = ticker.tick() => {
+                    if budget.is_some_and(|budget| runner.requests_sent() >= budget) {
+                        runner.stop();
+                    }
+                }
+            }
+        }
+        sent += runner.requests_sent();
+        if let Err(e) = runner.wait().await {
+            UsageError::new(format!("Stage {} failed: {}", stage.name, e)).exit(false);
+        }
+
+        println!("Stage {} reported {} results", stage.name, reported.len());
+        if number + 1 == count {
+            break;
+        }
+        if pipeline.max_requests.is_some_and(|max| sent >= max) {
+            println!("The request budget of {} is used up, stopping the pipeline", pipeline.max_requests.unwrap_or_default());
+            break;
+        }
+        if reported.is_empty() {
+            println!("Nothing to feed the next stage with, stopping the pipeline");
+            break;
+        }
+    }
+}
+
+// The values of a field of the records, each one once, in the order they were reported.
+fn unique(records: &[Value], field: &str) -> Vec<String> {
+    let mut values: Vec<String> = Vec::new();
+    for value in records.iter().filter_map(|record| record[field].as_str()) {
+        if !values.iter().any(|seen| seen == value) {
+            values.push(value.to_string());
+        }
+    }
+    values
+}
@@ -0,0 +1,37 @@
+use crate::payloads::Payload;
+use crate::template::Template;
+use crate::usage::UsageError;
+
+// Separates the name of a placeholder from its default value, as in ##FUZZ:=guest##.
+const DEFAULT_SEPARATOR: &str = ":=";
+
+// Word shown for the single request of a --defaults run.
+pub const DEFAULTS_WORD: &str = "(defaults)";
+
+// Name of a placeholder, which picks the wordlist it draws from.
+pub fn name(inner: &str) -> &str {
+    inner.split_once(DEFAULT_SEPARATOR).map_or(inner, |(name, _)| name)
+}
+
+// Text a placeholder is replaced with. That is the payload's word for the placeholder, encoded
+// with --encode, or in --defaults mode (no payload) the default the placeholder declares. A
+// placeholder without a default can't be filled in that mode, check_defaults turns those runs
+// down.
+pub fn fill(inner: &str, payload: Option<&Payload>) -> String {
+    match payload {
+        Some(payload) => payload.insert_for(name(inner)).unwrap_or_default(),
+        None => inner.split_once(DEFAULT_SEPARATOR).map(|(_, default)| default.to_string()).unwrap_or_default(),
+    }
+}
+
+// Makes sure every placeholder of a text declares a default, so that a --defaults run fails before
+// sending anything rather than halfway.
+pub fn check_defaults(template: &Template) -> Result<(), UsageError> {
+    match template.placeholders().into_iter().find(|inner| !inner.contains(DEFAULT_SEPARATOR)) {
+        Some(inner) => Err(UsageError::new(format!(
+            "Placeholder \"{}\" has no default, declare one as NAME{}value",
+            inner, DEFAULT_SEPARATOR
+        ))),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,130 @@
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+use crate::stats::RunStats;
+
+// How often the bar is redrawn.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
+// Width of the bar itself, in characters.
+const BAR_WIDTH: usize = 30;
+// Weight of the newest measurement in the requests per second shown, smoothing out the jumps.
+const RATE_SMOOTHING: f64 = 0.3;
+
+// Whether a bar is on screen, so that whatever else gets printed can take it off first.
+static SHOWN: AtomicBool = AtomicBool::new(false);
+
+/// A progress bar on the last line of stderr: completed and total requests, requests per second,
+/// failed requests and the time left. Results are printed above it.
+pub struct ProgressBar {
+    handle: JoinHandle<()>,
+}
+
+impl ProgressBar {
+    // Only shown on a terminal, anything else reading stderr would get the redraws as garbage.
+    pub fn start(stats: Arc<RunStats>, total: usize) -> Option<ProgressBar> {
+        if !std::io::stderr().is_terminal() || !enable_escape_codes() {
+            return None;
+        }
+        let handle = tokio::spawn(async move {
+            let started = Instant::now();
+            let mut ticker = tokio::time::interval(REDRAW_INTERVAL);
+            let mut last = (started, 0);
+            let mut rate = 0.0;
+            loop {
+                ticker.tick().await;
+                let completed = stats.requests_sent.load(Ordering::Relaxed).saturating_sub(stats.in_flight());
+                let now = Instant::now();
+                let seconds = now.duration_since(last.0).as_secs_f64();
+                if seconds > 0.0 {
+                    // completed comes from two counters read one after the other, it can go down
+                    // a little between ticks.
+                    let current = completed.saturating_sub(last.1) as f64 / seconds;
+                    rate = if last.1 == 0 { current } else { rate + RATE_SMOOTHING * (current - rate) };
+                }
+                last = (now, completed);
+                draw(completed, total, rate, stats.failures.total(), started.elapsed());
+            }
+        });
+        Some(ProgressBar { handle })
+    }
+
+    pub fn finish(self) {
+        self.handle.abort();
+        clear();
+    }
+}
+
+// Windows consoles only act on the escape codes redrawing the bar once asked to, and those older
+// than Windows 10 can't be. The bar is left out there.
+#[cfg(windows)]
+fn enable_escape_codes() -> bool {
+    use windows_sys::Win32::System::Console::{GetConsoleMode, GetStdHandle, SetConsoleMode, ENABLE_VIRTUAL_TERMINAL_PROCESSING, STD_ERROR_HANDLE};
+    // SAFETY: the console mode of stderr is read into a local and written back with one more flag.
+    unsafe {
+        let handle = GetStdHandle(STD_ERROR_HANDLE);
+        let mut mode = 0;
+        GetConsoleMode(handle, &mut mode) != 0
+            && (mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0 || SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0)
+    }
+}
+
+#[cfg(not(windows))]
+fn enable_escape_codes() -> bool {
+    true
+}
+
+// Takes the bar off the screen until its next redraw.
+pub fn clear() {
+    if SHOWN.swap(false, Ordering::Relaxed) {
+        eprint!("\r\x1b[2K");
+    }
+}
+
+fn draw(completed: usize, total: usize, rate: f64, failed: usize, elapsed: Duration) {
+    let line = if total == usize::MAX {
+        // A --forever run has no total to fill a bar or count down to.
+        format!("\r\x1b[2K{} requests, {:.1} req/s, {} failed, running for {}", completed, rate, failed, format_duration(elapsed))
+    } else {
+        bar(completed, total, rate, failed, elapsed)
+    };
+    let mut stderr = std::io::stderr().lock();
+    let _ = stderr.write_all(line.as_bytes());
+    let _ = stderr.flush();
+    SHOWN.store(true, Ordering::Relaxed);
+}
+
+fn bar(completed: usize, total: usize, rate: f64, failed: usize, elapsed: Duration) -> String {
+    // Harvested and discovered words can make the run longer than planned.
+    let total = total.max(completed).max(1);
+    let filled = completed * BAR_WIDTH / total;
+    // The time left is from the average rate, steadier than the current one.
+    let average = completed as f64 / elapsed.as_secs_f64().max(0.001);
+    let eta = if completed == 0 || average == 0.0 {
+        String::from("-")
+    } else {
+        format_duration(Duration::from_secs_f64((total - completed) as f64 / average))
+    };
+    format!(
+        "\r\x1b[2K[{}{}] {}/{} ({}%) {:.1} req/s, {} failed, ETA {}",
+        "#".repeat(filled),
+        "-".repeat(BAR_WIDTH - filled),
+        completed,
+        total,
+        completed * 100 / total,
+        rate,
+        failed,
+        eta
+    )
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..60 => format!("{}s", seconds),
+        60..3600 => format!("{}m{:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h{:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
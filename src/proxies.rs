@@ -0,0 +1,51 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::stats::RunStats;
+use crate::transport::{self, Transport};
+use crate::{socks, wordlist, Args};
+
+/// The proxies of --proxy-list, each with its own transport, handed out in turn so that the
+/// requests of a run are spread over all of them.
+pub struct ProxyPool {
+    transports: Vec<Arc<dyn Transport>>,
+    next: AtomicUsize,
+}
+
+impl ProxyPool {
+    // Reads the proxy list, one proxy per line. Empty lines and lines starting with '#' are
+    // skipped, and proxies without a scheme are taken as HTTP proxies.
+    pub fn load(path: &str, args: &Args, stats: &Arc<RunStats>) -> Result<ProxyPool, String> {
+        let content = wordlist::read_text(path).map_err(|e| format!("Could not read the proxy list {}: {}", path, e))?;
+        let transports = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| if line.contains("://") { line.to_string() } else { format!("http://{}", line) })
+            .map(|proxy| {
+                check(&proxy).map_err(|e| format!("Invalid proxy {} in {}: {}", proxy, path, e))?;
+                let transport = transport::build(args, stats, Some(&proxy)).map_err(|e| format!("Invalid proxy {} in {}: {}", proxy, path, e))?;
+                Ok(Arc::from(transport))
+            })
+            .collect::<Result<Vec<Arc<dyn Transport>>, String>>()?;
+        if transports.is_empty() {
+            return Err(format!("The proxy list {} has no proxies in it", path));
+        }
+        println!("Rotating through {} proxies", transports.len());
+        Ok(ProxyPool { transports, next: AtomicUsize::new(0) })
+    }
+
+    // Transport going through the next proxy of the list.
+    pub fn next(&self) -> Arc<dyn Transport> {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.transports.len();
+        Arc::clone(&self.transports[index])
+    }
+}
+
+// Proxies go through reqwest, which only takes HTTP(S) proxies here.
+pub fn check(proxy: &str) -> Result<(), String> {
+    if socks::is_socks(proxy) {
+        return Err(String::from("SOCKS proxies are only supported by the raw transport, add --transport raw"));
+    }
+    reqwest::Proxy::all(proxy).map(drop).map_err(|e| e.to_string())
+}
@@ -0,0 +1,195 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Token bucket limiting how many requests go out per second. Up to a second worth of requests can
+/// go out at once after an idle period.
+pub enum RateLimiter {
+    Local { rate: f64, bucket: Mutex<Bucket> },
+    // The bucket lives in a file that every instance coordinating on it reads and updates under
+    // an exclusive lock, so that they all share one budget. If the file can't be used, the run
+    // goes on with a bucket of its own.
+    Shared { rate: f64, path: PathBuf, warned: AtomicBool, fallback: Mutex<Option<Bucket>> },
+}
+
+pub struct Bucket {
+    tokens: f64,
+    // Unix time of the last refill, in seconds.
+    refilled: f64,
+}
+
+impl Bucket {
+    // Refills the bucket for the time that passed and takes a token if there is one. Otherwise
+    // returns how long to wait for the next one.
+    fn take(&mut self, rate: f64, now: f64) -> Option<Duration> {
+        let burst = rate.max(1.0);
+        self.tokens = (self.tokens + (now - self.refilled).max(0.0) * rate).min(burst);
+        self.refilled = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+        }
+    }
+}
+
+impl RateLimiter {
+    // coordination is the value of --rate-coordination, only "file:PATH" is supported. Both are
+    // checked by usage::check_options first.
+    pub fn new(rate: f64, coordination: Option<&str>) -> Result<RateLimiter, String> {
+        if rate <= 0.0 {
+            return Err(String::from("--rate needs to be more than 0"));
+        }
+        match coordination {
+            None => Ok(RateLimiter::Local { rate, bucket: Mutex::new(Bucket { tokens: rate.max(1.0), refilled: now() }) }),
+            Some(spec) => Ok(RateLimiter::Shared {
+                rate,
+                path: PathBuf::from(coordination_file(spec)?),
+                warned: AtomicBool::new(false),
+                fallback: Mutex::new(None),
+            }),
+        }
+    }
+
+    // Waits until a request is allowed to go out.
+    pub async fn acquire(&self) {
+        loop {
+            let wait = match self {
+                RateLimiter::Local { rate, bucket } => bucket.lock().unwrap().take(*rate, now()),
+                RateLimiter::Shared { rate, path, warned, fallback } => {
+                    let local = fallback.lock().unwrap().as_mut().map(|bucket| bucket.take(*rate, now()));
+                    if let Some(wait) = local {
+                        wait
+                    } else {
+                        let (rate, shared_path) = (*rate, path.clone());
+                        let taken = tokio::task::spawn_blocking(move || take_shared(&shared_path, rate))
+                            .await
+                            .map_err(io::Error::other)
+                            .and_then(|taken| taken);
+                        match taken {
+                            Ok((wait, shared_rate)) => {
+                                if shared_rate != rate && !warned.swap(true, Ordering::Relaxed) {
+                                    eprintln!(
+                                        "The rate coordination file was set up for {} requests per second, that rate \
+                                        is used instead of --rate {}.",
+                                        shared_rate, rate
+                                    );
+                                }
+                                wait
+                            }
+                            // Only the first worker to fail reports it, the others find the fallback set.
+                            Err(e) => {
+                                let mut fallback = fallback.lock().unwrap();
+                                if fallback.is_none() {
+                                    eprintln!(
+                                        "Could not use the rate coordination file {}: {}. Going on with --rate {} for \
+                                        this instance only.",
+                                        path.display(),
+                                        e,
+                                        rate
+                                    );
+                                }
+                                fallback.get_or_insert_with(|| Bucket { tokens: 0.0, refilled: now() }).take(rate, now())
+                            }
+                        }
+                    }
+                }
+            };
+            match wait {
+                None => return,
+                Some(wait) => tokio::time::sleep(wait).await,
+            }
+        }
+    }
+}
+
+// Path of the file of a --rate-coordination spec.
+pub fn coordination_file(spec: &str) -> Result<&str, String> {
+    spec.strip_prefix("file:").ok_or_else(|| format!("Unsupported rate coordination {}, use file:PATH", spec))
+}
+
+// Takes a token from the bucket stored in the file, creating it when it doesn't exist yet. The
+// file holds "tokens refilled rate"; the rate of whoever created it wins so that every instance
+// refills the bucket the same way. Returns the wait for the next token, if any, and that rate.
+fn take_shared(path: &PathBuf, rate: f64) -> io::Result<(Option<Duration>, f64)> {
+    let mut file: File = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+    file.lock()?;
+
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    let fields: Vec<f64> = content.split_whitespace().filter_map(|field| field.parse().ok()).collect();
+    let (mut bucket, rate) = match fields[..] {
+        [tokens, refilled, shared_rate] if shared_rate > 0.0 => (Bucket { tokens, refilled }, shared_rate),
+        _ => (Bucket { tokens: rate.max(1.0), refilled: now() }, rate),
+    };
+    let wait = bucket.take(rate, now());
+
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    write!(file, "{} {} {}", bucket.tokens, bucket.refilled, rate)?;
+    file.unlock()?;
+    Ok((wait, rate))
+}
+
+fn now() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn full(rate: f64) -> Bucket {
+        Bucket { tokens: rate.max(1.0), refilled: 100.0 }
+    }
+
+    #[test]
+    fn burst_then_wait() {
+        let mut bucket = full(5.0);
+        for _ in 0..5 {
+            assert_eq!(bucket.take(5.0, 100.0), None);
+        }
+        let wait = bucket.take(5.0, 100.0).unwrap();
+        assert!((wait.as_secs_f64() - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn refill() {
+        let mut bucket = Bucket { tokens: 0.0, refilled: 100.0 };
+        assert_eq!(bucket.take(5.0, 100.4), None);
+        assert_eq!(bucket.take(5.0, 100.4), None);
+        assert!(bucket.take(5.0, 100.4).is_some());
+    }
+
+    // However long the bucket sat unused, it never holds more than a second of requests.
+    #[test]
+    fn burst_is_capped() {
+        let mut bucket = Bucket { tokens: 0.0, refilled: 0.0 };
+        for _ in 0..5 {
+            assert_eq!(bucket.take(5.0, 1000.0), None);
+        }
+        assert!(bucket.take(5.0, 1000.0).is_some());
+    }
+
+    #[test]
+    fn rate_below_one() {
+        let mut bucket = full(0.5);
+        assert_eq!(bucket.take(0.5, 100.0), None);
+        let wait = bucket.take(0.5, 100.0).unwrap();
+        assert!((wait.as_secs_f64() - 2.0).abs() < 1e-9);
+        assert_eq!(bucket.take(0.5, 102.0), None);
+    }
+
+    // A clock stepping back, or another instance's clock in a shared file, doesn't add tokens.
+    #[test]
+    fn time_going_back() {
+        let mut bucket = Bucket { tokens: 0.0, refilled: 100.0 };
+        assert!(bucket.take(5.0, 50.0).is_some());
+        assert!(bucket.take(5.0, 50.1).is_some());
+        assert_eq!(bucket.take(5.0, 50.2), None);
+    }
+}
@@ -0,0 +1,145 @@
+use regex::Regex;
+use reqwest::header::{HeaderMap, HeaderValue};
+
+use crate::results::RequestResult;
+use crate::Args;
+
+const MASK: &str = "[REDACTED]";
+
+/// Masks sensitive values in everything the program shows or writes: the values of some headers
+/// and whatever matches some patterns. Requests still go out with the real values.
+pub struct Redactor {
+    // Lowercase, header names are case insensitive.
+    headers: Vec<String>,
+    patterns: Vec<Regex>,
+}
+
+impl Redactor {
+    pub fn from_args(args: &Args) -> Option<Redactor> {
+        if args.redact.is_empty() && args.redact_regex.is_empty() {
+            return None;
+        }
+        Some(Redactor {
+            headers: args.redact.iter().map(|name| name.trim().to_lowercase()).collect(),
+            patterns: args.redact_regex.clone(),
+        })
+    }
+
+    // Masks what the patterns match.
+    pub fn text(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for pattern in &self.patterns {
+            text = pattern.replace_all(&text, MASK).into_owned();
+        }
+        text
+    }
+
+    fn is_redacted(&self, name: &str) -> bool {
+        self.headers.iter().any(|header| header.eq_ignore_ascii_case(name.trim()))
+    }
+
+    pub fn headers(&self, headers: &HeaderMap) -> HeaderMap {
+        let mut redacted = HeaderMap::new();
+        for (name, value) in headers {
+            let value = if self.is_redacted(name.as_str()) {
+                HeaderValue::from_static(MASK)
+            } else {
+                let text = self.text(&String::from_utf8_lossy(value.as_bytes()));
+                HeaderValue::from_str(&text).unwrap_or_else(|_| value.clone())
+            };
+            redacted.append(name, value);
+        }
+        redacted
+    }
+
+    // Masks raw HTTP text, a request or a response: the values of the header lines first, then
+    // the patterns over all of it.
+    pub fn http(&self, http: &str) -> String {
+        let (head, body) = http.split_once("\r\n\r\n").map_or((http, None), |(head, body)| (head, Some(body)));
+        let mut lines: Vec<String> = Vec::new();
+        for line in head.split("\r\n") {
+            match line.split_once(':') {
+                Some((name, _)) if self.is_redacted(name) => lines.push(format!("{}: {}", name, MASK)),
+                _ => lines.push(line.to_string()),
+            }
+        }
+        let mut http = lines.join("\r\n");
+        if let Some(body) = body {
+            http.push_str("\r\n\r\n");
+            http.push_str(body);
+        }
+        self.text(&http)
+    }
+
+    // Masks a command line before it gets stored: the values of the redacted headers wherever
+    // they are given (-H "Name: value", --header=, the headers of a --context), then the patterns
+    // over every argument. A header value runs up to the next option of a --context.
+    pub fn arguments(&self, arguments: &[String]) -> Vec<String> {
+        let names: Vec<String> = self.headers.iter().map(|name| regex::escape(name)).collect();
+        let values = (!names.is_empty())
+            .then(|| format!(r#"(?i)(^|[\s'"=]|-H)({})(\s*:)(?:[^ ]| +[^ -])*"#, names.join("|")))
+            .and_then(|pattern| Regex::new(&pattern).ok());
+        arguments
+            .iter()
+            .map(|argument| match &values {
+                Some(values) => self.text(&values.replace_all(argument, format!("${{1}}${{2}}${{3}} {}", MASK))),
+                None => self.text(argument),
+            })
+            .collect()
+    }
+
+    // Masks everything of a result that gets shown, logged or exported.
+    pub fn result(&self, result: &mut RequestResult) {
+        result.url = self.text(&result.url);
+        result.sent_url = self.text(&result.sent_url);
+        result.word = self.text(&result.word);
+        result.headers = self.headers(&result.headers);
+        result.request = result.request.as_deref().map(|request| self.http(request));
+        result.body = result.body.as_deref().map(|body| self.text(body));
+        result.preview = result.preview.as_deref().map(|preview| self.text(preview));
+        result.excerpt = result.excerpt.as_deref().map(|excerpt| self.text(excerpt));
+    }
+}
+
+// A command line as it can be stored, masked when the run redacts anything.
+pub fn stored_arguments(arguments: Vec<String>, redactor: Option<&Redactor>) -> Vec<String> {
+    match redactor {
+        Some(redactor) => redactor.arguments(&arguments),
+        None => arguments,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactor(headers: &[&str], patterns: &[&str]) -> Redactor {
+        Redactor {
+            headers: headers.iter().map(|name| name.to_lowercase()).collect(),
+            patterns: patterns.iter().map(|pattern| Regex::new(pattern).unwrap()).collect(),
+        }
+    }
+
+    fn arguments(redactor: &Redactor, arguments: &[&str]) -> Vec<String> {
+        redactor.arguments(&arguments.iter().map(|argument| argument.to_string()).collect::<Vec<String>>())
+    }
+
+    #[test]
+    fn header_values_in_arguments() {
+        let redactor = redactor(&["Authorization", "cookie"], &[]);
+        assert_eq!(
+            arguments(&redactor, &["-H", "authorization: Bearer abc", "-HCookie:a=b", "--header=Cookie: a=b; c=d", "-H", "Accept: */*"]),
+            ["-H", "authorization: [REDACTED]", "-HCookie: [REDACTED]", "--header=Cookie: [REDACTED]", "-H", "Accept: */*"]
+        );
+        assert_eq!(
+            arguments(&redactor, &["--context", "admin:-H Cookie: session=A -H X-Role: admin", "--redact", "Authorization"]),
+            ["--context", "admin:-H Cookie: [REDACTED] -H X-Role: admin", "--redact", "Authorization"]
+        );
+    }
+
+    #[test]
+    fn patterns_in_arguments() {
+        let redactor = redactor(&[], &["token=[a-z0-9]+"]);
+        assert_eq!(arguments(&redactor, &["-u", "http://example.com/?token=abc123&a=b"]), ["-u", "http://example.com/?[REDACTED]&a=b"]);
+    }
+}
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use crate::encode;
+use crate::redact::Redactor;
+use crate::usage::UsageError;
+use crate::{ci, get_body, get_headers, get_targets, payloads, placeholders, render_request, Args};
+
+// Renders the request for --word (or the defaults) without sending it. With --expect the request
+// is compared against a stored golden file, and the run fails with a diff when they differ.
+pub fn run(args: Args) {
+    let args = Arc::new(args);
+    let payload = match (&args.word, args.defaults) {
+        (Some(word), _) => Some(payloads::Payload::single(word.clone()).encoded(&Arc::new(encode::Encoding::from_args(&args)))),
+        (None, true) => None,
+        (None, false) => UsageError::new("render needs --word or --defaults").exit(false),
+    };
+
+    let target = get_targets(&args).unwrap_or_else(|e| e.exit(false)).remove(0);
+    let headers = get_headers(Arc::clone(&args)).unwrap_or_else(|e| e.exit(false));
+    let body = get_body(Arc::clone(&args)).unwrap_or_else(|e| e.exit(false));
+    if payload.is_none() {
+        placeholders::check_defaults(&target).unwrap_or_else(|e| e.exit(false));
+    }
+    let rendered = render_request(&args, &target, &headers, &body, payload.as_ref());
+    let http = rendered
+        .to_http()
+        .unwrap_or_else(|e| UsageError::new(format!("Invalid URL {}: {}", rendered.url, e)).exit(false));
+    // Secrets are kept out of golden files just like out of the output.
+    let http = Redactor::from_args(&args).map_or(http.clone(), |redactor| redactor.http(&http));
+
+    let Some(path) = &args.expect else {
+        print!("{}", http);
+        return;
+    };
+    if args.update_expected {
+        std::fs::write(path, &http).unwrap_or_else(|e| UsageError::new(format!("Could not write {}: {}", path, e)).exit(false));
+        println!("Wrote {}", path);
+        return;
+    }
+    let expected = crate::wordlist::read_text(path).unwrap_or_else(|e| {
+        UsageError::new(format!("Could not read {}: {} (--update-expected writes it)", path, e)).exit(false)
+    });
+    // Line endings are left out of the comparison, golden files edited by hand rarely keep CRLF.
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = http.lines().collect();
+    if expected == actual {
+        println!("The request matches {}", path);
+        return;
+    }
+    println!("The request differs from {}:", path);
+    for line in diff(&expected, &actual) {
+        println!("{}", line);
+    }
+    std::process::exit(ci::EXIT_FINDINGS);
+}
+
+// A line diff in the style of diff -u without hunks: "-" for lines only expected, "+" for lines
+// only rendered, " " for lines in both. Requests are short, so the quadratic table is fine.
+fn diff(expected: &[&str], actual: &[&str]) -> Vec<String> {
+    // common[i][j] is the length of the longest common subsequence of expected[i..] and actual[j..].
+    let mut common = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            common[i][j] = if expected[i] == actual[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            lines.push(format!(" {}", expected[i]));
+            i += 1;
+            j += 1;
+        } else if j == actual.len() || (i < expected.len() && common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(format!("-{}", expected[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+{}", actual[j]));
+            j += 1;
+        }
+    }
+    lines
+}
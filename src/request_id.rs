@@ -0,0 +1,23 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::fingerprint::fnv1a;
+
+/// Hands out the IDs of --request-id-header: a prefix unique to the run followed by the number of
+/// the request, so that IDs never repeat within a run and are unlikely to across runs.
+pub struct RequestIds {
+    prefix: String,
+    next: AtomicUsize,
+}
+
+impl RequestIds {
+    pub fn new() -> RequestIds {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+        let seed = format!("{}-{}", nanos, std::process::id());
+        RequestIds { prefix: format!("{:016x}", fnv1a(seed.as_bytes())), next: AtomicUsize::new(1) }
+    }
+
+    pub fn next(&self) -> String {
+        format!("{}-{:06}", self.prefix, self.next.fetch_add(1, Ordering::Relaxed))
+    }
+}
@@ -0,0 +1,98 @@
+use std::error::Error;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::transport::{RenderedRequest, Transport, TransportError, TransportResponse};
+
+// File descriptors kept aside for everything that isn't a request socket: stdio, the wordlist,
+// output files, the runtime's own handles.
+#[cfg(unix)]
+const RESERVED_FDS: usize = 64;
+
+// Errors the OS returns when it refuses to hand out another socket.
+#[cfg(unix)]
+const EXHAUSTION_ERRORS: [i32; 4] = [libc::EMFILE, libc::ENFILE, libc::EADDRNOTAVAIL, libc::ENOBUFS];
+#[cfg(not(unix))]
+const EXHAUSTION_ERRORS: [i32; 0] = [];
+
+static WARNED_EXHAUSTION: AtomicBool = AtomicBool::new(false);
+
+// Raises the soft file descriptor limit as far as the hard limit allows and returns the number of
+// sockets that can safely be open at the same time. None means the platform has no such limit.
+#[cfg(unix)]
+pub fn socket_budget() -> Option<usize> {
+    let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    // SAFETY: getrlimit only writes into the struct it is given.
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0 {
+        return None;
+    }
+
+    let wanted = limit.rlim_max.min(1 << 20);
+    if limit.rlim_cur < wanted {
+        let raised = libc::rlimit { rlim_cur: wanted, rlim_max: limit.rlim_max };
+        // SAFETY: setrlimit only reads the struct it is given.
+        if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &raised) } == 0 {
+            limit.rlim_cur = wanted;
+        }
+    }
+    Some((limit.rlim_cur as usize).saturating_sub(RESERVED_FDS).max(1))
+}
+
+#[cfg(not(unix))]
+pub fn socket_budget() -> Option<usize> {
+    None
+}
+
+// Tells when more requests could be in flight than the file descriptor limit allows sockets for,
+// usize::MAX standing for an endless run without a bound.
+pub fn warn_over_budget(in_flight: usize, budget: Option<usize>) {
+    let Some(budget) = budget else { return };
+    if in_flight == usize::MAX {
+        eprintln!(
+            "An endless --rps run has no bound on the requests in flight, the file descriptor limit only allows \
+            about {}. Requests over the limit will be queued.",
+            budget
+        );
+    } else if in_flight > budget {
+        eprintln!(
+            "Up to {} requests could be in flight but the file descriptor limit only allows about {}. \
+            Requests over the limit will be queued.",
+            in_flight, budget
+        );
+    }
+}
+
+// Sends a request, waiting and trying again whenever the OS refuses to open another socket. Any
+// other error is returned as is.
+pub async fn send_with_backoff(
+    transport: &dyn Transport,
+    request: &RenderedRequest,
+) -> Result<TransportResponse, TransportError> {
+    let mut delay = Duration::from_millis(100);
+    loop {
+        match transport.send(request).await {
+            Err(error) if is_resource_exhaustion(error.as_ref()) => {
+                if !WARNED_EXHAUSTION.swap(true, Ordering::Relaxed) {
+                    eprintln!("The OS is refusing new sockets ({}), requests will wait for free ones.", error);
+                }
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(Duration::from_secs(2));
+            }
+            other => return other,
+        }
+    }
+}
+
+fn is_resource_exhaustion(error: &(dyn Error + 'static)) -> bool {
+    let mut source: Option<&(dyn Error + 'static)> = Some(error);
+    while let Some(current) = source {
+        if let Some(io_error) = current.downcast_ref::<io::Error>() {
+            if io_error.raw_os_error().is_some_and(|code| EXHAUSTION_ERRORS.contains(&code)) {
+                return true;
+            }
+        }
+        source = current.source();
+    }
+    false
+}
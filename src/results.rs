@@ -0,0 +1,425 @@
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::block::BlockDetector;
+use crate::calibrate::Calibration;
+use crate::challenge::{Challenge, ChallengeTally};
+use crate::ci;
+use crate::dump::{self, Dumper};
+use crate::encrypt::Encryption;
+use crate::failures::{self, Failure};
+use crate::filters::ResponseFilter;
+use crate::matrix::Matrix;
+use crate::outliers::OutlierFilter;
+use crate::output::Output;
+use crate::peek::Peek;
+use crate::progress;
+use crate::redact::Redactor;
+use crate::resume::Progress;
+use crate::score;
+use crate::session::Session;
+use crate::stats::RunStats;
+use crate::webhook::{Webhook, WebhookFilter};
+use crate::Args;
+
+/// Everything the workers learned from a single request. Workers don't print anything
+/// themselves, they send these to the collector which decides what gets shown and when.
+#[derive(Debug)]
+pub struct RequestResult {
+    pub url: String,
+    // The URL the request went to, with the words filled in.
+    pub sent_url: String,
+    pub word: String,
+    // Index of the target the request was made for.
+    pub target: usize,
+    // How many times the request was sent, more than once with --retries.
+    pub attempts: u32,
+    // The ID sent in the --request-id-header header.
+    pub request_id: Option<String>,
+    pub status: reqwest::StatusCode,
+    pub length: usize,
+    // Words and lines of the body, which move less than its length on pages that embed the word
+    // or a timestamp.
+    pub words: usize,
+    pub lines: usize,
+    // How many times --count-regex matched in the body.
+    pub count: Option<usize>,
+    pub duration: Duration,
+    pub headers: reqwest::header::HeaderMap,
+    // The headers asked for with --capture-headers, by the names they were asked with. Set by
+    // the collector once the headers are redacted.
+    pub captured_headers: Option<Vec<(String, String)>>,
+    // The request as HTTP text, only kept for the dumps of --verbose.
+    pub request: Option<String>,
+    // Only kept around when it is going to be displayed, bodies can get big.
+    pub body: Option<String>,
+    pub signals: Option<score::BodySignals>,
+    // Which body matcher hit, if any.
+    pub matched: Option<String>,
+    // The body around the match, unless --match-context is 0.
+    pub excerpt: Option<String>,
+    // Whether the download was stopped early by --stream-match. The length is then the amount of
+    // bytes read until the match.
+    pub aborted: bool,
+    // Set in --peek-bytes mode, where the length only covers the peeked bytes.
+    pub peek: Option<Peek>,
+    // How many times the run had been paused by --block-detect when the request was sent.
+    pub rotation: usize,
+    // Set when the response is a challenge or block page rather than an answer of the application.
+    pub challenge: Option<Challenge>,
+    // Length of the visible text of the body, with --text-length or its filters.
+    pub text_length: Option<usize>,
+    // Language of the visible text, with --detect-language or its filters.
+    pub language: Option<&'static str>,
+    // Name of the --context the request was sent in.
+    pub context: Option<String>,
+    // Start of the body on one line, with --preview.
+    pub preview: Option<String>,
+    // Elements added and removed compared to the baseline, with --html-diff.
+    pub html_diff: Option<String>,
+    // Set when the word didn't come from a wordlist but from --js-discover or --harvest.
+    pub source: Option<&'static str>,
+}
+
+impl RequestResult {
+    // The result as a structured record, for the outputs and --result-webhook.
+    pub fn to_json(&self, score: Option<&score::Score>, tags: &[(String, String)]) -> serde_json::Value {
+        serde_json::json!({
+            "url": self.url,
+            "sent_url": self.sent_url,
+            "word": self.word,
+            "source": self.source,
+            "status": self.status.as_u16(),
+            "length": self.length,
+            "words": self.words,
+            "lines": self.lines,
+            "count": self.count,
+            "duration_ms": self.duration.as_millis() as u64,
+            "matched": self.matched,
+            "excerpt": self.excerpt,
+            "aborted": self.aborted,
+            "attempts": self.attempts,
+            "request_id": self.request_id,
+            "title": self.peek.as_ref().and_then(|peek| peek.title.clone()),
+            "text_length": self.text_length,
+            "language": self.language,
+            "context": self.context,
+            "preview": self.preview,
+            "html_diff": self.html_diff,
+            "challenge": self.challenge.map(|challenge| challenge.to_string()),
+            "score": score.map(|score| score.value),
+            "reasons": score.map(|score| score.reasons.clone()),
+            "tags": tags_json(tags),
+            "headers": self.captured_headers.as_ref().map(|headers| tags_json(headers)),
+            "location": self.headers.get(reqwest::header::LOCATION).map(|location| String::from_utf8_lossy(location.as_bytes())),
+        })
+    }
+
+    // Keeps the values of the listed headers, several values of the same header joined by commas.
+    fn capture_headers(&mut self, names: &[String]) {
+        let mut captured = Vec::new();
+        for name in names {
+            let values: Vec<String> = self
+                .headers
+                .get_all(name.trim())
+                .iter()
+                .map(|value| String::from_utf8_lossy(value.as_bytes()).to_string())
+                .collect();
+            if !values.is_empty() {
+                captured.push((name.trim().to_string(), values.join(", ")));
+            }
+        }
+        self.captured_headers = Some(captured);
+    }
+}
+
+// The --tag pairs of the run, or any other pairs, as a JSON object.
+pub fn tags_json(tags: &[(String, String)]) -> serde_json::Value {
+    tags.iter().map(|(key, value)| (key.clone(), serde_json::Value::from(value.as_str()))).collect()
+}
+
+// Receives the results of every request until all workers are done. Results are printed as soon
+// as they arrive, except in score mode where they need to be compared against each other first.
+// The session is handed back once everything was recorded so the run can be closed in it.
+#[allow(clippy::too_many_arguments)]
+pub async fn collect(
+    args: Arc<Args>,
+    stats: Arc<RunStats>,
+    mut receiver: mpsc::UnboundedReceiver<RequestResult>,
+    mut session: Option<Session>,
+    mut matrix: Option<Matrix>,
+    mut webhook: Option<Webhook>,
+    mut block_detector: Option<BlockDetector>,
+    mut output: Option<Output>,
+    mut progress: Option<Progress>,
+    calibration: Option<Calibration>,
+) -> Option<Session> {
+    let mut held: Vec<RequestResult> = Vec::new();
+    let mut challenges = ChallengeTally::default();
+    let response_filter = ResponseFilter::from_args(&args, calibration);
+    let redactor = Redactor::from_args(&args);
+    let mut dumper = Dumper::from_args(&args);
+    let mut outliers = if args.auto_filter_outliers {
+        Some(OutlierFilter::new(args.outlier_sample))
+    } else {
+        None
+    };
+
+    while let Some(mut result) = receiver.recv().await {
+        // Recorded before redaction, the state file needs the words as they were sent.
+        if let Some(progress) = progress.as_mut() {
+            progress.record(&result);
+        }
+        // Nothing past this point gets to see the sensitive values.
+        if let Some(redactor) = &redactor {
+            redactor.result(&mut result);
+        }
+        if !args.capture_headers.is_empty() {
+            result.capture_headers(&args.capture_headers);
+        }
+        // The matrix shows every cell, whether the result is going to be reported or not.
+        if let Some(matrix) = matrix.as_mut() {
+            matrix.record(&result);
+        }
+        if let Some(webhook) = webhook.as_mut().filter(|webhook| webhook.filter == WebhookFilter::All) {
+            webhook.send(&result, None).await;
+        }
+        if let Some(detector) = block_detector.as_mut() {
+            detector.push(&result);
+        }
+        challenges.push(result.challenge);
+        // Challenge pages say nothing about the application, they don't get to shape what an
+        // outlier is.
+        let ready = match outliers.as_mut() {
+            Some(filter) if result.challenge.is_none() => filter.push(result),
+            _ => vec![result],
+        };
+        report(&args, &stats, response_filter.as_ref(), ready, &mut held, &mut session, &mut webhook, &mut output, &mut dumper).await;
+    }
+    if let Some(progress) = progress.as_mut() {
+        progress.checkpoint();
+    }
+    if let Some(filter) = outliers.as_mut() {
+        report(&args, &stats, response_filter.as_ref(), filter.finish(), &mut held, &mut session, &mut webhook, &mut output, &mut dumper)
+            .await;
+    }
+
+    if args.score {
+        let mut scored = score::score_results(held);
+        if let Some(top) = args.top {
+            scored.truncate(top);
+        }
+        for (result, score) in scored {
+            print_result(&args, &stats, &result, Some(&score), &mut session, &mut webhook, &mut output, &mut dumper).await;
+        }
+    }
+    challenges.print_summary();
+    if let Some(dumper) = dumper {
+        dumper.finish().await;
+    }
+    // Waits for the sinks writing on threads of their own to empty their queue.
+    if let Some(output) = output {
+        let _ = tokio::task::spawn_blocking(move || output.finish()).await;
+    }
+    if let Some(webhook) = webhook {
+        webhook.finish().await;
+    }
+
+    if let Some(matrix) = matrix {
+        matrix.print();
+        if let Some(path) = &args.matrix_output {
+            if let Err(e) = matrix.write_csv(path, Encryption::from_args(&args).as_ref()) {
+                eprintln!("Could not write the matrix to {}: {}", path, e);
+            }
+        }
+    }
+    session
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn report(
+    args: &Args,
+    stats: &RunStats,
+    response_filter: Option<&ResponseFilter>,
+    results: Vec<RequestResult>,
+    held: &mut Vec<RequestResult>,
+    session: &mut Option<Session>,
+    webhook: &mut Option<Webhook>,
+    output: &mut Option<Output>,
+    dumper: &mut Option<Dumper>,
+) {
+    let body_matchers = !args.match_string.is_empty() || !args.match_regex.is_empty();
+    for result in results {
+        if body_matchers && result.matched.is_none() {
+            continue;
+        }
+        if response_filter.is_some_and(|filter| !filter.allows(&result)) {
+            continue;
+        }
+        // Challenged results are left out of scoring for the same reason.
+        if args.score && result.challenge.is_none() {
+            held.push(result);
+        } else {
+            print_result(args, stats, &result, None, session, webhook, output, dumper).await;
+        }
+    }
+}
+
+// Requests without a complete response have nothing to filter or score, they are counted and
+// listed as they happen. Past --max-errors of them the run is stopped.
+#[allow(clippy::too_many_arguments)]
+pub fn print_failure(
+    args: &Args,
+    stats: &RunStats,
+    stop: &AtomicBool,
+    word: &str,
+    context: Option<&str>,
+    request: Option<&str>,
+    error: &(dyn Error + 'static),
+    attempts: u32,
+) {
+    let failure = Failure::classify(error);
+    let failed = stats.failures.push(failure);
+    let mut line = format!("Failed ({}). Word: {}. Error: {}", failure, shorten(word), failures::describe(error));
+    if let Some(context) = context {
+        line.push_str(&format!(". Context: {}", context));
+    }
+    let redactor = Redactor::from_args(args);
+    if let Some(redactor) = &redactor {
+        line = redactor.text(&line);
+    }
+    if attempts > 1 {
+        line.push_str(&format!(". Attempts: {}", attempts));
+    }
+    progress::clear();
+    println!("{}", line);
+    if args.dumps() {
+        let (mut word, mut request, mut error) = (word.to_string(), request.map(str::to_string), failures::describe(error));
+        if let Some(redactor) = &redactor {
+            word = redactor.text(&word);
+            request = request.map(|request| redactor.http(&request));
+            error = redactor.text(&error);
+        }
+        dump::write_failure(args, &word, request.as_deref(), &error);
+    }
+    // Contexts count their failures on their own, see contexts::Lanes.
+    if context.is_none() && args.max_errors.is_some_and(|max| failed >= max) && !stop.swap(true, Ordering::Relaxed) {
+        eprintln!("Stopping the run after {} failed requests.", failed);
+    }
+}
+
+// Words over this many characters are shortened in the printed results, such as the ones of
+// --length-sweep.
+const MAX_PRINTED_WORD: usize = 64;
+
+fn shorten(word: &str) -> String {
+    let length = word.chars().count();
+    if length <= MAX_PRINTED_WORD {
+        return word.to_string();
+    }
+    format!("{}... ({} characters)", word.chars().take(MAX_PRINTED_WORD / 2).collect::<String>(), length)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn print_result(
+    args: &Args,
+    stats: &RunStats,
+    result: &RequestResult,
+    score: Option<&score::Score>,
+    session: &mut Option<Session>,
+    webhook: &mut Option<Webhook>,
+    output: &mut Option<Output>,
+    dumper: &mut Option<Dumper>,
+) {
+    let length = match &result.peek {
+        Some(peek) => peek.length_class.clone(),
+        None => result.length.to_string(),
+    };
+    let mut length = format!("{}. Words: {}. Lines: {}", length, result.words, result.lines);
+    if let Some(count) = result.count {
+        length.push_str(&format!(". Count: {}", count));
+    }
+    let word = shorten(&result.word);
+    let mut line = match score {
+        Some(score) => format!(
+            "Score: {:.1}. Status code: {}. Length: {}. Word: {}. Reasons: {}",
+            score.value, result.status, length, word, score.reasons.join(", ")
+        ),
+        None => format!("Status code: {}. Length: {}. Word: {}", result.status, length, word),
+    };
+    if let Some(source) = result.source {
+        line.push_str(&format!(" ({})", source));
+    }
+    if let Some(text_length) = result.text_length.filter(|_| args.text_length) {
+        line.push_str(&format!(". Text length: {}", text_length));
+    }
+    if let Some(language) = result.language.filter(|_| args.detect_language) {
+        line.push_str(&format!(". Language: {}", language));
+    }
+    if let Some(peek) = &result.peek {
+        if let Some(title) = &peek.title {
+            line.push_str(&format!(". Title: {}", title));
+        }
+        if !peek.signatures.is_empty() {
+            line.push_str(&format!(". Signatures: {}", peek.signatures.join(", ")));
+        }
+    }
+    if let Some(matched) = &result.matched {
+        line.push_str(&format!(". Matched: {}", matched));
+        if let Some(excerpt) = &result.excerpt {
+            line.push_str(&format!(" in \"{}\"", excerpt));
+        }
+    }
+    if let Some(preview) = &result.preview {
+        line.push_str(&format!(". Preview: {}", preview));
+    }
+    if let Some(html_diff) = &result.html_diff {
+        line.push_str(&format!(". HTML diff: {}", html_diff));
+    }
+    if let Some(challenge) = &result.challenge {
+        line.push_str(&format!(". Challenged: {}", challenge));
+    }
+    if result.aborted {
+        line.push_str(" (download stopped)");
+    }
+    if !args.match_time.is_empty() {
+        line.push_str(&format!(". Time: {}ms", result.duration.as_millis()));
+    }
+    if result.attempts > 1 {
+        line.push_str(&format!(". Attempts: {}", result.attempts));
+    }
+    if let Some(request_id) = &result.request_id {
+        line.push_str(&format!(". Request ID: {}", request_id));
+    }
+    if let Some(context) = &result.context {
+        line.push_str(&format!(". Context: {}", context));
+    }
+    // With several targets the word alone doesn't say which request this was.
+    if args.url_file.is_some() {
+        line.push_str(&format!(". URL: {}", result.url));
+    }
+    // A --benchmark run only prints its summary.
+    let print = ci::count_result(stats, args.ci) && args.benchmark.is_none();
+    if print {
+        progress::clear();
+        match &args.output_template {
+            Some(template) => println!("{}", template.render(&result.to_json(score, &args.tag))),
+            None => println!("{}", line),
+        }
+    }
+    if let Some(Err(e)) = session.as_mut().map(|session| session.record(&line, &result.word)) {
+        eprintln!("Could not record the result in the session: {}", e);
+    }
+    if let Some(webhook) = webhook.as_mut().filter(|webhook| webhook.filter == WebhookFilter::Matched) {
+        webhook.send(result, score).await;
+    }
+    if let Some(output) = output {
+        output.write(result, score);
+    }
+    if let Some(dumper) = dumper.as_mut().filter(|_| print) {
+        dumper.write(result);
+    }
+}
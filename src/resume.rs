@@ -0,0 +1,108 @@
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::results::RequestResult;
+
+// How often the collector writes the state file while the run goes on.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Which words have been completed for every target, kept in the --resume state file so that an
+/// interrupted run can be started again without sending them twice. The file looks like
+/// {"completed": {"http://example.com/##FUZZ##": ["admin", "login"]}}.
+pub struct Progress {
+    path: PathBuf,
+    done: Completed,
+    // Whether anything was completed since the last checkpoint.
+    changed: bool,
+    checkpointed: Instant,
+}
+
+impl Progress {
+    // Reads the state file, which doesn't have to exist yet.
+    pub fn open(path: &str, targets: &[String]) -> Result<Progress, String> {
+        let completed = match fs::read_to_string(path) {
+            Ok(content) => parse(&content).ok_or_else(|| format!("{} is not a --resume state file", path))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(format!("Could not read {}: {}", path, e)),
+        };
+        let done = Completed { targets: targets.to_vec(), completed };
+        let count: usize = targets.iter().filter_map(|target| done.completed.get(target)).map(HashSet::len).sum();
+        if count > 0 {
+            println!("Resuming from {}: {} requests were already completed and are skipped", path, count);
+        }
+        Ok(Progress { path: PathBuf::from(path), done, changed: false, checkpointed: Instant::now() })
+    }
+
+    // What was completed when the run started, for the producer to skip.
+    pub fn completed(&self) -> Completed {
+        self.done.clone()
+    }
+
+    // Marks the request of the result as completed, and writes the state file every once in a
+    // while.
+    pub fn record(&mut self, result: &RequestResult) {
+        let target = self.done.targets[result.target].clone();
+        self.changed |= self.done.completed.entry(target).or_default().insert(result.word.clone());
+        if self.checkpointed.elapsed() >= CHECKPOINT_INTERVAL {
+            self.checkpoint();
+        }
+    }
+
+    // Writes the state file. It is written next to the old one and renamed over it, so that a run
+    // killed halfway through never leaves a broken file behind.
+    pub fn checkpoint(&mut self) {
+        self.checkpointed = Instant::now();
+        if !self.changed {
+            return;
+        }
+        let completed: serde_json::Map<String, Value> = self
+            .done
+            .completed
+            .iter()
+            .map(|(target, words)| {
+                let mut words: Vec<&String> = words.iter().collect();
+                words.sort();
+                (target.clone(), json!(words))
+            })
+            .collect();
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(".tmp");
+        let written = fs::write(&temporary, json!({ "completed": completed }).to_string())
+            .and_then(|_| fs::rename(&temporary, &self.path));
+        match written {
+            Ok(()) => self.changed = false,
+            Err(e) => eprintln!("Could not write the state file {}: {}", self.path.display(), e),
+        }
+    }
+}
+
+/// The words completed for every target, by the URL of the target so that the state file still
+/// applies when the targets are given in another order.
+#[derive(Clone)]
+pub struct Completed {
+    targets: Vec<String>,
+    completed: HashMap<String, HashSet<String>>,
+}
+
+impl Completed {
+    // Whether the word was completed for the target in an earlier run.
+    pub fn is_done(&self, target: usize, word: &str) -> bool {
+        self.completed.get(&self.targets[target]).is_some_and(|words| words.contains(word))
+    }
+}
+
+fn parse(content: &str) -> Option<HashMap<String, HashSet<String>>> {
+    let state: Value = serde_json::from_str(content).ok()?;
+    state["completed"]
+        .as_object()?
+        .iter()
+        .map(|(target, words)| {
+            let words = words.as_array()?.iter().map(|word| word.as_str().map(String::from)).collect::<Option<_>>()?;
+            Some((target.clone(), words))
+        })
+        .collect()
+}
@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::transport::{TransportError, TransportResponse};
+
+// Wait before the first retry, doubled for every one after it.
+const FIRST_BACKOFF: Duration = Duration::from_millis(500);
+// Longest wait between two attempts, Retry-After included.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+// Share of the requests of a target that needed a retry or failed anyway past which its results
+// are flagged as unreliable, once it got enough requests for the share to mean anything.
+const FLAKY_SCORE: f64 = 0.1;
+const FLAKY_MIN_REQUESTS: usize = 20;
+
+// Whether the attempt is worth making again: it failed altogether (the connection was refused or
+// reset, or it timed out) or the server answered with one of the --retry-on statuses.
+pub fn should_retry(sent: &Result<TransportResponse, TransportError>, statuses: &[u16]) -> bool {
+    match sent {
+        Ok(response) => statuses.contains(&response.status.as_u16()),
+        Err(_) => true,
+    }
+}
+
+// Wait before the next attempt, attempt being the number of the one that just failed. A
+// Retry-After in seconds from the server is followed instead, up to MAX_BACKOFF.
+pub fn backoff(attempt: u32, response: Option<&TransportResponse>) -> Duration {
+    let retry_after = response
+        .and_then(|response| response.headers.get(reqwest::header::RETRY_AFTER))
+        .and_then(|value| value.to_str().ok()?.trim().parse::<u64>().ok())
+        .map(Duration::from_secs);
+    let exponential = FIRST_BACKOFF.saturating_mul(1 << (attempt - 1).min(16));
+    retry_after.unwrap_or(exponential).min(MAX_BACKOFF)
+}
+
+/// How much retrying each target needed, for the end of run summary. A target that only answers
+/// after retries, or not at all, gives results that say more about its load than about the
+/// words.
+#[derive(Default)]
+pub struct RetryTally {
+    targets: Mutex<BTreeMap<usize, TargetRetries>>,
+}
+
+#[derive(Default, Clone, Copy)]
+struct TargetRetries {
+    requests: usize,
+    // Requests that took more than one attempt, and the extra attempts they took.
+    retried: usize,
+    retries: usize,
+    // Requests that got no response after all their attempts.
+    failed: usize,
+}
+
+impl TargetRetries {
+    // Share of the requests that needed a retry or failed anyway, from 0 to 1.
+    fn score(&self) -> f64 {
+        let troubled = self.requests.min(self.retried.max(self.failed));
+        troubled as f64 / self.requests.max(1) as f64
+    }
+}
+
+impl RetryTally {
+    pub fn record(&self, target: usize, attempts: u32, failed: bool) {
+        let mut targets = self.targets.lock().unwrap();
+        let tally = targets.entry(target).or_default();
+        tally.requests += 1;
+        if attempts > 1 {
+            tally.retried += 1;
+            tally.retries += attempts as usize - 1;
+        }
+        if failed {
+            tally.failed += 1;
+        }
+    }
+
+    // "Retries: 37 for 20 of 500 requests", then a warning for every target whose score says its
+    // results can't be trusted. Nothing when no request was retried.
+    pub fn print_summary(&self, target_urls: &[String]) {
+        let targets = self.targets.lock().unwrap();
+        let total = targets.values().fold(TargetRetries::default(), |total, tally| TargetRetries {
+            requests: total.requests + tally.requests,
+            retried: total.retried + tally.retried,
+            retries: total.retries + tally.retries,
+            failed: total.failed + tally.failed,
+        });
+        if total.retries == 0 {
+            return;
+        }
+        println!("Retries: {} for {} of {} requests", total.retries, total.retried, total.requests);
+        for (target, tally) in targets.iter() {
+            let score = tally.score();
+            if tally.requests < FLAKY_MIN_REQUESTS || score < FLAKY_SCORE {
+                continue;
+            }
+            println!(
+                "Flaky target (score {:.2}: {} of {} requests retried, {} failed anyway): {}. Its results are likely \
+                unreliable, slow down with --rate or --concurrency and run it again.",
+                score,
+                tally.retried,
+                tally.requests,
+                tally.failed,
+                target_urls.get(*target).map_or("?", String::as_str)
+            );
+        }
+    }
+}
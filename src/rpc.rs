@@ -0,0 +1,263 @@
+use clap::{Parser, ValueEnum};
+use serde_json::{json, Value};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::stats::RunStats;
+use crate::usage::UsageError;
+use crate::Args;
+
+// Error codes defined by JSON-RPC 2.0.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RpcTransport {
+    /// Commands are read from stdin and answers written to stdout, one JSON message per line.
+    Stdio,
+}
+
+/// What a run driven over RPC gets from the server: where to send its results, the flag telling
+/// it to stop, and the stats the status command reads.
+pub struct Embedding {
+    pub results: mpsc::UnboundedSender<Value>,
+    pub stop: Arc<AtomicBool>,
+    pub stats: Arc<RunStats>,
+}
+
+struct Run {
+    handle: JoinHandle<()>,
+    stop: Arc<AtomicBool>,
+    stats: Arc<RunStats>,
+    started: Instant,
+    results: Arc<AtomicUsize>,
+}
+
+// Serves JSON-RPC 2.0 until stdin is closed. The methods are:
+//  - configure {"args": [...]}: the command line options of the next run
+//  - start: starts the configured run, its results come as "result" notifications and its end as
+//    a "finished" notification
+//  - status: whether a run is going on and how far it got
+//  - stop: stops taking new words, the requests in flight still finish
+// Everything the run would normally print goes to stderr, stdout only carries JSON-RPC messages.
+// Serving stops early if the client closes stdout.
+pub async fn serve(transport: RpcTransport) -> Result<(), UsageError> {
+    check_supported()?;
+    match transport {
+        RpcTransport::Stdio => {}
+    }
+    let mut rpc_out = take_stdout().map_err(|e| UsageError::new(format!("Could not set up stdout for --rpc: {}", e)))?;
+    let (messages, mut outgoing) = mpsc::unbounded_channel::<Value>();
+    let writer = tokio::task::spawn_blocking(move || {
+        while let Some(message) = outgoing.blocking_recv() {
+            if writeln!(rpc_out, "{}", message).and_then(|_| rpc_out.flush()).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut configured: Option<Arc<Args>> = None;
+    let mut run: Option<Run> = None;
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request: Value = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                if messages.send(error(Value::Null, PARSE_ERROR, &e.to_string())).is_err() {
+                    break;
+                }
+                continue;
+            }
+        };
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let method = match request.get("method").and_then(Value::as_str) {
+            Some(method) => method,
+            None => {
+                if messages.send(error(id, INVALID_REQUEST, "missing method")).is_err() {
+                    break;
+                }
+                continue;
+            }
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        let running = run.as_ref().is_some_and(|run| !run.handle.is_finished());
+
+        let answer = match method {
+            "configure" if running => Err((INVALID_REQUEST, String::from("a run is going on, stop it first"))),
+            "configure" => configure(&params).map(|args| {
+                configured = Some(Arc::new(args));
+                json!(true)
+            }),
+            "start" if running => Err((INVALID_REQUEST, String::from("a run is already going on"))),
+            "start" => match &configured {
+                Some(args) => {
+                    run = Some(start(Arc::clone(args), messages.clone()));
+                    Ok(json!(true))
+                }
+                None => Err((INVALID_REQUEST, String::from("configure the run first"))),
+            },
+            "status" => Ok(match &run {
+                Some(run) => json!({
+                    "state": if running { "running" } else { "finished" },
+                    "requests": run.stats.requests_sent.load(Ordering::Relaxed),
+                    "in_flight": run.stats.in_flight(),
+                    "results": run.results.load(Ordering::Relaxed),
+                    "elapsed_ms": run.started.elapsed().as_millis() as u64,
+                }),
+                None => json!({ "state": if configured.is_some() { "configured" } else { "idle" } }),
+            }),
+            "stop" => {
+                if let Some(run) = &run {
+                    run.stop.store(true, Ordering::Relaxed);
+                }
+                Ok(json!(running))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method {}", method))),
+        };
+        // Requests without an id are notifications, which get no answer.
+        if request.get("id").is_none() {
+            continue;
+        }
+        let message = match answer {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error(id, code, &message),
+        };
+        // The writer only goes away once nobody reads the answers anymore.
+        if messages.send(message).is_err() {
+            break;
+        }
+    }
+
+    // stdin or stdout closed, the run still going on is let to stop cleanly before leaving.
+    if let Some(run) = run {
+        run.stop.store(true, Ordering::Relaxed);
+        let _ = run.handle.await;
+    }
+    drop(messages);
+    let _ = writer.await;
+    Ok(())
+}
+
+// Only unix lets stdout be kept for the messages while the run prints to stderr.
+pub fn check_supported() -> Result<(), UsageError> {
+    if cfg!(unix) {
+        Ok(())
+    } else {
+        Err(UsageError::new("--rpc is only supported on unix"))
+    }
+}
+
+fn configure(params: &Value) -> Result<Args, (i64, String)> {
+    let options: Vec<String> = match params.get("args").and_then(Value::as_array) {
+        Some(options) => options.iter().map(|option| option.as_str().map(str::to_string)).collect::<Option<_>>(),
+        None => None,
+    }
+    .ok_or((INVALID_PARAMS, String::from("params.args needs to be an array of strings")))?;
+    let program = std::env::args().next().unwrap_or_default();
+    let args = Args::try_parse_from(std::iter::once(program).chain(options)).map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+    if args.command.is_some() || args.rpc.is_some() || args.ci {
+        return Err((INVALID_PARAMS, String::from("subcommands, --rpc and --ci can't be used in a run driven over RPC")));
+    }
+    Ok(args)
+}
+
+fn start(args: Arc<Args>, messages: mpsc::UnboundedSender<Value>) -> Run {
+    let stop = Arc::new(AtomicBool::new(false));
+    let stats = Arc::new(RunStats::default());
+    let results = Arc::new(AtomicUsize::new(0));
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+
+    // Forwards the results of the run as notifications, counting them for the status command.
+    let forwarded = {
+        let messages = messages.clone();
+        let results = Arc::clone(&results);
+        tokio::spawn(async move {
+            while let Some(record) = receiver.recv().await {
+                results.fetch_add(1, Ordering::Relaxed);
+                let _ = messages.send(json!({ "jsonrpc": "2.0", "method": "result", "params": record }));
+            }
+        })
+    };
+    let embedding = Embedding { results: sender, stop: Arc::clone(&stop), stats: Arc::clone(&stats) };
+    let engine = tokio::spawn(crate::run(args, Some(embedding)));
+
+    let handle = {
+        let stop = Arc::clone(&stop);
+        let stats = Arc::clone(&stats);
+        tokio::spawn(async move {
+            // A run refused for its options (bad wordlist, invalid URL...) or that panics passes the
+            // message on.
+            let outcome = engine.await;
+            let _ = forwarded.await;
+            let error = match outcome {
+                Ok(Ok(())) => Value::Null,
+                Ok(Err(usage)) => Value::from(usage.to_string()),
+                Err(e) => Value::from(failure_message(e)),
+            };
+            let _ = messages.send(json!({
+                "jsonrpc": "2.0",
+                "method": "finished",
+                "params": {
+                    "requests": stats.requests_sent.load(Ordering::Relaxed),
+                    "stopped": stop.load(Ordering::Relaxed),
+                    "error": error,
+                },
+            }));
+        })
+    };
+    Run { handle, stop, stats, started: Instant::now(), results }
+}
+
+// Why a run task ended without finishing, the message of its panic when it panicked.
+pub fn failure_message(error: tokio::task::JoinError) -> String {
+    if !error.is_panic() {
+        return error.to_string();
+    }
+    let panic = error.into_panic();
+    panic
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| panic.downcast_ref::<&str>().map(|message| message.to_string()))
+        .unwrap_or_else(|| String::from("the run failed"))
+}
+
+fn error(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+// Keeps the real stdout for the JSON-RPC messages and points file descriptor 1 at stderr, so that
+// whatever the run prints can't get mixed into them.
+#[cfg(unix)]
+fn take_stdout() -> std::io::Result<std::fs::File> {
+    use std::os::fd::FromRawFd;
+
+    std::io::stdout().flush()?;
+    // SAFETY: dup and dup2 only work on file descriptors, the duplicate is owned by the File.
+    unsafe {
+        let rpc_out = libc::dup(libc::STDOUT_FILENO);
+        if rpc_out < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let rpc_out = std::fs::File::from_raw_fd(rpc_out);
+        if libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(rpc_out)
+    }
+}
+
+// Refused by check_supported before getting here.
+#[cfg(not(unix))]
+fn take_stdout() -> std::io::Result<std::fs::File> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
@@ -0,0 +1,432 @@
+use std::sync::Arc;
+
+/// One function of a hashcat rule, with its arguments. Positions and counts are given as 0-9 then
+/// A-Z for 10 to 35.
+#[derive(Debug, Clone, Copy)]
+enum Function {
+    Nothing,
+    Lower,
+    Upper,
+    Capitalize,
+    InvertCapitalize,
+    ToggleAll,
+    Toggle(usize),
+    Reverse,
+    Duplicate,
+    DuplicateTimes(usize),
+    Reflect,
+    RotateLeft,
+    RotateRight,
+    Append(char),
+    Prepend(char),
+    DeleteFirst,
+    DeleteLast,
+    Delete(usize),
+    Extract(usize, usize),
+    Omit(usize, usize),
+    Insert(usize, char),
+    Overwrite(usize, char),
+    Truncate(usize),
+    Replace(char, char),
+    Purge(char),
+    DuplicateFirst(usize),
+    DuplicateLast(usize),
+    DuplicateAll,
+    SwapFront,
+    SwapBack,
+    Swap(usize, usize),
+    ShiftLeft(usize),
+    ShiftRight(usize),
+    Increment(usize),
+    Decrement(usize),
+    ReplaceNext(usize),
+    ReplacePrevious(usize),
+    DuplicateBlockFront(usize),
+    DuplicateBlockBack(usize),
+    Title,
+    TitleWith(char),
+    // Rejections drop the word instead of changing it.
+    RejectLonger(usize),
+    RejectShorter(usize),
+    RejectUnlessLength(usize),
+    RejectContains(char),
+    RejectUnlessContains(char),
+    RejectUnlessFirst(char),
+    RejectUnlessLast(char),
+    RejectUnlessAt(usize, char),
+    RejectUnlessCount(usize, char),
+}
+
+/// The rules of a --rules file, in the hashcat and John the Ripper syntax: one rule per line, each
+/// a list of functions applied to the word in turn, such as "c $1 $!" for "Password1!". Every rule
+/// makes one candidate of each word of the wordlist.
+#[derive(Debug)]
+pub struct Rules {
+    rules: Vec<Vec<Function>>,
+}
+
+impl Rules {
+    // Blank lines and lines starting with # are left out. A function this engine doesn't know
+    // fails the whole file, rather than quietly making other candidates than hashcat would.
+    pub fn load(path: &str) -> Result<Arc<Rules>, String> {
+        let text = crate::wordlist::read_text(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+        let mut rules = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            rules.push(parse_rule(line).map_err(|e| format!("{} line {}: {}", path, number + 1, e))?);
+        }
+        if rules.is_empty() {
+            return Err(format!("{} has no rules", path));
+        }
+        Ok(Arc::new(Rules { rules }))
+    }
+
+    // The candidates of a word, one per rule that doesn't reject it, without repeats.
+    pub fn apply(&self, word: &str) -> Vec<String> {
+        let mut candidates: Vec<String> = Vec::new();
+        for rule in &self.rules {
+            let Some(candidate) = apply_rule(rule, word) else { continue };
+            if !candidates.contains(&candidate) {
+                candidates.push(candidate);
+            }
+        }
+        candidates
+    }
+}
+
+fn parse_rule(line: &str) -> Result<Vec<Function>, String> {
+    let mut chars = line.chars();
+    let mut functions = Vec::new();
+    while let Some(name) = chars.next() {
+        if name == ' ' || name == '\t' {
+            continue;
+        }
+        let mut char_arg = || chars.next().ok_or_else(|| format!("{} is missing an argument", name));
+        let function = match name {
+            ':' => Function::Nothing,
+            'l' => Function::Lower,
+            'u' => Function::Upper,
+            'c' => Function::Capitalize,
+            'C' => Function::InvertCapitalize,
+            't' => Function::ToggleAll,
+            'r' => Function::Reverse,
+            'd' => Function::Duplicate,
+            'f' => Function::Reflect,
+            '{' => Function::RotateLeft,
+            '}' => Function::RotateRight,
+            '[' => Function::DeleteFirst,
+            ']' => Function::DeleteLast,
+            'q' => Function::DuplicateAll,
+            'k' => Function::SwapFront,
+            'K' => Function::SwapBack,
+            'E' => Function::Title,
+            '$' => Function::Append(char_arg()?),
+            '^' => Function::Prepend(char_arg()?),
+            '@' => Function::Purge(char_arg()?),
+            'e' => Function::TitleWith(char_arg()?),
+            '!' => Function::RejectContains(char_arg()?),
+            '/' => Function::RejectUnlessContains(char_arg()?),
+            '(' => Function::RejectUnlessFirst(char_arg()?),
+            ')' => Function::RejectUnlessLast(char_arg()?),
+            's' => {
+                let from = char_arg()?;
+                Function::Replace(from, char_arg()?)
+            }
+            'T' | 'p' | 'D' | '\'' | 'z' | 'Z' | 'L' | 'R' | '+' | '-' | '.' | ',' | 'y' | 'Y' | '<' | '>' | '_' => {
+                let n = position(char_arg()?)?;
+                match name {
+                    'T' => Function::Toggle(n),
+                    'p' => Function::DuplicateTimes(n),
+                    'D' => Function::Delete(n),
+                    '\'' => Function::Truncate(n),
+                    'z' => Function::DuplicateFirst(n),
+                    'Z' => Function::DuplicateLast(n),
+                    'L' => Function::ShiftLeft(n),
+                    'R' => Function::ShiftRight(n),
+                    '+' => Function::Increment(n),
+                    '-' => Function::Decrement(n),
+                    '.' => Function::ReplaceNext(n),
+                    ',' => Function::ReplacePrevious(n),
+                    'y' => Function::DuplicateBlockFront(n),
+                    'Y' => Function::DuplicateBlockBack(n),
+                    '<' => Function::RejectLonger(n),
+                    '>' => Function::RejectShorter(n),
+                    _ => Function::RejectUnlessLength(n),
+                }
+            }
+            'x' | 'O' | '*' => {
+                let n = position(char_arg()?)?;
+                let m = position(char_arg()?)?;
+                match name {
+                    'x' => Function::Extract(n, m),
+                    'O' => Function::Omit(n, m),
+                    _ => Function::Swap(n, m),
+                }
+            }
+            'i' | 'o' | '=' | '%' => {
+                let n = position(char_arg()?)?;
+                let c = char_arg()?;
+                match name {
+                    'i' => Function::Insert(n, c),
+                    'o' => Function::Overwrite(n, c),
+                    '=' => Function::RejectUnlessAt(n, c),
+                    _ => Function::RejectUnlessCount(n, c),
+                }
+            }
+            other => return Err(format!("unsupported rule function {}", other)),
+        };
+        functions.push(function);
+    }
+    Ok(functions)
+}
+
+fn position(c: char) -> Result<usize, String> {
+    match c {
+        '0'..='9' => Ok(c as usize - '0' as usize),
+        'A'..='Z' => Ok(c as usize - 'A' as usize + 10),
+        _ => Err(format!("invalid position {}, use 0-9 or A-Z", c)),
+    }
+}
+
+fn toggle(c: char) -> char {
+    if c.is_lowercase() {
+        c.to_uppercase().next().unwrap_or(c)
+    } else {
+        c.to_lowercase().next().unwrap_or(c)
+    }
+}
+
+// Like hashcat, a position past the end of the word leaves it as it is.
+fn apply_rule(rule: &[Function], word: &str) -> Option<String> {
+    let mut w: Vec<char> = word.chars().collect();
+    for function in rule {
+        let len = w.len();
+        match *function {
+            Function::Nothing => {}
+            Function::Lower => w = w.iter().flat_map(|c| c.to_lowercase()).collect(),
+            Function::Upper => w = w.iter().flat_map(|c| c.to_uppercase()).collect(),
+            Function::Capitalize => {
+                w = w.iter().flat_map(|c| c.to_lowercase()).collect();
+                if let Some(first) = w.first_mut() {
+                    *first = first.to_uppercase().next().unwrap_or(*first);
+                }
+            }
+            Function::InvertCapitalize => {
+                w = w.iter().flat_map(|c| c.to_uppercase()).collect();
+                if let Some(first) = w.first_mut() {
+                    *first = first.to_lowercase().next().unwrap_or(*first);
+                }
+            }
+            Function::ToggleAll => w.iter_mut().for_each(|c| *c = toggle(*c)),
+            Function::Toggle(n) if n < len => w[n] = toggle(w[n]),
+            Function::Reverse => w.reverse(),
+            Function::Duplicate => w.extend(w.clone()),
+            Function::DuplicateTimes(n) => w = w.repeat(n + 1),
+            Function::Reflect => w.extend(w.iter().rev().copied().collect::<Vec<char>>()),
+            Function::RotateLeft if len > 0 => w.rotate_left(1),
+            Function::RotateRight if len > 0 => w.rotate_right(1),
+            Function::Append(c) => w.push(c),
+            Function::Prepend(c) => w.insert(0, c),
+            Function::DeleteFirst if len > 0 => {
+                w.remove(0);
+            }
+            Function::DeleteLast => {
+                w.pop();
+            }
+            Function::Delete(n) if n < len => {
+                w.remove(n);
+            }
+            Function::Extract(n, m) if n + m <= len => w = w[n..n + m].to_vec(),
+            Function::Omit(n, m) if n + m <= len => {
+                w.drain(n..n + m);
+            }
+            Function::Insert(n, c) if n <= len => w.insert(n, c),
+            Function::Overwrite(n, c) if n < len => w[n] = c,
+            Function::Truncate(n) => w.truncate(n),
+            Function::Replace(from, to) => w.iter_mut().filter(|c| **c == from).for_each(|c| *c = to),
+            Function::Purge(c) => w.retain(|other| *other != c),
+            Function::DuplicateFirst(n) if len > 0 => w.splice(0..0, std::iter::repeat_n(w[0], n)).for_each(drop),
+            Function::DuplicateLast(n) if len > 0 => w.extend(std::iter::repeat_n(w[len - 1], n)),
+            Function::DuplicateAll => w = w.iter().flat_map(|c| [*c, *c]).collect(),
+            Function::SwapFront if len > 1 => w.swap(0, 1),
+            Function::SwapBack if len > 1 => w.swap(len - 1, len - 2),
+            Function::Swap(n, m) if n < len && m < len => w.swap(n, m),
+            Function::ShiftLeft(n) if n < len => w[n] = shift(w[n], |byte| byte << 1),
+            Function::ShiftRight(n) if n < len => w[n] = shift(w[n], |byte| byte >> 1),
+            Function::Increment(n) if n < len => w[n] = shift(w[n], |byte| byte.wrapping_add(1)),
+            Function::Decrement(n) if n < len => w[n] = shift(w[n], |byte| byte.wrapping_sub(1)),
+            Function::ReplaceNext(n) if n + 1 < len => w[n] = w[n + 1],
+            Function::ReplacePrevious(n) if n > 0 && n < len => w[n] = w[n - 1],
+            Function::DuplicateBlockFront(n) if n <= len => w.splice(0..0, w[..n].to_vec()).for_each(drop),
+            Function::DuplicateBlockBack(n) if n <= len => w.extend(w[len - n..].to_vec()),
+            Function::Title => w = title(&w, ' '),
+            Function::TitleWith(separator) => w = title(&w, separator),
+            // As in hashcat, <N keeps words of N characters and >N too, unlike John the Ripper.
+            Function::RejectLonger(n) if len > n => return None,
+            Function::RejectShorter(n) if len < n => return None,
+            Function::RejectUnlessLength(n) if len != n => return None,
+            Function::RejectContains(c) if w.contains(&c) => return None,
+            Function::RejectUnlessContains(c) if !w.contains(&c) => return None,
+            Function::RejectUnlessFirst(c) if w.first() != Some(&c) => return None,
+            Function::RejectUnlessLast(c) if w.last() != Some(&c) => return None,
+            Function::RejectUnlessAt(n, c) if w.get(n) != Some(&c) => return None,
+            Function::RejectUnlessCount(n, c) if w.iter().filter(|other| **other == c).count() < n => return None,
+            _ => {}
+        }
+    }
+    Some(w.into_iter().collect())
+}
+
+// Bit operations of hashcat work on bytes, characters past ASCII are left alone. So are those the
+// operation would take past ASCII: hashcat makes a lone byte such as 0xE6 of them, which isn't
+// text, and taking it for the Latin-1 character would send other bytes than hashcat does.
+fn shift(c: char, operation: impl Fn(u8) -> u8) -> char {
+    match c.is_ascii().then(|| operation(c as u8)) {
+        Some(byte) if byte.is_ascii() => byte as char,
+        _ => c,
+    }
+}
+
+// Lowercase, with the first letter and every letter after the separator uppercase.
+fn title(word: &[char], separator: char) -> Vec<char> {
+    let mut titled = Vec::with_capacity(word.len());
+    let mut start = true;
+    for c in word {
+        if start {
+            titled.extend(c.to_uppercase());
+        } else {
+            titled.extend(c.to_lowercase());
+        }
+        start = *c == separator;
+    }
+    titled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(rule: &str, word: &str) -> Option<String> {
+        apply_rule(&parse_rule(rule).unwrap(), word)
+    }
+
+    // The examples of the hashcat rule documentation, all on the word p@ssW0rd.
+    #[test]
+    fn functions_match_hashcat() {
+        let examples = [
+            (":", "p@ssW0rd"),
+            ("l", "p@ssw0rd"),
+            ("u", "P@SSW0RD"),
+            ("c", "P@ssw0rd"),
+            ("C", "p@SSW0RD"),
+            ("t", "P@SSw0RD"),
+            ("T3", "p@sSW0rd"),
+            ("r", "dr0Wss@p"),
+            ("d", "p@ssW0rdp@ssW0rd"),
+            ("p2", "p@ssW0rdp@ssW0rdp@ssW0rd"),
+            ("f", "p@ssW0rddr0Wss@p"),
+            ("{", "@ssW0rdp"),
+            ("}", "dp@ssW0r"),
+            ("$1", "p@ssW0rd1"),
+            ("^1", "1p@ssW0rd"),
+            ("[", "@ssW0rd"),
+            ("]", "p@ssW0r"),
+            ("D3", "p@sW0rd"),
+            ("x04", "p@ss"),
+            ("O12", "psW0rd"),
+            ("i4!", "p@ss!W0rd"),
+            ("o3$", "p@s$W0rd"),
+            ("'6", "p@ssW0"),
+            ("ss$", "p@$$W0rd"),
+            ("@s", "p@W0rd"),
+            ("z2", "ppp@ssW0rd"),
+            ("Z2", "p@ssW0rddd"),
+            ("q", "pp@@ssssWW00rrdd"),
+            ("k", "@pssW0rd"),
+            ("K", "p@ssW0dr"),
+            ("*34", "p@sWs0rd"),
+            ("R2", "p@9sW0rd"),
+            ("+2", "p@tsW0rd"),
+            ("-1", "p?ssW0rd"),
+            (".1", "psssW0rd"),
+            (",1", "ppssW0rd"),
+            ("y2", "p@p@ssW0rd"),
+            ("Y2", "p@ssW0rdrd"),
+        ];
+        for (rule, expected) in examples {
+            assert_eq!(apply(rule, "p@ssW0rd").as_deref(), Some(expected), "rule {}", rule);
+        }
+    }
+
+    #[test]
+    fn title_case() {
+        assert_eq!(apply("E", "p@ssW0rd w0rld").as_deref(), Some("P@ssw0rd W0rld"));
+        assert_eq!(apply("e-", "p@ssW0rd-w0rld").as_deref(), Some("P@ssw0rd-W0rld"));
+    }
+
+    #[test]
+    fn functions_apply_in_turn() {
+        assert_eq!(apply("c $1 $!", "password").as_deref(), Some("Password1!"));
+        assert_eq!(apply("u$1r", "ab").as_deref(), Some("1BA"));
+    }
+
+    #[test]
+    fn positions_past_the_end_leave_the_word() {
+        for rule in ["T9", "D9", "x28", "O28", "i9!", "o9!", "*09", "L9", ".3", ",0", "y5"] {
+            assert_eq!(apply(rule, "abcd").as_deref(), Some("abcd"), "rule {}", rule);
+        }
+        assert_eq!(apply("i4!", "abcd").as_deref(), Some("abcd!"));
+        assert_eq!(apply("[", "").as_deref(), Some(""));
+    }
+
+    // <N keeps words of at most N characters and >N those of at least N, like hashcat's
+    // out_len > N and out_len < N rejections.
+    #[test]
+    fn rejections_match_hashcat() {
+        assert_eq!(apply("<4", "abcd").as_deref(), Some("abcd"));
+        assert_eq!(apply("<4", "abcde"), None);
+        assert_eq!(apply(">4", "abcd").as_deref(), Some("abcd"));
+        assert_eq!(apply(">4", "abc"), None);
+        assert_eq!(apply("_4", "abcd").as_deref(), Some("abcd"));
+        assert_eq!(apply("_4", "abc"), None);
+        assert_eq!(apply("!b", "abcd"), None);
+        assert_eq!(apply("/z", "abcd"), None);
+        assert_eq!(apply("(a )d", "abcd").as_deref(), Some("abcd"));
+        assert_eq!(apply("(b", "abcd"), None);
+        assert_eq!(apply(")c", "abcd"), None);
+        assert_eq!(apply("=1b", "abcd").as_deref(), Some("abcd"));
+        assert_eq!(apply("=1c", "abcd"), None);
+        assert_eq!(apply("%2a", "abca").as_deref(), Some("abca"));
+        assert_eq!(apply("%3a", "abca"), None);
+    }
+
+    #[test]
+    fn byte_operations_stay_in_ascii() {
+        // 's' shifted left is 0xE6, past ASCII, so it stays as it is.
+        assert_eq!(apply("L2", "p@ssW0rd").as_deref(), Some("p@ssW0rd"));
+        assert_eq!(apply("L0", "!").as_deref(), Some("B"));
+        assert_eq!(apply("+0", "\u{7f}").as_deref(), Some("\u{7f}"));
+        assert_eq!(apply("-0", "\0").as_deref(), Some("\0"));
+        assert_eq!(apply("+0 R0 L0", "é").as_deref(), Some("é"));
+    }
+
+    #[test]
+    fn positions_go_from_0_to_z() {
+        assert_eq!(position('0'), Ok(0));
+        assert_eq!(position('9'), Ok(9));
+        assert_eq!(position('A'), Ok(10));
+        assert_eq!(position('Z'), Ok(35));
+        assert!(position('a').is_err());
+        assert!(position('!').is_err());
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        assert!(parse_rule("$").is_err());
+        assert!(parse_rule("x1").is_err());
+        assert!(parse_rule("Tz").is_err());
+        assert!(parse_rule("X123").is_err());
+    }
+}
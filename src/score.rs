@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::results::RequestResult;
+
+// Lowercase fragments that usually only show up in a body when something went wrong on the
+// server side.
+const ERROR_STRINGS: [&str; 12] = [
+    "exception",
+    "stack trace",
+    "traceback",
+    "sql syntax",
+    "syntax error",
+    "fatal error",
+    "warning:",
+    "odbc",
+    "ora-0",
+    "undefined index",
+    "unhandled",
+    "internal server error",
+];
+
+/// Facts about a response body that scoring needs. They are computed by the worker right away so
+/// that the body itself doesn't have to be kept until the end of the run.
+#[derive(Debug, Default)]
+pub struct BodySignals {
+    pub reflected: bool,
+    pub error_strings: Vec<&'static str>,
+}
+
+impl BodySignals {
+    pub fn from_body(words: &[String], body: &str) -> BodySignals {
+        let lowercase = body.to_lowercase();
+        BodySignals {
+            // Very short words are found by chance in nearly every page.
+            reflected: words.iter().any(|word| word.len() >= 3 && body.contains(word.as_str())),
+            error_strings: ERROR_STRINGS.into_iter().filter(|s| lowercase.contains(s)).collect(),
+        }
+    }
+}
+
+pub struct Score {
+    pub value: f64,
+    pub reasons: Vec<String>,
+}
+
+// Gives every result an interest score relative to the rest of the run and returns them sorted
+// from most to least interesting. Nothing here is absolute: a 500 is only interesting if most
+// other responses aren't 500s, and the same goes for lengths and response times.
+pub fn score_results(results: Vec<RequestResult>) -> Vec<(RequestResult, Score)> {
+    let total = results.len() as f64;
+
+    let mut status_counts: HashMap<u16, usize> = HashMap::new();
+    for result in &results {
+        *status_counts.entry(result.status.as_u16()).or_default() += 1;
+    }
+    let lengths = Spread::new(results.iter().map(|r| r.length as f64).collect());
+    let durations = Spread::new(results.iter().map(|r| r.duration.as_secs_f64()).collect());
+
+    let mut scored: Vec<(RequestResult, Score)> = results
+        .into_iter()
+        .map(|result| {
+            let mut score = Score { value: 0.0, reasons: Vec::new() };
+
+            let share = status_counts[&result.status.as_u16()] as f64 / total;
+            if share < 0.1 {
+                score.value += 3.0 * (1.0 - share);
+                score.reasons.push(format!("rare status ({:.1}%)", share * 100.0));
+            }
+            if result.status.is_server_error() {
+                score.value += 1.0;
+                score.reasons.push("server error".to_string());
+            }
+
+            let length_deviation = lengths.deviation(result.length as f64);
+            if length_deviation > 3.0 {
+                score.value += (length_deviation / 3.0).min(3.0);
+                score.reasons.push("outlier length".to_string());
+            }
+
+            let duration_deviation = durations.deviation(result.duration.as_secs_f64());
+            if duration_deviation > 3.0 && result.duration > Duration::from_millis(200) {
+                score.value += (duration_deviation / 3.0).min(2.0);
+                score.reasons.push(format!("slow ({:.2?})", result.duration));
+            }
+
+            if let Some(signals) = &result.signals {
+                if !signals.error_strings.is_empty() {
+                    score.value += 2.0;
+                    score.reasons.push(format!("error strings ({})", signals.error_strings.join(", ")));
+                }
+                if signals.reflected {
+                    score.value += 1.5;
+                    score.reasons.push("payload reflected".to_string());
+                }
+            }
+
+            (result, score)
+        })
+        .collect();
+
+    scored.sort_by(|a, b| b.1.value.total_cmp(&a.1.value));
+    scored
+}
+
+// Median and median absolute deviation of a set of values. Used instead of mean and standard
+// deviation because a handful of extreme responses shouldn't hide each other.
+struct Spread {
+    median: f64,
+    mad: f64,
+}
+
+impl Spread {
+    fn new(mut values: Vec<f64>) -> Spread {
+        let center = median(&mut values);
+        let mut deviations: Vec<f64> = values.iter().map(|v| (v - center).abs()).collect();
+        let mad = median(&mut deviations);
+        Spread { median: center, mad }
+    }
+
+    // Robust z-score of a value. Perfectly uniform runs have no deviation at all, so a small
+    // fraction of the median is used as the floor to keep tiny differences from standing out.
+    fn deviation(&self, value: f64) -> f64 {
+        let scale = (1.4826 * self.mad).max(self.median * 0.05).max(f64::EPSILON);
+        (value - self.median).abs() / scale
+    }
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    values[values.len() / 2]
+}
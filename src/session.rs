@@ -0,0 +1,241 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{self, SystemTime, UNIX_EPOCH};
+
+use clap::Subcommand;
+
+use crate::redact::{self, Redactor};
+
+/// Actions of the sessions subcommand.
+#[derive(Subcommand, Debug)]
+pub enum SessionsAction {
+    /// Lists every stored session.
+    List,
+    /// Prints the configuration, run history and result counts of a session.
+    Show {
+        name: String,
+    },
+    /// Deletes a session and everything stored in it.
+    Clean {
+        /// Session to delete.
+        #[arg(required_unless_present = "all")]
+        name: Option<String>,
+
+        /// Deletes every stored session.
+        #[arg(long, conflicts_with = "name")]
+        all: bool,
+    },
+}
+
+// Directory holding everything the program stores between runs.
+pub fn app_dir() -> io::Result<PathBuf> {
+    let home = ["HOME", "USERPROFILE"].into_iter().filter_map(std::env::var_os).find(|home| !home.is_empty()).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "Could not find the home directory, neither HOME nor USERPROFILE is set")
+    })?;
+    Ok(PathBuf::from(home).join(".httprepeater"))
+}
+
+pub fn sessions_dir() -> io::Result<PathBuf> {
+    Ok(app_dir()?.join("sessions"))
+}
+
+/// An engagement workspace. Every run made with the same session name appends to the same
+/// directory, which holds:
+///  - config.txt: the command line of every run
+///  - runs.log: start and end of every run, with the --identify text it was sent with
+///  - results.log: every reported result line
+///  - matches.txt: the words of the reported results, one per line
+pub struct Session {
+    dir: PathBuf,
+    results: BufWriter<File>,
+    matches: BufWriter<File>,
+}
+
+impl Session {
+    pub fn open(name: &str, identification: Option<&str>, redactor: Option<&Redactor>) -> io::Result<Session> {
+        check_name(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let dir = sessions_dir()?.join(name);
+        fs::create_dir_all(&dir)?;
+
+        let command_line = redact::stored_arguments(std::env::args().collect(), redactor);
+        let command_line: Vec<String> = command_line.iter().map(|arg| quote_arg(arg)).collect();
+        append_line(&dir.join("config.txt"), &format!("[{}] {}", timestamp(SystemTime::now()), command_line.join(" ")))?;
+        let started = match identification {
+            Some(identification) => format!("Run started, identified as \"{}\"", identification),
+            None => String::from("Run started"),
+        };
+        append_line(&dir.join("runs.log"), &format!("[{}] {}", timestamp(SystemTime::now()), started))?;
+
+        Ok(Session {
+            results: BufWriter::new(open_append(&dir.join("results.log"))?),
+            matches: BufWriter::new(open_append(&dir.join("matches.txt"))?),
+            dir,
+        })
+    }
+
+    pub fn record(&mut self, line: &str, word: &str) -> io::Result<()> {
+        writeln!(self.results, "{}", line)?;
+        writeln!(self.matches, "{}", word)
+    }
+
+    pub fn finish(mut self, elapsed: time::Duration) -> io::Result<()> {
+        self.results.flush()?;
+        self.matches.flush()?;
+        append_line(&self.dir.join("runs.log"), &format!("[{}] Run finished in {:.2?}", timestamp(SystemTime::now()), elapsed))
+    }
+}
+
+// Sessions are directories, their names can't point anywhere else. Characters Windows doesn't
+// allow in directory names are left out everywhere, for a session to be named the same on every
+// system.
+pub fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+        return Err(String::from("Session names can't be empty, . or .., nor contain path separators"));
+    }
+    if name.contains(['<', '>', ':', '"', '|', '?', '*']) || name.contains(char::is_control) || name.ends_with(['.', ' ']) {
+        return Err(format!("Session name {} can't be a directory name on Windows, which rules out <>:\"|?* and a dot or space at the end", name));
+    }
+    Ok(())
+}
+
+// Loads the words stored by a previous session, given as "name:matches" ("name" alone means the
+// same). Every run of a session appends to its matches, so repeated words are only used once.
+pub fn load_session_words(source: &str) -> Result<Vec<String>, String> {
+    let (name, kind) = source.rsplit_once(':').unwrap_or((source, "matches"));
+    if kind != "matches" {
+        return Err(format!("Unknown session source {}, only \"matches\" is available", kind));
+    }
+    check_name(name)?;
+    let path = sessions_dir().map_err(|e| e.to_string())?.join(name).join("matches.txt");
+    let file = File::open(&path).map_err(|_| format!("Session {} has no matches stored", name))?;
+
+    let mut seen = HashSet::new();
+    let mut words = Vec::new();
+    for line in io::BufReader::new(file).lines() {
+        let line = line.map_err(|e| format!("Could not read the matches of session {}: {}", name, e))?;
+        if seen.insert(line.clone()) {
+            words.push(line);
+        }
+    }
+    Ok(words)
+}
+
+pub fn run_command(action: &SessionsAction) -> Result<(), String> {
+    let sessions = sessions_dir().map_err(|e| e.to_string())?;
+    match action {
+        SessionsAction::List => {
+            let mut names: Vec<String> = match fs::read_dir(&sessions) {
+                Ok(entries) => entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.path().is_dir())
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .collect(),
+                Err(_) => Vec::new(),
+            };
+            if names.is_empty() {
+                println!("No sessions stored in {}", sessions.display());
+                return Ok(());
+            }
+            names.sort();
+            for name in names {
+                let dir = sessions.join(&name);
+                let modified = fs::metadata(dir.join("runs.log"))
+                    .and_then(|meta| meta.modified())
+                    .map(timestamp)
+                    .unwrap_or_else(|_| String::from("unknown"));
+                println!("{}\tlast used {}\t{} matches", name, modified, count_lines(&dir.join("matches.txt")));
+            }
+        }
+        SessionsAction::Show { name } => {
+            // The name is joined onto the sessions directory, ".." or an absolute path would
+            // lead out of it.
+            check_name(name)?;
+            let dir = sessions.join(name);
+            if !dir.is_dir() {
+                return Err(format!("Session {} does not exist, see \"httprepeater sessions list\"", name));
+            }
+            println!("Session: {}\nDirectory: {}\n", name, dir.display());
+            println!("Configuration:\n{}", fs::read_to_string(dir.join("config.txt")).unwrap_or_default());
+            println!("Runs:\n{}", fs::read_to_string(dir.join("runs.log")).unwrap_or_default());
+            println!("Results logged: {}", count_lines(&dir.join("results.log")));
+            println!("Matches: {}", count_lines(&dir.join("matches.txt")));
+        }
+        SessionsAction::Clean { name, all } => {
+            let target = match name {
+                Some(name) if !*all => {
+                    check_name(name)?;
+                    sessions.join(name)
+                }
+                _ => sessions,
+            };
+            if target.exists() {
+                fs::remove_dir_all(&target).map_err(|e| format!("Could not remove {}: {}", target.display(), e))?;
+                println!("Removed {}", target.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn open_append(path: &PathBuf) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn append_line(path: &PathBuf, line: &str) -> io::Result<()> {
+    writeln!(open_append(path)?, "{}", line)
+}
+
+fn count_lines(path: &PathBuf) -> usize {
+    match File::open(path) {
+        Ok(file) => io::BufReader::new(file).lines().count(),
+        Err(_) => 0,
+    }
+}
+
+// Quotes an argument for the config snapshot so the logged command can be pasted back in a shell.
+fn quote_arg(arg: &str) -> String {
+    if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=@,%+".contains(c)) {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+// Formats a time as an UTC ISO 8601 timestamp without pulling in a date library.
+pub fn timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (days, rest) = (secs / 86400, secs % 86400);
+
+    // Converts days since the epoch to a civil date (Howard Hinnant's algorithm).
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, rest / 3600, rest % 3600 / 60, rest % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Names are joined onto the sessions directory, and "sessions clean" deletes what they lead to.
+    #[test]
+    fn names_stay_in_the_sessions_directory() {
+        for name in ["", ".", "..", "/home/user", "a/..", "..\\a", "C:\\Users", "a:b", "name.", "name ", "a\nb"] {
+            assert!(check_name(name).is_err(), "{:?}", name);
+        }
+        for name in ["acme-api", "acme_api.2", ".hidden", "a b"] {
+            assert!(check_name(name).is_ok(), "{:?}", name);
+        }
+    }
+}
@@ -0,0 +1,74 @@
+/// SplitMix64, the small generator --shuffle draws from. The same seed gives the same order on
+/// every machine and every version, which a crate's generator doesn't promise.
+struct SplitMix(u64);
+
+impl SplitMix {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // A number below n, without the bias of a plain modulo.
+    fn below(&mut self, n: usize) -> usize {
+        ((self.next() as u128 * n as u128) >> 64) as usize
+    }
+}
+
+// Fisher-Yates: every order is as likely as any other.
+pub fn shuffle<T>(items: &mut [T], seed: u64) {
+    let mut rng = SplitMix(seed);
+    for index in (1..items.len()).rev() {
+        items.swap(index, rng.below(index + 1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reference outputs of SplitMix64 for seed 0.
+    #[test]
+    fn splitmix_sequence() {
+        let mut rng = SplitMix(0);
+        assert_eq!(rng.next(), 0xe220a8397b1dcdaf);
+        assert_eq!(rng.next(), 0x6e789e6aa1b965f4);
+        assert_eq!(rng.next(), 0x06c45d188009454f);
+    }
+
+    #[test]
+    fn below_stays_in_range() {
+        let mut rng = SplitMix(7);
+        for n in [1, 2, 3, 10, 1000] {
+            for _ in 0..100 {
+                assert!(rng.below(n) < n);
+            }
+        }
+    }
+
+    fn shuffled(count: usize, seed: u64) -> Vec<usize> {
+        let mut items: Vec<usize> = (0..count).collect();
+        shuffle(&mut items, seed);
+        items
+    }
+
+    // --shuffle with a --seed has to give the same order on every run, every machine and every
+    // version, this is that order.
+    #[test]
+    fn order_of_a_seed() {
+        assert_eq!(shuffled(10, 42), [8, 3, 6, 5, 4, 0, 9, 2, 1, 7]);
+        assert_eq!(shuffled(10, 42), shuffled(10, 42));
+        assert_ne!(shuffled(10, 42), shuffled(10, 43));
+    }
+
+    #[test]
+    fn every_item_is_kept() {
+        let mut items = shuffled(100, 1);
+        items.sort();
+        assert_eq!(items, (0..100).collect::<Vec<usize>>());
+        assert_eq!(shuffled(0, 1), Vec::<usize>::new());
+        assert_eq!(shuffled(1, 1), [0]);
+    }
+}
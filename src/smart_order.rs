@@ -0,0 +1,129 @@
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{self, error::TryRecvError};
+
+use crate::output::ResultSink;
+use crate::payloads::Payload;
+
+// Payloads looked ahead at when picking the next one to send.
+const WINDOW: usize = 1000;
+// How much the weight of older hits is kept each time a new one comes in, so that the most
+// recent hits matter the most.
+const DECAY: f64 = 0.8;
+// Length of the word prefixes compared.
+const PREFIX: usize = 4;
+
+/// Weights of the features (extension, tokens, prefix) of the words that were hits. A word gets
+/// the weights of the features it shares with them.
+#[derive(Default)]
+pub struct Feedback {
+    weights: HashMap<String, f64>,
+}
+
+impl Feedback {
+    fn record_hit(&mut self, word: &str) {
+        for weight in self.weights.values_mut() {
+            *weight *= DECAY;
+        }
+        for feature in features(word) {
+            *self.weights.entry(feature).or_default() += 1.0;
+        }
+    }
+
+    fn score(&self, features: &[String]) -> f64 {
+        features.iter().filter_map(|feature| self.weights.get(feature)).sum()
+    }
+}
+
+// "admin_panel.php" gives ext:php, tok:admin, tok:panel and pre:admi.
+fn features(word: &str) -> Vec<String> {
+    let word = word.trim_matches('/').to_lowercase();
+    let mut features = Vec::new();
+    if let Some((_, extension)) = word.rsplit_once('.').filter(|(stem, extension)| !stem.is_empty() && !extension.is_empty()) {
+        features.push(format!("ext:{}", extension));
+    }
+    for token in word.split(|c: char| !c.is_alphanumeric()).filter(|token| token.len() > 1) {
+        features.push(format!("tok:{}", token));
+    }
+    if word.chars().count() >= PREFIX {
+        features.push(format!("pre:{}", word.chars().take(PREFIX).collect::<String>()));
+    }
+    features.sort();
+    features.dedup();
+    features
+}
+
+// Reported results are fed back to the scheduler as hits.
+pub struct FeedbackSink(Arc<Mutex<Feedback>>);
+
+impl ResultSink for FeedbackSink {
+    fn name(&self) -> String {
+        String::from("--smart-order")
+    }
+
+    fn write(&mut self, record: &Value) -> io::Result<()> {
+        if let Some(word) = record["word"].as_str() {
+            self.0.lock().unwrap().record_hit(word);
+        }
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Sits between the producer and the workers and hands out, out of the next WINDOW payloads, the
+// one sharing the most with recent hits. Payloads scoring the same keep their wordlist order, so
+// until the first hit nothing changes. The output channel holds a single payload so that the
+// choice is made as late as possible.
+pub fn spawn(mut input: mpsc::Receiver<(usize, Payload)>) -> (mpsc::Receiver<(usize, Payload)>, FeedbackSink) {
+    let feedback = Arc::new(Mutex::new(Feedback::default()));
+    let (output, receiver) = mpsc::channel(1);
+    let sink = FeedbackSink(Arc::clone(&feedback));
+
+    tokio::spawn(async move {
+        let mut pool: VecDeque<((usize, Payload), Vec<String>)> = VecDeque::new();
+        let mut exhausted = false;
+        loop {
+            while !exhausted && pool.len() < WINDOW {
+                // Only waits for the producer when there is nothing to hand out at all.
+                let item = if pool.is_empty() {
+                    input.recv().await
+                } else {
+                    match input.try_recv() {
+                        Ok(item) => Some(item),
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => None,
+                    }
+                };
+                match item {
+                    Some(item) => {
+                        let features = item.1.words().iter().flat_map(|word| features(word)).collect();
+                        pool.push_back((item, features));
+                    }
+                    None => exhausted = true,
+                }
+            }
+            let best = {
+                let feedback = feedback.lock().unwrap();
+                let mut best: Option<(usize, f64)> = None;
+                for (index, (_, features)) in pool.iter().enumerate() {
+                    let score = feedback.score(features);
+                    if best.is_none_or(|(_, best_score)| score > best_score) {
+                        best = Some((index, score));
+                    }
+                }
+                best
+            };
+            let Some((index, _)) = best else { return };
+            let (item, _) = pool.remove(index).unwrap();
+            if output.send(item).await.is_err() {
+                return;
+            }
+        }
+    });
+    (receiver, sink)
+}
@@ -0,0 +1,113 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::transport::TransportError;
+
+// Port SOCKS proxies listen on when the proxy URL doesn't say.
+const DEFAULT_PORT: u16 = 1080;
+
+// Whether the proxy URL points to a SOCKS proxy rather than an HTTP one.
+pub fn is_socks(url: &str) -> bool {
+    let scheme = url.split_once("://").map_or("", |(scheme, _)| scheme).to_ascii_lowercase();
+    scheme.starts_with("socks")
+}
+
+/// A SOCKS5 proxy, given as socks5://[user:password@]host[:port] or socks5h://... With socks5h
+/// the proxy resolves the target's hostname, which reaches names only the proxy's side of the
+/// network knows about. With socks5 the hostname is resolved locally and the proxy only gets the
+/// address.
+pub struct Socks5Proxy {
+    host: String,
+    port: u16,
+    remote_dns: bool,
+    credentials: Option<(String, String)>,
+}
+
+impl Socks5Proxy {
+    pub fn parse(url: &str) -> Result<Socks5Proxy, String> {
+        let parsed = reqwest::Url::parse(url).map_err(|e| e.to_string())?;
+        let remote_dns = match parsed.scheme() {
+            "socks5" => false,
+            "socks5h" => true,
+            other => return Err(format!("unsupported scheme {}, use socks5 or socks5h", other)),
+        };
+        let host = parsed.host_str().ok_or("no host")?.trim_start_matches('[').trim_end_matches(']').to_string();
+        let credentials = match parsed.username() {
+            "" => None,
+            username => Some((username.to_string(), parsed.password().unwrap_or_default().to_string())),
+        };
+        Ok(Socks5Proxy { host, port: parsed.port().unwrap_or(DEFAULT_PORT), remote_dns, credentials })
+    }
+
+    // Opens a connection to the target through the proxy (RFC 1928), ready for the request or the
+    // TLS handshake.
+    pub async fn connect(&self, host: &str, port: u16) -> Result<TcpStream, TransportError> {
+        let mut stream = TcpStream::connect((self.host.as_str(), self.port)).await?;
+
+        let method = if self.credentials.is_some() { 0x02 } else { 0x00 };
+        stream.write_all(&[0x05, 0x01, method]).await?;
+        let mut choice = [0u8; 2];
+        stream.read_exact(&mut choice).await?;
+        if choice != [0x05, method] {
+            return Err("the SOCKS proxy refused the authentication method".into());
+        }
+        if let Some((username, password)) = &self.credentials {
+            // Username and password authentication, RFC 1929.
+            let mut request = vec![0x01, username.len() as u8];
+            request.extend_from_slice(username.as_bytes());
+            request.push(password.len() as u8);
+            request.extend_from_slice(password.as_bytes());
+            stream.write_all(&request).await?;
+            let mut status = [0u8; 2];
+            stream.read_exact(&mut status).await?;
+            if status[1] != 0x00 {
+                return Err("the SOCKS proxy rejected the credentials".into());
+            }
+        }
+
+        let mut request = vec![0x05, 0x01, 0x00];
+        match host.parse::<std::net::IpAddr>() {
+            Ok(address) => push_address(&mut request, address),
+            Err(_) if self.remote_dns => {
+                request.push(0x03);
+                request.push(host.len() as u8);
+                request.extend_from_slice(host.as_bytes());
+            }
+            Err(_) => {
+                let address = tokio::net::lookup_host((host, port)).await?.next().ok_or("the host has no address")?;
+                push_address(&mut request, address.ip());
+            }
+        }
+        request.extend_from_slice(&port.to_be_bytes());
+        stream.write_all(&request).await?;
+
+        let mut reply = [0u8; 4];
+        stream.read_exact(&mut reply).await?;
+        if reply[1] != 0x00 {
+            return Err(format!("the SOCKS proxy could not connect to {}:{} (error {})", host, port, reply[1]).into());
+        }
+        // The address the proxy bound to isn't needed, but has to be read past.
+        let bound = match reply[3] {
+            0x01 => 4,
+            0x04 => 16,
+            0x03 => stream.read_u8().await? as usize,
+            _ => return Err("the SOCKS proxy sent an invalid reply".into()),
+        };
+        let mut skipped = vec![0u8; bound + 2];
+        stream.read_exact(&mut skipped).await?;
+        Ok(stream)
+    }
+}
+
+fn push_address(request: &mut Vec<u8>, address: std::net::IpAddr) {
+    match address {
+        std::net::IpAddr::V4(address) => {
+            request.push(0x01);
+            request.extend_from_slice(&address.octets());
+        }
+        std::net::IpAddr::V6(address) => {
+            request.push(0x04);
+            request.extend_from_slice(&address.octets());
+        }
+    }
+}
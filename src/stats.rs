@@ -0,0 +1,192 @@
+use std::any::Any;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use hyper_util::client::legacy::connect::Connection;
+
+use crate::failures::FailureTally;
+use crate::latency::LatencyHistogram;
+use crate::retry::RetryTally;
+
+/// Counters shared by every worker for the end of run summary.
+#[derive(Default)]
+pub struct RunStats {
+    pub requests_sent: AtomicUsize,
+    in_flight: AtomicUsize,
+    pub peak_in_flight: AtomicUsize,
+    pub connections_created: AtomicUsize,
+    pub tls_handshakes: AtomicUsize,
+    pub failures: FailureTally,
+    pub latency: LatencyHistogram,
+    pub retries: RetryTally,
+    reported: AtomicUsize,
+}
+
+impl RunStats {
+    // Marks a request as started. The returned guard marks it as finished when dropped, which
+    // also covers requests whose task panicked halfway.
+    pub fn start_request(self: &Arc<Self>) -> InFlight {
+        self.requests_sent.fetch_add(1, Ordering::Relaxed);
+        let current = self.in_flight.fetch_add(1, Ordering::Relaxed) + 1;
+        self.peak_in_flight.fetch_max(current, Ordering::Relaxed);
+        InFlight { stats: Arc::clone(self) }
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    // Counts a reported result, returns how many there are now.
+    pub fn count_reported(&self) -> usize {
+        self.reported.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub fn reported(&self) -> usize {
+        self.reported.load(Ordering::Relaxed)
+    }
+
+    // Whether every request of the run failed, which tells nothing about the target.
+    pub fn all_failed(&self) -> bool {
+        let sent = self.requests_sent.load(Ordering::Relaxed);
+        sent > 0 && self.failures.total() >= sent
+    }
+
+    // How the run ended and how long it took, its failures and tags, then its latency, retries and
+    // connections.
+    pub fn print_summary(&self, elapsed: Duration, interrupted: bool, tags: &[(String, String)], target_labels: &[String]) {
+        let mut summary = if interrupted {
+            format!(
+                "Interrupted! Time taken: {:.2?}. Requests sent: {}. Results reported: {}",
+                elapsed,
+                self.requests_sent.load(Ordering::Relaxed),
+                self.reported()
+            )
+        } else {
+            format!("Complete! Time taken: {:.2?}", elapsed)
+        };
+        if let Some(failures) = self.failures.summary() {
+            summary.push_str(&format!(". {}", failures));
+        }
+        if !tags.is_empty() {
+            let tags: Vec<String> = tags.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+            summary.push_str(&format!(". Tags: {}", tags.join(", ")));
+        }
+        println!("{}", summary);
+        if let Some(latency) = self.latency.summary(elapsed) {
+            println!("{}", latency);
+        }
+        self.retries.print_summary(target_labels);
+    }
+
+    pub fn print_connection_summary(&self) {
+        let requests = self.requests_sent.load(Ordering::Relaxed);
+        let created = self.connections_created.load(Ordering::Relaxed);
+        println!(
+            "Connections: {} created, {} reused, {} TLS handshakes. Peak concurrent requests: {}.{}",
+            created,
+            requests.saturating_sub(created),
+            self.tls_handshakes.load(Ordering::Relaxed),
+            self.peak_in_flight.load(Ordering::Relaxed),
+            match open_sockets() {
+                Some(count) => format!(" Open sockets: {}.", count),
+                None => String::new(),
+            }
+        );
+    }
+}
+
+pub struct InFlight {
+    stats: Arc<RunStats>,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.stats.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// Sockets currently held by the process. Only Linux makes this cheap to find out.
+#[cfg(target_os = "linux")]
+fn open_sockets() -> Option<usize> {
+    let entries = std::fs::read_dir("/proc/self/fd").ok()?;
+    Some(
+        entries
+            .filter_map(|entry| std::fs::read_link(entry.ok()?.path()).ok())
+            .filter(|target| target.to_string_lossy().starts_with("socket:"))
+            .count(),
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_sockets() -> Option<usize> {
+    None
+}
+
+/// Connector layer for the reqwest client. reqwest only calls its connector when the pool has no
+/// idle connection to reuse, so every call that succeeds is a new connection.
+#[derive(Clone)]
+pub struct ConnectionCounter {
+    stats: Arc<RunStats>,
+}
+
+impl ConnectionCounter {
+    pub fn new(stats: Arc<RunStats>) -> ConnectionCounter {
+        ConnectionCounter { stats }
+    }
+}
+
+impl<S> tower_layer::Layer<S> for ConnectionCounter {
+    type Service = CountedConnector<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CountedConnector { inner, stats: Arc::clone(&self.stats) }
+    }
+}
+
+#[derive(Clone)]
+pub struct CountedConnector<S> {
+    inner: S,
+    stats: Arc<RunStats>,
+}
+
+impl<S, R> tower_service::Service<R> for CountedConnector<S>
+where
+    R: 'static,
+    S: tower_service::Service<R>,
+    S::Response: Connection,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let stats = Arc::clone(&self.stats);
+        // The hyper transport connects by plain URI, where the scheme tells whether TLS is used.
+        let https_uri = (&request as &dyn Any)
+            .downcast_ref::<http::Uri>()
+            .is_some_and(|uri| uri.scheme_str() == Some("https"));
+        let connecting = self.inner.call(request);
+        Box::pin(async move {
+            let connection = connecting.await?;
+            stats.connections_created.fetch_add(1, Ordering::Relaxed);
+
+            // With tls_info enabled reqwest attaches the TLS details to the connection, which is
+            // how a TLS connection can be told apart from a plain one from out here.
+            let mut extensions = http::Extensions::new();
+            connection.connected().get_extras(&mut extensions);
+            if https_uri || extensions.get::<reqwest::tls::TlsInfo>().is_some() {
+                stats.tls_handshakes.fetch_add(1, Ordering::Relaxed);
+            }
+            Ok(connection)
+        })
+    }
+}
@@ -0,0 +1,69 @@
+/// Words made of the same text repeated at growing lengths, for --length-sweep. Probes where
+/// buffers, header sizes or body limits give out without writing a wordlist for it.
+#[derive(Clone, Debug)]
+pub struct LengthSweep {
+    text: String,
+    min: usize,
+    max: usize,
+    step: Step,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Step {
+    PowersOfTwo,
+    Fixed(usize),
+}
+
+impl LengthSweep {
+    // Parses "'A'x1..65536", "Ax100..1000:step=100" or "'%00'x1..64:step=powers-of-two". The text
+    // may be quoted, the step is powers of two unless given. Lengths count repetitions of the text.
+    pub fn parse(spec: &str) -> Result<LengthSweep, String> {
+        let (sweep, step) = match spec.rsplit_once(":step=") {
+            Some((sweep, "powers-of-two")) => (sweep, Step::PowersOfTwo),
+            Some((sweep, step)) => match step.parse() {
+                Ok(step) if step > 0 => (sweep, Step::Fixed(step)),
+                _ => return Err(format!("invalid step \"{}\", use a number or powers-of-two", step)),
+            },
+            None => (spec, Step::PowersOfTwo),
+        };
+        let (text, range) = sweep.rsplit_once('x').ok_or("expected TEXTxMIN..MAX, such as 'A'x1..65536")?;
+        let text = ['\'', '"']
+            .iter()
+            .find_map(|quote| text.strip_prefix(*quote)?.strip_suffix(*quote))
+            .unwrap_or(text);
+        if text.is_empty() {
+            return Err(String::from("the text to repeat is empty"));
+        }
+        let (min, max) = range.split_once("..").unwrap_or((range, range));
+        let min: usize = min.parse().map_err(|_| format!("invalid length \"{}\"", min))?;
+        let max: usize = max.parse().map_err(|_| format!("invalid length \"{}\"", max))?;
+        if min == 0 || min > max {
+            return Err(format!("invalid range {}..{}, lengths start at 1", min, max));
+        }
+        Ok(LengthSweep { text: text.to_string(), min, max, step })
+    }
+
+    // The lengths of the sweep, always starting at the minimum and ending at the maximum.
+    fn lengths(&self) -> Vec<usize> {
+        let mut lengths = vec![self.min];
+        loop {
+            let last = *lengths.last().unwrap();
+            let next = match self.step {
+                Step::PowersOfTwo => (last + 1).next_power_of_two(),
+                Step::Fixed(step) => last + step,
+            };
+            if next >= self.max {
+                break;
+            }
+            lengths.push(next);
+        }
+        if *lengths.last().unwrap() != self.max {
+            lengths.push(self.max);
+        }
+        lengths
+    }
+
+    pub fn words(&self) -> Vec<String> {
+        self.lengths().into_iter().map(|length| self.text.repeat(length)).collect()
+    }
+}
@@ -0,0 +1,165 @@
+use crate::payloads::Payload;
+use crate::placeholders;
+
+/// A piece of a template: either text kept as is, or what sits between a pair of delimiters.
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Placeholder(String),
+}
+
+/// A text with placeholders, a URL, a header name or value or a body, cut into its segments once
+/// so that every request only has to put the words in. The same implementation serves every part
+/// of the request.
+#[derive(Debug, Clone)]
+pub struct Template {
+    text: String,
+    segments: Vec<Segment>,
+    delimiters: usize,
+}
+
+impl Template {
+    // Cuts the text at its delimiters. Pieces alternate between text and placeholders, as
+    // delimiters come in pairs, so splitting on the delimiter itself never lands inside a
+    // multi-byte character whatever the delimiter and the text are. An odd number of delimiters
+    // has to be refused before rendering, the trailing piece would otherwise be taken as a
+    // placeholder.
+    pub fn parse(text: &str, delim: &str) -> Template {
+        if delim.is_empty() {
+            return Template { text: text.to_string(), segments: vec![Segment::Text(text.to_string())], delimiters: 0 };
+        }
+        let segments = text
+            .split(delim)
+            .enumerate()
+            .filter(|(index, piece)| index % 2 == 1 || !piece.is_empty())
+            .map(|(index, piece)| match index % 2 {
+                0 => Segment::Text(piece.to_string()),
+                _ => Segment::Placeholder(piece.to_string()),
+            })
+            .collect();
+        Template { text: text.to_string(), segments, delimiters: text.matches(delim).count() }
+    }
+
+    // The text as it was given, delimiters included.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn delimiters(&self) -> usize {
+        self.delimiters
+    }
+
+    pub fn has_placeholders(&self) -> bool {
+        self.delimiters > 0
+    }
+
+    // What is between each pair of delimiters, in order.
+    pub fn placeholders(&self) -> Vec<&str> {
+        self.segments
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Placeholder(inner) => Some(inner.as_str()),
+                Segment::Text(_) => None,
+            })
+            .collect()
+    }
+
+    // Puts the payload's words (or the declared defaults when there is no payload) in place of
+    // every placeholder.
+    pub fn render(&self, payload: Option<&Payload>) -> String {
+        self.render_with(payload, |value| value)
+    }
+
+    // Same as render, with every filled in value going through `encode` first.
+    pub fn render_with(&self, payload: Option<&Payload>, encode: impl Fn(String) -> String) -> String {
+        let mut rendered = String::with_capacity(self.text.len());
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => rendered.push_str(text),
+                Segment::Placeholder(inner) => rendered.push_str(&encode(placeholders::fill(inner, payload))),
+            }
+        }
+        rendered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(word: &str) -> Payload {
+        Payload::single(word.to_string())
+    }
+
+    fn render(text: &str, delim: &str, payload: Option<&Payload>) -> String {
+        Template::parse(text, delim).render(payload)
+    }
+
+    #[test]
+    fn text_without_delimiters_is_left_alone() {
+        assert_eq!(render("http://example.com/é", "##", Some(&word("x"))), "http://example.com/é");
+        assert_eq!(render("", "##", Some(&word("x"))), "");
+        assert!(!Template::parse("http://example.com/é", "##").has_placeholders());
+    }
+
+    #[test]
+    fn ascii_delimiters_with_unicode_words() {
+        assert_eq!(render("/##FUZZ##/end", "##", Some(&word("日本語"))), "/日本語/end");
+        assert_eq!(render("é##FUZZ##ü", "##", Some(&word("🦀"))), "é🦀ü");
+    }
+
+    #[test]
+    fn unicode_delimiters() {
+        assert_eq!(render("/§FUZZ§", "§", Some(&word("admin"))), "/admin");
+        assert_eq!(render("ü🔥x🔥ü🔥y🔥", "🔥", Some(&word("ñ"))), "üñüñ");
+        assert_eq!(render("x«»F«»y", "«»", Some(&word("á"))), "xáy");
+    }
+
+    #[test]
+    fn delimiters_made_of_text_characters() {
+        // The delimiter shares bytes with the characters around it.
+        assert_eq!(render("éé€éé", "é", Some(&word("w"))), "w€w");
+    }
+
+    #[test]
+    fn placeholders_at_the_edges_and_next_to_each_other() {
+        assert_eq!(render("##a####b##", "##", Some(&word("w"))), "ww");
+        assert_eq!(render("##a##-##b##", "##", Some(&word("ä"))), "ä-ä");
+    }
+
+    #[test]
+    fn defaults_are_used_without_a_payload() {
+        assert_eq!(render("/?u=§USER:=ユーザー§", "§", None), "/?u=ユーザー");
+    }
+
+    #[test]
+    fn encoding_only_applies_to_the_filled_in_values() {
+        let template = Template::parse("/ö/##FUZZ##", "##");
+        let rendered = template.render_with(Some(&word("a b")), |value| value.replace(' ', "%20"));
+        assert_eq!(rendered, "/ö/a%20b");
+    }
+
+    #[test]
+    fn placeholders_are_listed_in_order() {
+        assert_eq!(Template::parse("x§A§y§B:=ü§", "§").placeholders(), vec!["A", "B:=ü"]);
+        assert!(Template::parse("no placeholders", "§").placeholders().is_empty());
+    }
+
+    #[test]
+    fn delimiters_are_counted() {
+        assert_eq!(Template::parse("§a§b§", "§").delimiters(), 3);
+        assert_eq!(Template::parse("##a##", "##").delimiters(), 2);
+    }
+
+    #[test]
+    fn an_empty_delimiter_has_no_placeholders() {
+        let template = Template::parse("ab", "");
+        assert!(!template.has_placeholders());
+        assert_eq!(template.render(Some(&word("x"))), "ab");
+    }
+
+    #[test]
+    fn the_text_is_kept_as_given() {
+        assert_eq!(Template::parse("/##FUZZ##", "##").text(), "/##FUZZ##");
+    }
+}
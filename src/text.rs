@@ -0,0 +1,45 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+// Elements whose content is never shown on the page.
+const HIDDEN_ELEMENTS: [&str; 4] = ["script", "style", "noscript", "template"];
+
+// Length of the text a browser would show for an HTML body, in characters: tags, comments and
+// hidden elements are dropped, entities decoded and whitespace runs counted once. Markup that
+// changes on every request (nonces, tokens, ids) doesn't move it, and neither does the encoding.
+pub fn visible_text_length(body: &str) -> usize {
+    visible_text(body).chars().count()
+}
+
+// The text a browser would show for an HTML body, whitespace runs squeezed to one space.
+pub fn visible_text(body: &str) -> String {
+    static HIDDEN: OnceLock<Regex> = OnceLock::new();
+    static TAGS: OnceLock<Regex> = OnceLock::new();
+    let hidden = HIDDEN.get_or_init(|| {
+        let elements = HIDDEN_ELEMENTS.join("|");
+        Regex::new(&format!(r"(?is)<!--.*?-->|<({})\b[^>]*>.*?</({})\s*>", elements, elements)).unwrap()
+    });
+    let tags = TAGS.get_or_init(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+
+    let text = hidden.replace_all(body, " ");
+    let text = tags.replace_all(&text, " ");
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+// The start of a body on a single line, for --preview: whitespace runs become one space and
+// control characters are dropped, then it is cut after `max` characters.
+pub fn preview(body: &str, max: usize) -> String {
+    let line = body.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let mut preview: String = line.chars().filter(|c| !c.is_control()).take(max).collect();
+    if line.chars().filter(|c| !c.is_control()).nth(max).is_some() {
+        preview.push_str("...");
+    }
+    preview
+}
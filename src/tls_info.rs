@@ -0,0 +1,155 @@
+use std::net::IpAddr;
+#[cfg(target_os = "linux")]
+use std::time::Duration;
+
+use crate::fingerprint::canonical_url;
+use crate::hosts::HostsMap;
+
+#[cfg(target_os = "linux")]
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What a TLS handshake with a host showed: the negotiated version and cipher, and the leaf
+/// certificate. Whether the certificate is trusted is recorded rather than required, an
+/// untrusted one is exactly what an intercepting middlebox looks like.
+#[derive(Debug)]
+struct TlsInfo {
+    version: String,
+    cipher: Option<String>,
+    subject: String,
+    issuer: String,
+    names: Vec<String>,
+    expires: String,
+    // None when the chain and the host name were verified, the reason otherwise.
+    untrusted: Option<String>,
+}
+
+// Shakes hands once with every https host the targets point to, on a separate connection made
+// straight to the host, and prints what was negotiated and the certificate it presented.
+pub async fn report(targets: &[String], delim: &str, hosts_file: Option<&HostsMap>) {
+    let mut hosts: Vec<(String, u16)> = Vec::new();
+    for target in targets {
+        let Ok(url) = reqwest::Url::parse(&canonical_url(target, delim)) else { continue };
+        if url.scheme() != "https" {
+            continue;
+        }
+        let Some(host) = url.host_str() else { continue };
+        let host = (host.trim_start_matches('[').trim_end_matches(']').to_string(), url.port_or_known_default().unwrap_or(443));
+        if !hosts.contains(&host) {
+            hosts.push(host);
+        }
+    }
+
+    let handles: Vec<_> = hosts
+        .into_iter()
+        .map(|(host, port)| {
+            let mapped = hosts_file.and_then(|hosts| hosts.lookup(&host)).and_then(|addresses| addresses.first().copied());
+            tokio::task::spawn_blocking(move || (format!("{}:{}", host, port), probe(&host, port, mapped)))
+        })
+        .collect();
+    for handle in handles {
+        let Ok((address, info)) = handle.await else { continue };
+        match info {
+            Ok(info) => info.print(&address),
+            Err(error) => println!("TLS {}: {}", address, error),
+        }
+    }
+}
+
+impl TlsInfo {
+    fn print(&self, address: &str) {
+        println!("TLS {}: {}, {}", address, self.version, self.cipher.as_deref().unwrap_or("unknown cipher"));
+        println!("  Certificate: {}, issued by {}, expires {}", self.subject, self.issuer, self.expires);
+        if !self.names.is_empty() {
+            println!("  Names: {}", self.names.join(", "));
+        }
+        match &self.untrusted {
+            Some(reason) => println!("  Not trusted: {}", reason),
+            None => println!("  Trusted"),
+        }
+    }
+}
+
+// Connects to the address of the --hosts-file when the host is listed there.
+#[cfg(target_os = "linux")]
+fn probe(host: &str, port: u16, mapped: Option<IpAddr>) -> Result<TlsInfo, String> {
+    use openssl::nid::Nid;
+    use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+    use openssl::x509::{X509NameRef, X509VerifyResult};
+    use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+
+    let address = match mapped {
+        Some(address) => SocketAddr::new(address, port),
+        None => (host, port)
+            .to_socket_addrs()
+            .map_err(|e| e.to_string())?
+            .next()
+            .ok_or_else(|| String::from("the host has no address"))?,
+    };
+    let stream = TcpStream::connect_timeout(&address, TIMEOUT).map_err(|e| e.to_string())?;
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(|e| e.to_string())?;
+    stream.set_write_timeout(Some(TIMEOUT)).map_err(|e| e.to_string())?;
+
+    // The chain is still verified, only the handshake doesn't stop when it isn't trusted.
+    let mut connector = SslConnector::builder(SslMethod::tls()).map_err(|e| e.to_string())?;
+    connector.set_verify(SslVerifyMode::NONE);
+    let stream = connector.build().connect(host, stream).map_err(|e| e.to_string())?;
+    let ssl = stream.ssl();
+    let certificate = ssl.peer_certificate().ok_or_else(|| String::from("no certificate was presented"))?;
+
+    let name = |name: &X509NameRef| {
+        let entries: Vec<String> = name
+            .entries()
+            .map(|entry| {
+                let key = entry.object().nid().short_name().unwrap_or("?");
+                format!("{}={}", key, String::from_utf8_lossy(entry.data().as_slice()))
+            })
+            .collect();
+        if entries.is_empty() {
+            String::from("(empty)")
+        } else {
+            entries.join(", ")
+        }
+    };
+    let mut names: Vec<String> = certificate
+        .subject_alt_names()
+        .map(|names| {
+            names
+                .iter()
+                .filter_map(|name| name.dnsname().map(str::to_string).or_else(|| name.ipaddress().map(format_ip)))
+                .collect()
+        })
+        .unwrap_or_default();
+    if names.is_empty() {
+        names.extend(certificate.subject_name().entries_by_nid(Nid::COMMONNAME).map(|entry| {
+            String::from_utf8_lossy(entry.data().as_slice()).to_string()
+        }));
+    }
+    let verified = ssl.verify_result();
+    Ok(TlsInfo {
+        version: ssl.version_str().to_string(),
+        cipher: ssl.current_cipher().map(|cipher| cipher.name().to_string()),
+        subject: name(certificate.subject_name()),
+        issuer: name(certificate.issuer_name()),
+        names,
+        expires: certificate.not_after().to_string(),
+        untrusted: (verified != X509VerifyResult::OK).then(|| verified.error_string().to_string()),
+    })
+}
+
+// Shown as the certificate gives them, 4 or 16 bytes.
+#[cfg(target_os = "linux")]
+fn format_ip(bytes: &[u8]) -> String {
+    match <[u8; 4]>::try_from(bytes) {
+        Ok(v4) => std::net::Ipv4Addr::from(v4).to_string(),
+        Err(_) => match <[u8; 16]>::try_from(bytes) {
+            Ok(v6) => std::net::Ipv6Addr::from(v6).to_string(),
+            Err(_) => String::from("?"),
+        },
+    }
+}
+
+// The TLS library used elsewhere doesn't tell the negotiated version and cipher.
+#[cfg(not(target_os = "linux"))]
+fn probe(_host: &str, _port: u16, _mapped: Option<IpAddr>) -> Result<TlsInfo, String> {
+    Err(String::from("--tls-info is only available on Linux"))
+}
@@ -0,0 +1,601 @@
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::bandwidth::BandwidthLimiter;
+use crate::hosts::{HostsMap, HostsResolver, IpFamily};
+use crate::socks::{self, Socks5Proxy};
+use crate::stats::{ConnectionCounter, RunStats};
+use crate::Args;
+
+pub type TransportError = Box<dyn std::error::Error + Send + Sync>;
+pub type SendFuture<'a> = Pin<Box<dyn Future<Output = Result<TransportResponse, TransportError>> + Send + 'a>>;
+
+/// Backends able to send a request.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransportKind {
+    /// reqwest client, follows redirects when asked to and pools connections.
+    Reqwest,
+    /// Plain hyper client, headers are sent as given without any defaults added.
+    Hyper,
+    /// Writes the request straight to the socket, in exactly the order and form it was written in.
+    Raw,
+}
+
+/// A request with every delimiter already replaced, ready to be sent by any transport.
+#[derive(Debug, Clone)]
+pub struct RenderedRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
+}
+
+impl RenderedRequest {
+    // Roughly how many bytes the request takes on the wire.
+    pub fn size(&self) -> usize {
+        let headers: usize = self.headers.iter().map(|(key, value)| key.len() + value.len() + 4).sum();
+        self.method.len() + self.url.len() + headers + self.body.as_ref().map_or(0, Vec::len) + 12
+    }
+
+    // The request as HTTP/1.1 text, exactly the way the raw transport writes it to the socket.
+    pub fn to_http(&self) -> Result<String, TransportError> {
+        let target = RawTarget::parse(&self.url)?;
+        Ok(String::from_utf8_lossy(&raw_request_bytes(self, &target)).to_string())
+    }
+}
+
+/// Something that can send a rendered request and hand back the response as it comes in.
+pub trait Transport: Send + Sync {
+    fn send<'a>(&'a self, request: &'a RenderedRequest) -> SendFuture<'a>;
+}
+
+enum ResponseBody {
+    Reqwest(reqwest::Response),
+    Hyper(hyper::body::Incoming),
+    Raw(Box<RawBody>),
+}
+
+/// Status and headers of a response. The body is read separately, either in chunks or whole.
+pub struct TransportResponse {
+    pub status: reqwest::StatusCode,
+    pub headers: reqwest::header::HeaderMap,
+    body: ResponseBody,
+    // With --max-bandwidth every piece of the body is paid for as it comes in.
+    limiter: Option<Arc<BandwidthLimiter>>,
+    // With --timeout the body has to be read by then, past it reading fails.
+    deadline: Option<tokio::time::Instant>,
+}
+
+impl TransportResponse {
+    pub fn limit(&mut self, limiter: Arc<BandwidthLimiter>) {
+        self.limiter = Some(limiter);
+    }
+
+    pub fn set_deadline(&mut self, deadline: Option<tokio::time::Instant>) {
+        self.deadline = deadline;
+    }
+
+    // Returns the next piece of the body, or None once it has been fully read.
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, TransportError> {
+        let chunk = match self.deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline, self.next_chunk()).await??,
+            None => self.next_chunk().await?,
+        };
+        if let (Some(limiter), Some(chunk)) = (&self.limiter, &chunk) {
+            limiter.consume(chunk.len()).await;
+        }
+        Ok(chunk)
+    }
+
+    async fn next_chunk(&mut self) -> Result<Option<Bytes>, TransportError> {
+        match &mut self.body {
+            ResponseBody::Reqwest(response) => Ok(response.chunk().await?),
+            ResponseBody::Hyper(incoming) => loop {
+                match incoming.frame().await {
+                    None => return Ok(None),
+                    Some(frame) => {
+                        // Trailers are frames too, those are skipped.
+                        if let Ok(data) = frame?.into_data() {
+                            return Ok(Some(data));
+                        }
+                    }
+                }
+            },
+            ResponseBody::Raw(body) => body.chunk().await,
+        }
+    }
+
+    pub async fn bytes(&mut self) -> Result<Vec<u8>, TransportError> {
+        let mut body = Vec::new();
+        while let Some(chunk) = self.chunk().await? {
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+}
+
+// Whether the request failed because it took too long, to connect or altogether.
+pub fn is_timeout(error: &(dyn std::error::Error + 'static)) -> bool {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(error);
+    while let Some(current) = source {
+        let timed_out = current.downcast_ref::<reqwest::Error>().is_some_and(reqwest::Error::is_timeout)
+            || current.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == std::io::ErrorKind::TimedOut)
+            || current.is::<tokio::time::error::Elapsed>();
+        if timed_out {
+            return true;
+        }
+        source = current.source();
+    }
+    false
+}
+
+// Builds the transport chosen with --transport, going through the given proxy or else through
+// --proxy. The reqwest transport goes through HTTP proxies and the raw one through SOCKS5 ones.
+// Fails on a proxy the transport can't go through.
+pub fn build(args: &Args, stats: &Arc<RunStats>, proxy: Option<&str>) -> Result<Box<dyn Transport>, String> {
+    build_with(args, stats, proxy, None)
+}
+
+// The same transport, only ever connecting over one IP family.
+pub fn build_for_family(args: &Args, stats: &Arc<RunStats>, family: IpFamily) -> Result<Box<dyn Transport>, String> {
+    build_with(args, stats, None, Some(family))
+}
+
+fn build_with(
+    args: &Args,
+    stats: &Arc<RunStats>,
+    proxy: Option<&str>,
+    family: Option<IpFamily>,
+) -> Result<Box<dyn Transport>, String> {
+    let proxy = proxy.or(args.proxy.as_deref());
+    Ok(match args.transport {
+        TransportKind::Reqwest => Box::new(ReqwestTransport::new(args, stats, proxy, family)?),
+        TransportKind::Hyper => Box::new(HyperTransport::new(args, stats, family)),
+        TransportKind::Raw => {
+            let proxy = proxy
+                .map(|proxy| Socks5Proxy::parse(proxy).map_err(|e| format!("Invalid proxy {}: {}", proxy, e)))
+                .transpose()?;
+            Box::new(RawTransport {
+                stats: Arc::clone(stats),
+                proxy,
+                connect_timeout: args.connect_timeout,
+                hosts: args.hosts_file.clone(),
+                family,
+            })
+        }
+    })
+}
+
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    // We need to create a client to disallow redirects. By default, reqwest follows all
+    // redirects. This is detrimental depending on the performed activity, but by creating
+    // a client there is extra overhead in performance.
+    pub fn new(args: &Args, stats: &Arc<RunStats>, proxy: Option<&str>, family: Option<IpFamily>) -> Result<ReqwestTransport, String> {
+        let mut client = reqwest::ClientBuilder::new()
+            .tls_info(true)
+            .connector_layer(ConnectionCounter::new(Arc::clone(stats)));
+        if let Some(timeout) = args.connect_timeout {
+            client = client.connect_timeout(timeout);
+        }
+        // Requests going through an HTTP proxy are resolved by the proxy.
+        if family.is_some() {
+            client = client.dns_resolver(Arc::new(HostsResolver::new(args.hosts_file.clone(), family)));
+        }
+        for (name, addresses) in args.hosts_file.iter().flat_map(HostsMap::entries).filter(|_| family.is_none()) {
+            let addresses: Vec<SocketAddr> = addresses.iter().map(|address| SocketAddr::new(*address, 0)).collect();
+            client = client.resolve_to_addrs(name, &addresses);
+        }
+        if !args.allowredirects {
+            client = client.redirect(reqwest::redirect::Policy::none());
+        }
+        if let Some(proxy) = proxy {
+            if socks::is_socks(proxy) {
+                return Err(String::from("SOCKS proxies are only supported by the raw transport, add --transport raw"));
+            }
+            client = client.proxy(reqwest::Proxy::all(proxy).map_err(|e| format!("Invalid proxy {}: {}", proxy, e))?);
+        }
+        let client = client.build().map_err(|e| format!("Could not set up the HTTP client: {}", e))?;
+        Ok(ReqwestTransport { client })
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send<'a>(&'a self, request: &'a RenderedRequest) -> SendFuture<'a> {
+        Box::pin(async move {
+            let mut builder = self.client.request(
+                reqwest::Method::from_bytes(request.method.as_bytes())?,
+                request.url.as_str(),
+            );
+            for (key, value) in &request.headers {
+                builder = builder.header(key, value);
+            }
+            if let Some(body) = &request.body {
+                builder = builder.body(body.clone());
+            }
+
+            let response = builder.send().await?;
+            Ok(TransportResponse {
+                status: response.status(),
+                headers: response.headers().clone(),
+                body: ResponseBody::Reqwest(response),
+                limiter: None,
+                deadline: None,
+            })
+        })
+    }
+}
+
+type HyperConnector =
+    crate::stats::CountedConnector<hyper_tls::HttpsConnector<hyper_util::client::legacy::connect::HttpConnector<HostsResolver>>>;
+
+pub struct HyperTransport {
+    client: hyper_util::client::legacy::Client<HyperConnector, Full<Bytes>>,
+}
+
+impl HyperTransport {
+    pub fn new(args: &Args, stats: &Arc<RunStats>, family: Option<IpFamily>) -> HyperTransport {
+        let resolver = HostsResolver::new(args.hosts_file.clone(), family);
+        let mut http = hyper_util::client::legacy::connect::HttpConnector::new_with_resolver(resolver);
+        http.enforce_http(false);
+        http.set_connect_timeout(args.connect_timeout);
+        let https = hyper_tls::HttpsConnector::new_with_connector(http);
+        let connector = tower_layer::Layer::layer(&ConnectionCounter::new(Arc::clone(stats)), https);
+        HyperTransport {
+            client: hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new()).build(connector),
+        }
+    }
+}
+
+impl Transport for HyperTransport {
+    fn send<'a>(&'a self, request: &'a RenderedRequest) -> SendFuture<'a> {
+        Box::pin(async move {
+            let mut builder = http::Request::builder()
+                .method(request.method.as_str())
+                .uri(request.url.as_str());
+            for (key, value) in &request.headers {
+                builder = builder.header(key, value);
+            }
+            let body = Full::new(Bytes::from(request.body.clone().unwrap_or_default()));
+
+            let (parts, incoming) = self.client.request(builder.body(body)?).await?.into_parts();
+            Ok(TransportResponse {
+                status: parts.status,
+                headers: parts.headers,
+                body: ResponseBody::Hyper(incoming),
+                limiter: None,
+                deadline: None,
+            })
+        })
+    }
+}
+
+trait RawStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> RawStream for T {}
+
+/// Opens a new connection for every request and writes the request line and headers exactly as
+/// they were given. Host, Content-Length and Connection are only added when missing, so sending
+/// conflicting or malformed framing on purpose is possible.
+pub struct RawTransport {
+    stats: Arc<RunStats>,
+    proxy: Option<Socks5Proxy>,
+    connect_timeout: Option<Duration>,
+    hosts: Option<HostsMap>,
+    family: Option<IpFamily>,
+}
+
+impl Transport for RawTransport {
+    fn send<'a>(&'a self, request: &'a RenderedRequest) -> SendFuture<'a> {
+        Box::pin(async move {
+            let target = RawTarget::parse(&request.url)?;
+
+            // A name of the --hosts-file goes to its address, through the proxy as well. Held to an
+            // IP family, the name is resolved here and only that family's addresses are kept.
+            let mut mapped: Option<Vec<SocketAddr>> = self.hosts.as_ref().and_then(|hosts| hosts.lookup(&target.host)).map(|addresses| {
+                addresses.iter().map(|address| SocketAddr::new(*address, target.port)).collect()
+            });
+            if let Some(family) = self.family {
+                let addresses = match mapped {
+                    Some(addresses) => addresses,
+                    None => tokio::net::lookup_host((target.host.as_str(), target.port)).await?.collect(),
+                };
+                let kept: Vec<SocketAddr> = addresses.into_iter().filter(|address| family.matches(&address.ip())).collect();
+                if kept.is_empty() {
+                    return Err(format!("{} has no {} address", target.host, family).into());
+                }
+                mapped = Some(kept);
+            }
+            let connect = async {
+                match (&self.proxy, mapped) {
+                    (Some(proxy), Some(addresses)) => proxy.connect(&addresses[0].ip().to_string(), target.port).await,
+                    (Some(proxy), None) => proxy.connect(&target.host, target.port).await,
+                    (None, Some(addresses)) => Ok(TcpStream::connect(&addresses[..]).await?),
+                    (None, None) => Ok(TcpStream::connect((target.host.as_str(), target.port)).await?),
+                }
+            };
+            let tcp = match self.connect_timeout {
+                Some(timeout) => tokio::time::timeout(timeout, connect).await??,
+                None => connect.await?,
+            };
+            self.stats.connections_created.fetch_add(1, Ordering::Relaxed);
+            let mut stream: Box<dyn RawStream> = if target.tls {
+                let connector = tokio_native_tls::TlsConnector::from(native_tls::TlsConnector::new()?);
+                let tls = connector.connect(&target.host, tcp).await?;
+                self.stats.tls_handshakes.fetch_add(1, Ordering::Relaxed);
+                Box::new(tls)
+            } else {
+                Box::new(tcp)
+            };
+
+            stream.write_all(&raw_request_bytes(request, &target)).await?;
+            stream.flush().await?;
+
+            let mut body = RawBody { stream, buffer: Vec::new(), framing: Framing::UntilClose };
+            let (status, headers) = body.read_head().await?;
+            body.framing = Framing::from_headers(&request.method, status, &headers);
+            Ok(TransportResponse {
+                status,
+                headers,
+                body: ResponseBody::Raw(Box::new(body)),
+                limiter: None,
+                deadline: None,
+            })
+        })
+    }
+}
+
+struct RawTarget {
+    tls: bool,
+    host: String,
+    port: u16,
+    authority: String,
+    path: String,
+}
+
+impl RawTarget {
+    // The URL is split by hand rather than through a URL parser, which would normalise the path
+    // and defeat the point of sending it raw.
+    fn parse(url: &str) -> Result<RawTarget, TransportError> {
+        let (scheme, rest) = url.split_once("://").ok_or("URL has no scheme")?;
+        let tls = match scheme.to_ascii_lowercase().as_str() {
+            "http" => false,
+            "https" => true,
+            other => return Err(format!("Unsupported scheme {}", other).into()),
+        };
+        let split = rest.find(['/', '?']).unwrap_or(rest.len());
+        let (authority, path) = rest.split_at(split);
+        let path = match path {
+            "" => String::from("/"),
+            path if path.starts_with('?') => format!("/{}", path),
+            path => path.to_string(),
+        };
+
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => (host, port.parse()?),
+            _ => (authority, if tls { 443 } else { 80 }),
+        };
+        Ok(RawTarget {
+            tls,
+            host: host.trim_start_matches('[').trim_end_matches(']').to_string(),
+            port,
+            authority: authority.to_string(),
+            path,
+        })
+    }
+}
+
+fn raw_request_bytes(request: &RenderedRequest, target: &RawTarget) -> Vec<u8> {
+    let has = |name: &str| request.headers.iter().any(|(key, _)| key.eq_ignore_ascii_case(name));
+
+    let mut head = format!("{} {} HTTP/1.1\r\n", request.method, target.path);
+    if !has("host") {
+        head.push_str(&format!("Host: {}\r\n", target.authority));
+    }
+    for (key, value) in &request.headers {
+        head.push_str(&format!("{}: {}\r\n", key, value));
+    }
+    if let Some(body) = &request.body {
+        if !has("content-length") && !has("transfer-encoding") {
+            head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        }
+    }
+    if !has("connection") {
+        head.push_str("Connection: close\r\n");
+    }
+    head.push_str("\r\n");
+
+    let mut bytes = head.into_bytes();
+    if let Some(body) = &request.body {
+        bytes.extend_from_slice(body);
+    }
+    bytes
+}
+
+enum Framing {
+    Length(usize),
+    // Bytes left in the current chunk, and whether the CRLF closing the previous chunk is pending.
+    Chunked { remaining: usize, trailing_crlf: bool },
+    UntilClose,
+    Done,
+}
+
+impl Framing {
+    fn from_headers(method: &str, status: reqwest::StatusCode, headers: &reqwest::header::HeaderMap) -> Framing {
+        if method.eq_ignore_ascii_case("HEAD")
+            || status.is_informational()
+            || status == reqwest::StatusCode::NO_CONTENT
+            || status == reqwest::StatusCode::NOT_MODIFIED
+        {
+            return Framing::Done;
+        }
+        let header = |name: reqwest::header::HeaderName| {
+            headers.get(name).and_then(|value| value.to_str().ok()).map(|value| value.trim().to_ascii_lowercase())
+        };
+        if header(reqwest::header::TRANSFER_ENCODING).is_some_and(|value| value.ends_with("chunked")) {
+            return Framing::Chunked { remaining: 0, trailing_crlf: false };
+        }
+        match header(reqwest::header::CONTENT_LENGTH).and_then(|value| value.parse().ok()) {
+            Some(length) => Framing::Length(length),
+            None => Framing::UntilClose,
+        }
+    }
+}
+
+struct RawBody {
+    stream: Box<dyn RawStream>,
+    buffer: Vec<u8>,
+    framing: Framing,
+}
+
+impl RawBody {
+    // Reads more bytes from the socket into the buffer. Returns false once the server closed.
+    async fn fill(&mut self) -> Result<bool, TransportError> {
+        let mut chunk = [0u8; 16384];
+        let read = self.stream.read(&mut chunk).await?;
+        self.buffer.extend_from_slice(&chunk[..read]);
+        Ok(read > 0)
+    }
+
+    // Interim responses such as 100 Continue are skipped, the head returned is the final one's. 101
+    // Switching Protocols is final, nothing else comes after it.
+    async fn read_head(&mut self) -> Result<(reqwest::StatusCode, reqwest::header::HeaderMap), TransportError> {
+        loop {
+            let mut header_slots = [httparse::EMPTY_HEADER; 128];
+            let mut response = httparse::Response::new(&mut header_slots);
+            if let httparse::Status::Complete(length) = response.parse(&self.buffer)? {
+                let status = reqwest::StatusCode::from_u16(response.code.unwrap_or(0))?;
+                let mut headers = reqwest::header::HeaderMap::new();
+                for header in response.headers.iter() {
+                    headers.append(
+                        reqwest::header::HeaderName::from_bytes(header.name.as_bytes())?,
+                        reqwest::header::HeaderValue::from_bytes(header.value)?,
+                    );
+                }
+                self.buffer.drain(..length);
+                if status.is_informational() && status != reqwest::StatusCode::SWITCHING_PROTOCOLS {
+                    continue;
+                }
+                return Ok((status, headers));
+            }
+            if !self.fill().await? {
+                return Err("Connection closed before the response headers were received".into());
+            }
+        }
+    }
+
+    async fn chunk(&mut self) -> Result<Option<Bytes>, TransportError> {
+        loop {
+            match self.framing {
+                Framing::Done | Framing::Length(0) => return Ok(None),
+                // A body cut short by the server closing fails, the same as with the other transports.
+                Framing::Length(remaining) => {
+                    if self.buffer.is_empty() && !self.fill().await? {
+                        return Err(format!("Connection closed with {} bytes of the body missing", remaining).into());
+                    }
+                    let take = remaining.min(self.buffer.len());
+                    self.framing = Framing::Length(remaining - take);
+                    return Ok(Some(Bytes::from(self.buffer.drain(..take).collect::<Vec<u8>>())));
+                }
+                Framing::UntilClose => {
+                    if self.buffer.is_empty() && !self.fill().await? {
+                        return Ok(None);
+                    }
+                    return Ok(Some(Bytes::from(std::mem::take(&mut self.buffer))));
+                }
+                Framing::Chunked { remaining, trailing_crlf } if remaining > 0 => {
+                    if self.buffer.is_empty() && !self.fill().await? {
+                        return Err("Connection closed in the middle of a chunk".into());
+                    }
+                    let take = remaining.min(self.buffer.len());
+                    self.framing = Framing::Chunked { remaining: remaining - take, trailing_crlf };
+                    return Ok(Some(Bytes::from(self.buffer.drain(..take).collect::<Vec<u8>>())));
+                }
+                Framing::Chunked { trailing_crlf, .. } => {
+                    // Needs the CRLF closing the previous chunk (if any) and the next size line.
+                    let skip = if trailing_crlf { 2 } else { 0 };
+                    let line_end = self.buffer.get(skip..).and_then(|rest| rest.windows(2).position(|w| w == b"\r\n"));
+                    let Some(line_end) = line_end else {
+                        if !self.fill().await? {
+                            return Err("Connection closed in the middle of a chunk".into());
+                        }
+                        continue;
+                    };
+                    let line = String::from_utf8_lossy(&self.buffer[skip..skip + line_end]).to_string();
+                    self.buffer.drain(..skip + line_end + 2);
+
+                    let size = usize::from_str_radix(line.split(';').next().unwrap_or_default().trim(), 16)?;
+                    self.framing = if size == 0 {
+                        Framing::Done
+                    } else {
+                        Framing::Chunked { remaining: size, trailing_crlf: true }
+                    };
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reads a whole response the way the raw transport does, from a socket that closes once the
+    // server wrote it.
+    async fn read(response: &str) -> Result<(u16, Vec<u8>), TransportError> {
+        let stream = Box::new(std::io::Cursor::new(response.as_bytes().to_vec()));
+        let mut body = RawBody { stream, buffer: Vec::new(), framing: Framing::UntilClose };
+        let (status, headers) = body.read_head().await?;
+        body.framing = Framing::from_headers("GET", status, &headers);
+        let mut content = Vec::new();
+        while let Some(chunk) = body.chunk().await? {
+            content.extend_from_slice(&chunk);
+        }
+        Ok((status.as_u16(), content))
+    }
+
+    #[tokio::test]
+    async fn content_length() {
+        let (status, body) = read("HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello, the rest is ignored").await.unwrap();
+        assert_eq!((status, body.as_slice()), (200, &b"hello"[..]));
+    }
+
+    #[tokio::test]
+    async fn short_body_fails() {
+        assert!(read("HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn chunked_with_extensions_and_trailers() {
+        let response = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+            5;name=value\r\nhello\r\n1 ; other\r\n,\r\n6\r\n world\r\n0\r\nX-Trailer: yes\r\n\r\n";
+        let (status, body) = read(response).await.unwrap();
+        assert_eq!((status, body.as_slice()), (200, &b"hello, world"[..]));
+    }
+
+    #[tokio::test]
+    async fn truncated_chunk_fails() {
+        assert!(read("HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\na\r\nhello").await.is_err());
+        assert!(read("HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n").await.is_err());
+        assert!(read("HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\nhello\r\n0\r\n\r\n").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn interim_responses_are_skipped() {
+        let response = "HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 103 Early Hints\r\nLink: </a.css>\r\n\r\n\
+            HTTP/1.1 201 Created\r\nContent-Length: 2\r\n\r\nok";
+        let (status, body) = read(response).await.unwrap();
+        assert_eq!((status, body.as_slice()), (201, &b"ok"[..]));
+    }
+}
@@ -0,0 +1,83 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io;
+
+use crate::output::ResultSink;
+
+/// The reported results laid out as the directories and files of every host, for --tree.
+#[derive(Default)]
+pub struct Tree {
+    hosts: BTreeMap<String, Node>,
+}
+
+#[derive(Default)]
+struct Node {
+    // Statuses of the results for this exact path, a path can be reported more than once with
+    // other headers or bodies.
+    statuses: Vec<u64>,
+    children: BTreeMap<String, Node>,
+}
+
+impl Tree {
+    fn insert(&mut self, url: &str, status: u64) {
+        let Ok(url) = reqwest::Url::parse(url) else { return };
+        let mut node = self.hosts.entry(url.origin().ascii_serialization()).or_default();
+        let mut segments: Vec<String> = url.path().split('/').filter(|segment| !segment.is_empty()).map(String::from).collect();
+        if let (Some(query), Some(last)) = (url.query(), segments.last_mut()) {
+            last.push('?');
+            last.push_str(query);
+        }
+        for segment in segments {
+            node = node.children.entry(segment).or_default();
+        }
+        if !node.statuses.contains(&status) {
+            node.statuses.push(status);
+        }
+    }
+
+    pub fn print(&self) {
+        for (host, root) in &self.hosts {
+            println!("{}{}", host, statuses(root));
+            print_children(root, "");
+        }
+    }
+}
+
+fn print_children(node: &Node, indent: &str) {
+    let count = node.children.len();
+    for (index, (segment, child)) in node.children.iter().enumerate() {
+        let last = index + 1 == count;
+        let slash = if child.children.is_empty() { "" } else { "/" };
+        println!("{}{}{}{}{}", indent, if last { "└── " } else { "├── " }, segment, slash, statuses(child));
+        print_children(child, &format!("{}{}", indent, if last { "    " } else { "│   " }));
+    }
+}
+
+// Directories only found on the way to what was reported have no status of their own.
+fn statuses(node: &Node) -> String {
+    if node.statuses.is_empty() {
+        return String::new();
+    }
+    let statuses: Vec<String> = node.statuses.iter().map(u64::to_string).collect();
+    format!(" [{}]", statuses.join(", "))
+}
+
+impl ResultSink for Tree {
+    fn name(&self) -> String {
+        String::from("--tree")
+    }
+
+    fn write(&mut self, record: &Value) -> io::Result<()> {
+        if let (Some(url), Some(status)) = (record["sent_url"].as_str(), record["status"].as_u64()) {
+            self.insert(url, status);
+        }
+        Ok(())
+    }
+
+    // The tree is printed once every result is in.
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        println!("Results by path:");
+        self.print();
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use crate::payloads::Payload;
+use crate::template::Template;
+
+// Characters that can't appear as is in a URL. A payload containing any of these would make the
+// URL fail to parse.
+const UNSAFE: [char; 12] = [' ', '"', '\'', '<', '>', '\\', '^', '`', '{', '|', '}', '\u{7f}'];
+
+// Replaces every delimited part of the URL with the payload's word (or the placeholder's default
+// when there is no payload). Unless auto encoding was turned off, characters of the word that
+// would make the URL invalid are percent-encoded. Everything else, including '%', is left alone so
+// that payloads can carry their own encoding.
+pub fn render_url(target: &Template, payload: Option<&Payload>, encode: bool) -> String {
+    if !target.has_placeholders() {
+        return target.text().to_string();
+    }
+    if encode {
+        target.render_with(payload, |value| encode_unsafe(&value))
+    } else {
+        target.render(payload)
+    }
+}
+
+pub fn encode_unsafe(word: &str) -> String {
+    let mut encoded = String::new();
+    for c in word.chars() {
+        if c.is_ascii_control() || UNSAFE.contains(&c) || !c.is_ascii() {
+            let mut buffer = [0; 4];
+            for byte in c.encode_utf8(&mut buffer).bytes() {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        } else {
+            encoded.push(c);
+        }
+    }
+    encoded
+}
@@ -0,0 +1,182 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::payloads::{self, Payload};
+use crate::template::Template;
+use crate::{encrypt, placeholders, proxies, rate, session, socks, transport, wordlist, Args};
+
+// Exit code of a run refused because of its options, the same one clap uses.
+pub const EXIT_USAGE: i32 = 2;
+
+// Methods a run can use.
+const HTTP_METHODS: [&str; 9] = ["GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS", "CONNECT", "TRACE"];
+
+/// Something wrong with the options of a run, found before anything was sent. Reported as a
+/// plain message rather than a panic.
+#[derive(Debug)]
+pub struct UsageError(String);
+
+impl UsageError {
+    pub fn new(message: impl Into<String>) -> UsageError {
+        UsageError(message.into())
+    }
+
+    // Prints the error and ends the program, as a JSON line in --ci mode.
+    pub fn exit(&self, ci: bool) -> ! {
+        if ci {
+            eprintln!("{}", serde_json::json!({ "event": "error", "message": self.0 }));
+        } else {
+            eprintln!("error: {}", self.0);
+        }
+        std::process::exit(EXIT_USAGE);
+    }
+}
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// Options that don't go together or can't work, checked before anything else happens.
+pub fn check_options(args: &Args) -> Result<(), UsageError> {
+    if !HTTP_METHODS.contains(&args.method.as_str()) {
+        return Err(UsageError::new(format!("Method {} is not valid, use one of {}", args.method, HTTP_METHODS.join(", "))));
+    }
+    if args.allowredirects && args.transport != transport::TransportKind::Reqwest {
+        return Err(UsageError::new("Only the reqwest transport can follow redirects"));
+    }
+    if (!args.rotate_proxy.is_empty() || args.proxy_list.is_some()) && args.transport != transport::TransportKind::Reqwest {
+        return Err(UsageError::new("Only the reqwest transport can go through a proxy"));
+    }
+    if let Some(proxy) = &args.proxy {
+        match args.transport {
+            transport::TransportKind::Raw if !socks::is_socks(proxy) => {
+                return Err(UsageError::new("The raw transport only goes through SOCKS5 proxies"));
+            }
+            transport::TransportKind::Reqwest if socks::is_socks(proxy) => {
+                return Err(UsageError::new("SOCKS proxies are only supported by the raw transport, add --transport raw"));
+            }
+            transport::TransportKind::Hyper => return Err(UsageError::new("The hyper transport can't go through a proxy")),
+            transport::TransportKind::Raw => {
+                socks::Socks5Proxy::parse(proxy).map_err(|e| UsageError::new(format!("Invalid proxy {}: {}", proxy, e)))?;
+            }
+            transport::TransportKind::Reqwest => {
+                proxies::check(proxy).map_err(|e| UsageError::new(format!("Invalid proxy {}: {}", proxy, e)))?;
+            }
+        }
+    }
+    for proxy in &args.rotate_proxy {
+        proxies::check(proxy).map_err(|e| UsageError::new(format!("Invalid proxy {}: {}", proxy, e)))?;
+    }
+    if args.concurrency == Some(0) {
+        return Err(UsageError::new("--concurrency needs to be at least 1"));
+    }
+    if args.rate.is_some_and(|rate| rate <= 0.0) {
+        return Err(UsageError::new("--rate needs to be more than 0"));
+    }
+    if let Some(spec) = &args.rate_coordination {
+        rate::coordination_file(spec).map_err(UsageError::new)?;
+    }
+    if args.rps.is_some_and(|rps| rps <= 0.0) {
+        return Err(UsageError::new("--rps needs to be more than 0"));
+    }
+    if args.repeat == Some(0) {
+        return Err(UsageError::new("--repeat needs to be at least 1"));
+    }
+    if args.stream_match && args.match_string.is_empty() && args.match_regex.is_empty() {
+        return Err(UsageError::new("--stream-match needs --match-string or --match-regex"));
+    }
+    if args.matrix && args.url_file.is_none() && args.context.is_empty() {
+        return Err(UsageError::new("--matrix needs several targets with --url-file, or several --context"));
+    }
+    if let Some(name) = &args.session {
+        session::check_name(name).map_err(UsageError::new)?;
+    }
+    if args.external_sort {
+        check_program("sort", "--external-sort")?;
+    }
+    if args.output_db.is_some() {
+        check_program("sqlite3", "--output-db")?;
+    }
+    if let Some(encryption) = &args.encrypt_output {
+        check_program(encryption.program(), "--encrypt-output")?;
+        if let encrypt::Encryption::Age(recipients) = encryption {
+            if !Path::new(recipients).is_file() {
+                return Err(UsageError::new(format!("Recipients file {} doesn't exist", recipients)));
+            }
+        }
+    }
+    for (index, context) in args.context.iter().enumerate() {
+        if args.context[..index].iter().any(|other| other.name == context.name) {
+            return Err(UsageError::new(format!("Two contexts are named {}", context.name)));
+        }
+    }
+    Ok(())
+}
+
+// Some options work through another program. Without it they would only fail once the run
+// started, after the first results were sent.
+fn check_program(program: &str, option: &str) -> Result<(), UsageError> {
+    let found = std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|dir| {
+            let path = dir.join(program);
+            path.is_file() || (cfg!(windows) && path.with_extension("exe").is_file())
+        })
+    });
+    if found {
+        Ok(())
+    } else {
+        Err(UsageError::new(format!("{} needs the {} program, which isn't on the PATH", option, program)))
+    }
+}
+
+// Wordlists are only read once the run starts, a missing one is caught here instead.
+pub fn check_wordlists(lists: &[(Option<String>, wordlist::Words)]) -> Result<(), UsageError> {
+    for (index, (name, _)) in lists.iter().enumerate() {
+        if lists[..index].iter().any(|(other, _)| other == name) {
+            return Err(UsageError::new(match name {
+                Some(name) => format!("Two wordlists are named {}", name),
+                None => String::from("Only one wordlist can be left without a name"),
+            }));
+        }
+    }
+    for (_, words) in lists {
+        if let wordlist::Words::File { path, .. } = words {
+            if !Path::new(path).is_file() {
+                return Err(UsageError::new(format!("Wordlist {} doesn't exist", path)));
+            }
+        }
+    }
+    let stdin_lists = lists.iter().filter(|(_, words)| matches!(words, wordlist::Words::Stdin { .. })).count();
+    if stdin_lists > 1 {
+        return Err(UsageError::new("Only one wordlist can be read from stdin with -l -"));
+    }
+    Ok(())
+}
+
+// Every placeholder of the URLs, headers and body needs a word to go in it: a default in
+// --defaults and --benchmark modes, else a wordlist of its name.
+pub fn check_placeholders<'a>(args: &Args, texts: impl Iterator<Item = &'a Template>, sample: Option<&Payload>) -> Result<(), UsageError> {
+    for template in texts {
+        match sample {
+            _ if args.fills_defaults() => placeholders::check_defaults(template)?,
+            Some(sample) => payloads::check_placeholders(template, sample)?,
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+// Delimiters come in pairs, one on each side of where the word goes.
+pub fn check_pairs(template: &Template, what: &str) -> Result<(), UsageError> {
+    if template.delimiters().is_multiple_of(2) {
+        return Ok(());
+    }
+    Err(UsageError::new(format!(
+        "Delimiters need to be set in pairs, the {} \"{}\" has {} of them",
+        what,
+        template.text(),
+        template.delimiters()
+    )))
+}
@@ -0,0 +1,97 @@
+use clap::ValueEnum;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::results::RequestResult;
+use crate::score::Score;
+
+// Deliveries in flight at the same time. Results past this wait for their turn.
+const MAX_CONCURRENT_DELIVERIES: usize = 8;
+const MAX_ATTEMPTS: u32 = 3;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WebhookFilter {
+    /// Only results that are reported, after body matchers, outlier filtering and --top.
+    Matched,
+    /// Every response, whether it is reported or not.
+    All,
+}
+
+/// Posts results as JSON to an external URL, one request per result.
+pub struct Webhook {
+    url: String,
+    pub filter: WebhookFilter,
+    tags: Vec<(String, String)>,
+    client: reqwest::Client,
+    permits: Arc<Semaphore>,
+    deliveries: JoinSet<()>,
+}
+
+impl Webhook {
+    pub fn new(url: &str, filter: WebhookFilter, tags: &[(String, String)]) -> Result<Webhook, String> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| format!("Could not set up the webhook client: {}", e))?;
+        Ok(Webhook {
+            url: url.to_string(),
+            filter,
+            tags: tags.to_vec(),
+            client,
+            permits: Arc::new(Semaphore::new(MAX_CONCURRENT_DELIVERIES)),
+            deliveries: JoinSet::new(),
+        })
+    }
+
+    // Waits for a delivery slot before handing the result over, so that a slow endpoint holds up
+    // the caller instead of piling up deliveries.
+    pub async fn send(&mut self, result: &RequestResult, score: Option<&Score>) {
+        let payload = result.to_json(score, &self.tags);
+
+        // The semaphore is never closed.
+        let Ok(permit) = Arc::clone(&self.permits).acquire_owned().await else { return };
+        let client = self.client.clone();
+        let url = self.url.clone();
+        self.deliveries.spawn(async move {
+            deliver(&client, &url, &payload).await;
+            drop(permit);
+        });
+        // Finished deliveries are reaped as we go so the set doesn't grow with the run.
+        while self.deliveries.try_join_next().is_some() {}
+    }
+
+    // Waits for the deliveries that are still in flight.
+    pub async fn finish(mut self) {
+        while self.deliveries.join_next().await.is_some() {}
+    }
+}
+
+// Retries on connection errors, 5xx and 429 with a growing delay. Other responses are final.
+async fn deliver(client: &reqwest::Client, url: &str, payload: &serde_json::Value) {
+    let mut delay = Duration::from_millis(500);
+    for attempt in 1..=MAX_ATTEMPTS {
+        let request = client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(payload.to_string());
+        let error = match request.send().await {
+            Ok(response) if response.status().is_success() => return,
+            Ok(response) if response.status().is_server_error() || response.status().as_u16() == 429 => {
+                format!("status {}", response.status())
+            }
+            Ok(response) => {
+                eprintln!("Webhook rejected result for word {}: status {}", payload["word"], response.status());
+                return;
+            }
+            Err(error) => error.to_string(),
+        };
+        if attempt == MAX_ATTEMPTS {
+            eprintln!("Webhook delivery failed for word {} after {} attempts: {}", payload["word"], attempt, error);
+            return;
+        }
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+    }
+}
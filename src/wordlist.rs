@@ -0,0 +1,417 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::brute::Brute;
+use crate::compress::{GzipDecoder, ZstdDecoder};
+use crate::rules::Rules;
+use crate::Args;
+
+// Words read ahead of the run when a wordlist is streamed from disk.
+const STREAM_BUFFER: usize = 4096;
+
+/// Encodings the wordlist file can be read as.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListEncoding {
+    Utf8,
+    Latin1,
+    Utf16le,
+    Auto,
+}
+
+// Reads all words from a file and pushes them to the Vec in Arc Mutex. Allows for easier access
+// later in the program.
+pub fn load_words_to_memory(filename: &str, encoding: ListEncoding, wordlist: Arc<Mutex<Vec<String>>>) -> Result<(), String> {
+    let mut vec = wordlist.lock().unwrap();
+    for_each_word(filename, encoding, |word| {
+        vec.push(word);
+        true
+    })
+}
+
+/// A wordlist, either already in memory, still on disk or still coming through stdin ("-l -").
+/// Cleaned and Mangled hold the words of another wordlist going through --trim, --skip-empty and
+/// --unique, or through --rules.
+pub enum Words {
+    Memory(Vec<String>),
+    File { path: String, encoding: ListEncoding },
+    Stdin { encoding: ListEncoding },
+    Brute(Brute),
+    Cleaned { words: Box<Words>, cleanup: Cleanup },
+    Mangled { words: Box<Words>, rules: Arc<Rules> },
+}
+
+impl Words {
+    // Fails when the file can't be read or has a line the encoding can't decode.
+    pub fn load(self) -> Result<Vec<String>, String> {
+        Ok(match self {
+            Words::Memory(words) => words,
+            Words::File { path, encoding } => {
+                let mut words = Vec::new();
+                for_each_word(&path, encoding, |word| {
+                    words.push(word);
+                    true
+                })?;
+                words
+            }
+            Words::Stdin { encoding } => {
+                let mut words = Vec::new();
+                read_words(std::io::stdin().lock(), encoding, |word| {
+                    words.push(word);
+                    true
+                })?;
+                words
+            }
+            Words::Brute(brute) => brute.words().collect(),
+            Words::Cleaned { words, cleanup } if cleanup.external_sort => Words::Cleaned { words, cleanup }.stream()?.collect(),
+            Words::Cleaned { words, cleanup } => {
+                words.load()?.into_iter().filter_map(cleanup.filter()).collect()
+            }
+            Words::Mangled { words, rules } => words.load()?.iter().flat_map(|word| rules.apply(word)).collect(),
+        })
+    }
+
+    // Goes through the words a single time. A file is counted first and then read again by a
+    // thread feeding a bounded channel, so memory stays the same whatever the size of the file,
+    // even through --rules and the cleanup options. Stdin can't be read twice, it is read to its
+    // end before the run can know how many words there are. Whatever is wrong with the file shows
+    // up while counting, before the run starts.
+    pub fn stream(self) -> Result<WordStream, String> {
+        Ok(match self {
+            Words::Memory(words) => WordStream { len: words.len(), words: Box::new(words.into_iter()) },
+            Words::Brute(brute) => WordStream { len: brute.count().unwrap_or(usize::MAX), words: Box::new(brute.words()) },
+            // The words of brute force are all different already, and have no whitespace to trim
+            // unless the charset asks for it.
+            Words::Cleaned { words, .. } if matches!(*words, Words::Brute(_)) => words.stream()?,
+            Words::Cleaned { words, cleanup } if cleanup.external_sort => {
+                let path = sort_unique(*words, cleanup)?;
+                let mut len = 0;
+                for_each_word(&path, ListEncoding::Utf8, |_| {
+                    len += 1;
+                    true
+                })?;
+                let (sender, receiver) = mpsc::sync_channel(STREAM_BUFFER);
+                thread::spawn(move || {
+                    if let Err(e) = for_each_word(&path, ListEncoding::Utf8, |word| sender.send(word).is_ok()) {
+                        eprintln!("{}", e);
+                    }
+                    let _ = std::fs::remove_file(&path);
+                });
+                WordStream { len, words: Box::new(receiver.into_iter()) }
+            }
+            words if words.replayable() => {
+                let mut len = 0;
+                words.replay(&mut |_| {
+                    len += 1;
+                    true
+                })?;
+                let (sender, receiver) = mpsc::sync_channel(STREAM_BUFFER);
+                // Stops reading as soon as nobody is taking words anymore. The file was read fine
+                // a moment ago, it failing now only cuts the run short.
+                thread::spawn(move || {
+                    if let Err(e) = words.replay(&mut |word| sender.send(word).is_ok()) {
+                        eprintln!("{}", e);
+                    }
+                });
+                WordStream { len, words: Box::new(receiver.into_iter()) }
+            }
+            words => Words::Memory(words.load()?).stream()?,
+        })
+    }
+
+    // Whether the words can be gone through twice without holding them in memory: a file, on its
+    // own or through --rules and the cleanup options.
+    fn replayable(&self) -> bool {
+        match self {
+            Words::File { .. } => true,
+            Words::Cleaned { words, cleanup } => !cleanup.external_sort && words.replayable(),
+            Words::Mangled { words, .. } => words.replayable(),
+            _ => false,
+        }
+    }
+
+    // Calls f with every word until it returns false, reading the file again every time. Rules can
+    // reject words or make the same candidate twice, so counting goes through them too.
+    fn replay(&self, f: &mut dyn FnMut(String) -> bool) -> Result<(), String> {
+        match self {
+            Words::File { path, encoding } => for_each_word(path, *encoding, f),
+            Words::Cleaned { words, cleanup } => {
+                let mut keep = cleanup.filter();
+                words.replay(&mut |word| keep(word).is_none_or(&mut *f))
+            }
+            Words::Mangled { words, rules } => words.replay(&mut |word| rules.apply(&word).into_iter().all(&mut *f)),
+            _ => unreachable!("only wordlists on disk can be replayed"),
+        }
+    }
+}
+
+/// What --trim, --skip-empty and --unique do to the words of every wordlist as they are read.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cleanup {
+    pub trim: bool,
+    pub skip_empty: bool,
+    pub unique: bool,
+    // Dedupes through the sort program and a temporary file instead of remembering every word.
+    pub external_sort: bool,
+}
+
+impl Cleanup {
+    pub fn from_args(args: &Args) -> Cleanup {
+        Cleanup { trim: args.trim, skip_empty: args.skip_empty, unique: args.unique, external_sort: args.external_sort }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.trim || self.skip_empty || self.unique
+    }
+
+    // The word as it should be sent, or None when it should be left out. Every filter remembers
+    // the words it already let through for --unique, one is made for every pass over a list.
+    fn filter(self) -> impl FnMut(String) -> Option<String> {
+        let mut seen = HashSet::new();
+        move |word| {
+            let word = if self.trim { word.trim().to_string() } else { word };
+            if self.skip_empty && word.is_empty() {
+                return None;
+            }
+            if self.unique && !self.external_sort && !seen.insert(word.clone()) {
+                return None;
+            }
+            Some(word)
+        }
+    }
+}
+
+// Temporary files of --external-sort in this process, so that several wordlists don't share one.
+static SORTED_LISTS: AtomicUsize = AtomicUsize::new(0);
+
+// Writes the words, trimmed and without the empty ones as asked, through "sort -u" into a
+// temporary file. The C locale makes sort compare bytes, other locales can take two different
+// words for the same one.
+fn sort_unique(words: Words, cleanup: Cleanup) -> Result<String, String> {
+    let index = SORTED_LISTS.fetch_add(1, Ordering::Relaxed);
+    let path = std::env::temp_dir().join(format!("httprepeater-unique-{}-{}", std::process::id(), index));
+    let mut child = Command::new("sort")
+        .arg("-u")
+        .arg("-o")
+        .arg(&path)
+        .env("LC_ALL", "C")
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Could not start sort to dedupe the wordlist: {}", e))?;
+    let mut stdin = BufWriter::new(child.stdin.take().unwrap());
+    let mut write = |word: String| writeln!(stdin, "{}", word).is_ok();
+    let cleanup = Cleanup { unique: false, ..cleanup };
+    match words {
+        Words::Stdin { encoding } => {
+            let mut keep = cleanup.filter();
+            read_words(std::io::stdin().lock(), encoding, |word| keep(word).is_none_or(&mut write))?;
+        }
+        words if words.replayable() => Words::Cleaned { words: Box::new(words), cleanup }.replay(&mut write)?,
+        words => {
+            for word in (Words::Cleaned { words: Box::new(words), cleanup }).load()? {
+                if !write(word) {
+                    break;
+                }
+            }
+        }
+    }
+    let flushed = stdin.into_inner().map(drop);
+    let status = child.wait().map_err(|e| format!("Could not wait for sort: {}", e))?;
+    if flushed.is_err() || !status.success() {
+        return Err(format!("sort could not dedupe the wordlist, it exited with {}", status));
+    }
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Words of a wordlist, handed out one at a time.
+pub struct WordStream {
+    pub len: usize,
+    words: Box<dyn Iterator<Item = String> + Send>,
+}
+
+impl Iterator for WordStream {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        self.words.next()
+    }
+}
+
+// Calls f with every word of the file in order, reading it a piece at a time, until f returns
+// false. For byte oriented encodings the split happens on the raw bytes, so in auto mode a single
+// badly encoded line falls back to latin1 on its own instead of taking the rest of the file down
+// with it.
+fn for_each_word(filename: &str, encoding: ListEncoding, f: impl FnMut(String) -> bool) -> Result<(), String> {
+    read_words(open(filename)?, encoding, f)
+}
+
+// Opens a wordlist file, decompressing it on the fly when it is gzip or zstd compressed. The
+// magic bytes tell, whatever the extension.
+fn open(filename: &str) -> Result<Box<dyn BufRead>, String> {
+    let unreadable = |e: std::io::Error| format!("Could not read the wordlist {}: {}", filename, e);
+    let mut reader = BufReader::new(File::open(filename).map_err(unreadable)?);
+    let magic = reader.fill_buf().map_err(unreadable)?;
+    if magic.starts_with(&[0x1f, 0x8b]) {
+        return Ok(Box::new(BufReader::new(GzipDecoder::new(reader))));
+    }
+    if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        return Ok(Box::new(BufReader::new(ZstdDecoder::new(reader))));
+    }
+    Ok(Box::new(reader))
+}
+
+fn read_words(mut reader: impl BufRead, encoding: ListEncoding, mut f: impl FnMut(String) -> bool) -> Result<(), String> {
+    let unreadable = |e: std::io::Error| format!("Could not read the wordlist: {}", e);
+    let encoding = match encoding {
+        ListEncoding::Auto if looks_like_utf16le(reader.fill_buf().map_err(unreadable)?) => ListEncoding::Utf16le,
+        other => other,
+    };
+
+    let mut index = 0;
+    loop {
+        let line = if encoding == ListEncoding::Utf16le {
+            read_utf16le_line(&mut reader)
+        } else {
+            let mut line = Vec::new();
+            match reader.read_until(b'\n', &mut line).map_err(unreadable)? {
+                0 => None,
+                _ => Some(decode_line(&line, encoding, index)?),
+            }
+        };
+        let Some(mut line) = line else { return Ok(()) };
+        // The line break itself doesn't belong to the word.
+        if line.ends_with('\n') {
+            line.pop();
+        }
+        if !f(clean_line(line, index == 0)) {
+            return Ok(());
+        }
+        index += 1;
+    }
+}
+
+fn decode_line(line: &[u8], encoding: ListEncoding, index: usize) -> Result<String, String> {
+    match encoding {
+        ListEncoding::Latin1 => Ok(decode_latin1(line)),
+        ListEncoding::Auto => Ok(String::from_utf8(line.to_vec()).unwrap_or_else(|_| decode_latin1(line))),
+        _ => String::from_utf8(line.to_vec())
+            .map_err(|_| format!("Line {} of the wordlist is not valid UTF-8, use --list-encoding", index + 1)),
+    }
+}
+
+// Reads code units up to and including the next line break. None at the end of the file.
+fn read_utf16le_line(reader: &mut impl Read) -> Option<String> {
+    let mut units = Vec::new();
+    let mut pair = [0; 2];
+    while reader.read_exact(&mut pair).is_ok() {
+        let unit = u16::from_le_bytes(pair);
+        units.push(unit);
+        if unit == u16::from(b'\n') {
+            break;
+        }
+    }
+    if units.is_empty() {
+        return None;
+    }
+    Some(
+        char::decode_utf16(units)
+            .map(|unit| unit.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect(),
+    )
+}
+
+// UTF-16 files almost always start with a BOM. When they don't, plain ASCII content still gives
+// them away through the zero high bytes of every other position.
+fn looks_like_utf16le(bytes: &[u8]) -> bool {
+    if bytes.starts_with(&[0xFF, 0xFE]) {
+        return true;
+    }
+    let sample = &bytes[..bytes.len().min(512)];
+    if sample.len() < 2 {
+        return false;
+    }
+    let zeros = sample.iter().skip(1).step_by(2).filter(|byte| **byte == 0).count();
+    zeros * 2 > sample.len() / 2
+}
+
+// Latin1 maps every byte directly to the unicode code point of the same value.
+fn decode_latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| *byte as char).collect()
+}
+
+// Reads a whole text file of the command line, such as a rules or hosts file, without the byte
+// order mark Windows editors put at its start. str::lines already takes CRLF endings apart.
+pub fn read_text(path: &str) -> std::io::Result<String> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(match text.strip_prefix('\u{feff}') {
+        Some(text) => text.to_string(),
+        None => text,
+    })
+}
+
+// Wordlists saved on Windows usually come with CRLF line endings and sometimes with a byte order
+// mark at the start of the file. Neither belongs to the word, and leaving them in silently breaks
+// the payload (e.g. "admin\r" never matches anything on the server).
+fn clean_line(mut line: String, first_line: bool) -> String {
+    if first_line && line.starts_with('\u{feff}') {
+        line.remove(0);
+    }
+    while line.ends_with('\r') {
+        line.pop();
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(bytes: &[u8], encoding: ListEncoding) -> Result<Vec<String>, String> {
+        let mut words = Vec::new();
+        read_words(bytes, encoding, |word| {
+            words.push(word);
+            true
+        })?;
+        Ok(words)
+    }
+
+    fn utf16le(text: &str, bom: bool) -> Vec<u8> {
+        let bom = bom.then_some(0xFEFF);
+        bom.into_iter().chain(text.encode_utf16()).flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn utf8() {
+        assert_eq!(words("\u{feff}café\r\nnaïve\n\nlast".as_bytes(), ListEncoding::Utf8).unwrap(), ["café", "naïve", "", "last"]);
+        let error = words(b"fine\ncaf\xe9\n", ListEncoding::Utf8).unwrap_err();
+        assert!(error.contains("Line 2"), "{}", error);
+    }
+
+    #[test]
+    fn latin1() {
+        assert_eq!(words(b"caf\xe9\r\n\xfcber\n", ListEncoding::Latin1).unwrap(), ["café", "über"]);
+    }
+
+    #[test]
+    fn utf16le_with_and_without_bom() {
+        for bom in [true, false] {
+            let bytes = utf16le("admin\r\nlogin\n😀", bom);
+            assert_eq!(words(&bytes, ListEncoding::Utf16le).unwrap(), ["admin", "login", "😀"], "BOM: {}", bom);
+        }
+    }
+
+    // Auto mode tells UTF-16 by its BOM or its zero bytes, and decodes each line that isn't valid
+    // UTF-8 as latin1 on its own.
+    #[test]
+    fn auto() {
+        assert_eq!(words(&utf16le("admin\nlogin", true), ListEncoding::Auto).unwrap(), ["admin", "login"]);
+        assert_eq!(words(&utf16le("admin\nlogin", false), ListEncoding::Auto).unwrap(), ["admin", "login"]);
+        assert_eq!(words(b"caf\xc3\xa9\ncaf\xe9\n", ListEncoding::Auto).unwrap(), ["café", "café"]);
+        assert!(words(b"", ListEncoding::Auto).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use clap::Subcommand;
+
+use crate::session::app_dir;
+
+const SECLISTS: &str = "https://raw.githubusercontent.com/danielmiessler/SecLists/master/";
+
+// Curated wordlists that can be fetched by name: (name, path inside SecLists, description).
+const CATALOG: [(&str, &str, &str); 8] = [
+    ("common-paths", "Discovery/Web-Content/common.txt", "Common files and directories of web servers"),
+    ("raft-small-directories", "Discovery/Web-Content/raft-small-directories.txt", "Directory names seen in the wild"),
+    ("raft-small-files", "Discovery/Web-Content/raft-small-files.txt", "File names seen in the wild"),
+    ("api-endpoints", "Discovery/Web-Content/api/api-endpoints.txt", "REST API endpoint paths"),
+    ("parameters", "Discovery/Web-Content/burp-parameter-names.txt", "Query and body parameter names"),
+    ("subdomains", "Discovery/DNS/subdomains-top1million-5000.txt", "The 5000 most common subdomains"),
+    ("usernames", "Usernames/top-usernames-shortlist.txt", "Short list of common usernames"),
+    ("passwords", "Passwords/Common-Credentials/10k-most-common.txt", "The 10000 most common passwords"),
+];
+
+/// Actions of the wordlists subcommand.
+#[derive(Subcommand, Debug)]
+pub enum WordlistsAction {
+    /// Lists the built-in wordlists and whether they were already fetched.
+    List,
+    /// Downloads built-in wordlists into the local store, after which they can be used as
+    /// "-l @name".
+    Fetch {
+        /// Wordlists to download.
+        #[arg(required_unless_present = "all")]
+        names: Vec<String>,
+
+        /// Downloads every built-in wordlist.
+        #[arg(long, conflicts_with = "names")]
+        all: bool,
+    },
+}
+
+pub fn wordlists_dir() -> io::Result<PathBuf> {
+    Ok(app_dir()?.join("wordlists"))
+}
+
+// Turns the value of --list into a file path. "@name" refers to a built-in wordlist, anything else
+// is used as is.
+pub fn resolve(list: &str) -> Result<String, String> {
+    let Some(name) = list.strip_prefix('@') else {
+        return Ok(list.to_string());
+    };
+    if !CATALOG.iter().any(|(known, _, _)| *known == name) {
+        return Err(format!("Unknown built-in wordlist {}, see \"httprepeater wordlists list\"", name));
+    }
+    let path = wordlists_dir().map_err(|e| e.to_string())?.join(format!("{}.txt", name));
+    if !path.is_file() {
+        return Err(format!("Wordlist {} was not fetched yet, run \"httprepeater wordlists fetch {}\"", name, name));
+    }
+    Ok(path.to_string_lossy().to_string())
+}
+
+pub async fn run_command(action: &WordlistsAction) -> Result<(), String> {
+    let dir = wordlists_dir().map_err(|e| e.to_string())?;
+    match action {
+        WordlistsAction::List => {
+            for (name, _, description) in CATALOG {
+                let path = dir.join(format!("{}.txt", name));
+                let state = match fs::metadata(&path) {
+                    Ok(meta) => format!("fetched, {} KB", meta.len().div_ceil(1024)),
+                    Err(_) => String::from("not fetched"),
+                };
+                println!("@{}\t{}\t{}", name, state, description);
+            }
+        }
+        WordlistsAction::Fetch { names, all } => {
+            let names: Vec<&str> = if *all {
+                CATALOG.iter().map(|(name, _, _)| *name).collect()
+            } else {
+                names.iter().map(String::as_str).collect()
+            };
+            fs::create_dir_all(&dir).map_err(|e| format!("Could not create {}: {}", dir.display(), e))?;
+            for name in names {
+                fetch(name, &dir).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// The download goes to a temporary file first so an interrupted fetch never leaves a truncated
+// wordlist behind under the real name.
+async fn fetch(name: &str, dir: &Path) -> Result<(), String> {
+    let Some((_, path, _)) = CATALOG.iter().find(|(known, _, _)| *known == name) else {
+        return Err(format!("Unknown built-in wordlist {}, see \"httprepeater wordlists list\"", name));
+    };
+    let url = format!("{}{}", SECLISTS, path);
+    println!("Fetching {} from {}", name, url);
+
+    let failed = |e: reqwest::Error| format!("Could not download {}: {}", name, e);
+    let response = reqwest::get(&url).await.and_then(reqwest::Response::error_for_status).map_err(failed)?;
+    let bytes = response.bytes().await.map_err(failed)?;
+
+    let target = dir.join(format!("{}.txt", name));
+    let partial = dir.join(format!("{}.txt.part", name));
+    let written = fs::File::create(&partial)
+        .and_then(|mut file| file.write_all(&bytes).and_then(|_| file.sync_all()))
+        .and_then(|_| fs::rename(&partial, &target));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&partial);
+        return Err(format!("Could not store {} in {}: {}", name, target.display(), e));
+    }
+
+    let words = bytes.split(|byte| *byte == b'\n').filter(|line| !line.is_empty()).count();
+    println!("Stored {} words in {}", words, target.display());
+    Ok(())
+}